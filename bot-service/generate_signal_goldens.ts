@@ -0,0 +1,156 @@
+/**
+ * Golden fixtures for the desktop app's Rust port of the SignalEngine
+ * (src-tauri/src/signals.rs). Feeds seeded candle series through the real
+ * engine and writes candles plus the resulting UnifiedSignal.
+ *
+ * Run after any change to signalEngine.ts and commit the output:
+ *   npx tsx generate_signal_goldens.ts
+ */
+
+import * as fs from 'fs';
+import * as path from 'path';
+import { SignalEngine, type Candle, type Timeframe } from './src/services/signalEngine';
+
+const OUTPUT = path.join(__dirname, '../src-tauri/tests/fixtures/signal_goldens.json');
+
+const TIMEFRAME_MS: Record<Timeframe, number> = {
+  '1m': 60_000,
+  '5m': 300_000,
+  '15m': 900_000,
+  '1h': 3_600_000,
+  '4h': 14_400_000,
+};
+
+// mulberry32, so the fixtures are reproducible
+function random(seed: number): () => number {
+  let state = seed >>> 0;
+  return () => {
+    state = (state + 0x6d2b79f5) >>> 0;
+    let t = state;
+    t = Math.imul(t ^ (t >>> 15), t | 1);
+    t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+    return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+  };
+}
+
+// Random walk with `drift` percent per candle and `noise` percent of spread
+function series(seed: number, timeframe: Timeframe, count: number, start: number, drift: number, noise: number): Candle[] {
+  const next = random(seed);
+  const candles: Candle[] = [];
+  let price = start;
+  for (let i = 0; i < count; i++) {
+    const open = price;
+    const close = open * (1 + (drift + (next() - 0.5) * noise) / 100);
+    const high = Math.max(open, close) * (1 + (next() * noise) / 200);
+    const low = Math.min(open, close) * (1 - (next() * noise) / 200);
+    candles.push({
+      time: 1_700_000_000_000 + i * TIMEFRAME_MS[timeframe],
+      open: round(open),
+      high: round(high),
+      low: round(low),
+      close: round(close),
+      volume: round(100 + next() * 900),
+    });
+    price = close;
+  }
+  return candles;
+}
+
+function round(value: number): number {
+  return Math.round(value * 1e6) / 1e6;
+}
+
+interface Scenario {
+  name: string;
+  symbol: string;
+  candles: Partial<Record<Timeframe, Candle[]>>;
+}
+
+const scenarios: Scenario[] = [
+  {
+    name: 'uptrend',
+    symbol: 'ETHUSDT',
+    candles: {
+      '1m': series(1, '1m', 100, 2000, 0.05, 0.4),
+      '5m': series(2, '5m', 100, 2000, 0.12, 0.8),
+      '15m': series(3, '15m', 100, 2000, 0.2, 1.2),
+      '1h': series(4, '1h', 100, 2000, 0.35, 2.0),
+    },
+  },
+  {
+    name: 'downtrend',
+    symbol: 'BTCUSDT',
+    candles: {
+      '1m': series(5, '1m', 100, 40000, -0.05, 0.4),
+      '5m': series(6, '5m', 100, 40000, -0.12, 0.8),
+      '15m': series(7, '15m', 100, 40000, -0.2, 1.2),
+      '1h': series(8, '1h', 100, 40000, -0.35, 2.0),
+    },
+  },
+  {
+    name: 'conflicting',
+    symbol: 'ARBUSDT',
+    candles: {
+      '1m': series(9, '1m', 100, 1.2, 0.3, 0.6),
+      '5m': series(10, '5m', 100, 1.2, -0.3, 0.6),
+      '15m': series(11, '15m', 100, 1.2, 0.0, 1.5),
+      '1h': series(12, '1h', 100, 1.2, 0.25, 1.0),
+      '4h': series(13, '4h', 100, 1.2, -0.4, 2.5),
+    },
+  },
+  {
+    name: 'sideways',
+    symbol: 'LINKUSDT',
+    candles: {
+      '1m': series(14, '1m', 100, 15, 0, 0.3),
+      '5m': series(15, '5m', 100, 15, 0, 0.6),
+      '15m': series(16, '15m', 100, 15, 0, 1.0),
+      '1h': series(17, '1h', 100, 15, 0, 1.5),
+    },
+  },
+  {
+    name: 'thin_history',
+    symbol: 'SOLUSDT',
+    candles: {
+      '1m': series(18, '1m', 100, 100, 0.1, 0.5),
+      '1h': series(19, '1h', 20, 100, 0.1, 0.5),
+      '4h': [],
+    },
+  },
+];
+
+class FixtureEngine extends SignalEngine {
+  private fixture: Scenario;
+
+  constructor(fixture: Scenario) {
+    super();
+    this.fixture = fixture;
+  }
+
+  async fetchCandles(_symbol: string, timeframe: Timeframe): Promise<Candle[]> {
+    return this.fixture.candles[timeframe] ?? [];
+  }
+}
+
+async function main() {
+  const goldens = [];
+  for (const scenario of scenarios) {
+    const timeframes = Object.keys(scenario.candles) as Timeframe[];
+    const signal = await new FixtureEngine(scenario).generateSignal(scenario.symbol, timeframes);
+    goldens.push({
+      name: scenario.name,
+      symbol: scenario.symbol,
+      timeframes,
+      candles: scenario.candles,
+      // Wall-clock time, not part of the comparison
+      signal: { ...signal, timestamp: 0 },
+    });
+  }
+  fs.writeFileSync(OUTPUT, JSON.stringify(goldens, null, 2) + '\n');
+  console.log(`Wrote ${goldens.length} goldens to ${OUTPUT}`);
+}
+
+main().catch((error) => {
+  console.error(error);
+  process.exit(1);
+});
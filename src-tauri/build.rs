@@ -1,3 +1,41 @@
+use std::collections::HashMap;
+
+// Same resolution as config::load, so a build that would refuse to start
+// fails here instead
+fn check_backend_profile() {
+  for var in ["MONADIER_PROFILE", "MONADIER_SUPABASE_URL", "MONADIER_SUPABASE_ANON_KEY"] {
+    println!("cargo:rerun-if-env-changed={}", var);
+  }
+  println!("cargo:rerun-if-changed=config/backend.json");
+
+  let release = std::env::var("PROFILE").as_deref() == Ok("release");
+  let profile = std::env::var("MONADIER_PROFILE")
+    .unwrap_or_else(|_| if release { "prod" } else { "dev" }.to_string());
+
+  let profiles: HashMap<String, serde_json::Value> =
+    serde_json::from_str(include_str!("config/backend.json")).expect("invalid config/backend.json");
+  let Some(config) = profiles.get(&profile) else {
+    panic!("unknown backend profile '{}' in MONADIER_PROFILE", profile);
+  };
+
+  let value = |key: &str, var: &str| {
+    config[key]
+      .as_str()
+      .filter(|value| !value.is_empty())
+      .map(String::from)
+      .or_else(|| std::env::var(var).ok().filter(|value| !value.is_empty()))
+  };
+  if value("supabase_url", "MONADIER_SUPABASE_URL").is_none()
+    || value("anon_key", "MONADIER_SUPABASE_ANON_KEY").is_none()
+  {
+    panic!(
+      "backend profile '{}' has no Supabase URL or anon key, set MONADIER_SUPABASE_URL and MONADIER_SUPABASE_ANON_KEY",
+      profile
+    );
+  }
+}
+
 fn main() {
+  check_backend_profile();
   tauri_build::build()
 }
@@ -0,0 +1,591 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Listener, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::error::{AppError, AppResult};
+use crate::gmx_monitor;
+use crate::journal;
+use crate::license;
+use crate::paper;
+use crate::price_stream::{PriceTick, TICK_EVENT};
+use crate::settings;
+use crate::signals::{self, SignalDirection, UnifiedSignal};
+
+const RULES_KEY: &str = "alert_rules";
+
+pub const FIRED_EVENT: &str = "alerts://fired";
+
+// Prices, positions and the license are checked this often
+const EVALUATE_INTERVAL: Duration = Duration::from_secs(30);
+// Signals take a candle fetch per timeframe, so less often
+const SIGNAL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// Longest lead time a license expiry rule may ask for
+const MAX_EXPIRY_DAYS: i64 = 365;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Crossing {
+    Above,
+    Below,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    // The price moves through `price`, once per crossing
+    PriceCross {
+        symbol: String,
+        price: f64,
+        crossing: Crossing,
+    },
+    // Any LONG or SHORT signal when `direction` is None
+    SignalConfidence {
+        symbol: String,
+        min_confidence: f64,
+        direction: Option<SignalDirection>,
+    },
+    // Open vault or paper position under this PnL, in percent of margin
+    PositionPnl {
+        symbol: Option<String>,
+        below_percent: f64,
+    },
+    LicenseExpiry {
+        within_days: i64,
+    },
+}
+
+// User-defined rule, persisted in the settings store
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertRule {
+    // Assigned on first save
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub condition: AlertCondition,
+}
+
+impl AlertRule {
+    fn validate(&self) -> AppResult<()> {
+        let invalid = |message: &str| Err(AppError::InvalidInput(message.to_string()));
+        match &self.condition {
+            AlertCondition::PriceCross { symbol, price, .. } => {
+                if symbol.trim().is_empty() || !(price.is_finite() && *price > 0.0) {
+                    return invalid("A price alert needs a symbol and a positive price");
+                }
+            }
+            AlertCondition::SignalConfidence {
+                symbol,
+                min_confidence,
+                ..
+            } => {
+                if symbol.trim().is_empty() || !(0.0..=100.0).contains(min_confidence) {
+                    return invalid("A signal alert needs a symbol and a confidence of 0-100");
+                }
+            }
+            AlertCondition::PositionPnl { below_percent, .. } => {
+                if !below_percent.is_finite() {
+                    return invalid("A PnL alert needs a percent");
+                }
+            }
+            AlertCondition::LicenseExpiry { within_days } => {
+                if !(0..=MAX_EXPIRY_DAYS).contains(within_days) {
+                    return invalid("Days before expiry must be 0-365");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn title(&self) -> &str {
+        if self.name.trim().is_empty() {
+            "Monadier alert"
+        } else {
+            &self.name
+        }
+    }
+}
+
+// Edge state of the rules, registered as Tauri state
+#[derive(Default)]
+pub struct AlertState {
+    // The stored rules, loaded once and replaced on every save. Price ticks
+    // check them several times a second.
+    rules: Mutex<Option<Vec<AlertRule>>>,
+    // "<rule id>:<subject>" for every condition currently holding
+    raised: Mutex<HashSet<String>>,
+    // Previous price per symbol, to spot crossings
+    last_prices: Mutex<HashMap<String, f64>>,
+}
+
+impl AlertState {
+    // Messages of the conditions that just started holding. Conditions that
+    // stopped holding are dropped so they can fire again.
+    fn rising(&self, rule_id: &str, holding: Vec<(String, String)>) -> Vec<String> {
+        let prefix = format!("{}:", rule_id);
+        let keys: Vec<String> = holding
+            .iter()
+            .map(|(subject, _)| format!("{}{}", prefix, subject))
+            .collect();
+
+        let mut raised = self.raised.lock().unwrap();
+        raised.retain(|key| !key.starts_with(&prefix) || keys.contains(key));
+        keys.into_iter()
+            .zip(holding)
+            .filter_map(|(key, (_, message))| raised.insert(key).then_some(message))
+            .collect()
+    }
+
+    fn forget(&self, rule_id: &str) {
+        let prefix = format!("{}:", rule_id);
+        self.raised
+            .lock()
+            .unwrap()
+            .retain(|key| !key.starts_with(&prefix));
+    }
+}
+
+pub fn rules(app: &tauri::AppHandle) -> Vec<AlertRule> {
+    app.state::<AlertState>()
+        .rules
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| settings::load(app, RULES_KEY))
+        .clone()
+}
+
+fn save_rules(app: &tauri::AppHandle, rules: &[AlertRule]) -> AppResult<()> {
+    settings::save(app, RULES_KEY, rules)?;
+    *app.state::<AlertState>().rules.lock().unwrap() = Some(rules.to_vec());
+    Ok(())
+}
+
+// Add a rule, or replace the one with the same id
+pub fn save_rule(app: &tauri::AppHandle, mut rule: AlertRule) -> AppResult<AlertRule> {
+    rule.validate()?;
+    let mut rules = rules(app);
+    if rule.id.is_empty() {
+        rule.id = format!(
+            "alert-{}-{:08x}",
+            Utc::now().timestamp_millis(),
+            rand::random::<u32>()
+        );
+    }
+
+    match rules.iter_mut().find(|r| r.id == rule.id) {
+        Some(existing) => *existing = rule.clone(),
+        None => rules.push(rule.clone()),
+    }
+    save_rules(app, &rules)?;
+    // An edited condition starts from scratch
+    app.state::<AlertState>().forget(&rule.id);
+    Ok(rule)
+}
+
+pub fn delete_rule(app: &tauri::AppHandle, id: &str) -> AppResult<()> {
+    let mut rules = rules(app);
+    let before = rules.len();
+    rules.retain(|r| r.id != id);
+    if rules.len() == before {
+        return Err(AppError::NotFound(format!("alert rule {}", id)));
+    }
+    save_rules(app, &rules)?;
+    app.state::<AlertState>().forget(id);
+    Ok(())
+}
+
+fn enabled_rules(app: &tauri::AppHandle) -> Vec<AlertRule> {
+    rules(app).into_iter().filter(|r| r.enabled).collect()
+}
+
+// Deliver one alert: journal it for the in-app log, push it to the webview
+// and show a native notification
+pub fn notify(app: &tauri::AppHandle, rule_id: Option<&str>, title: &str, message: &str) {
+    let event = journal::record_alert(app, rule_id, title, message);
+    if let Err(e) = app.emit(FIRED_EVENT, &event) {
+        log::error!("Failed to emit {}: {}", FIRED_EVENT, e);
+    }
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(title)
+        .body(message)
+        .show()
+    {
+        log::warn!("Failed to show notification {}: {}", title, e);
+    }
+}
+
+fn fire(app: &tauri::AppHandle, rule: &AlertRule, holding: Vec<(String, String)>) {
+    for message in app.state::<AlertState>().rising(&rule.id, holding) {
+        log::info!("Alert {} fired: {}", rule.id, message);
+        notify(app, Some(&rule.id), rule.title(), &message);
+    }
+}
+
+// Whether the move from `previous` to `price` went through `level`
+fn crossed(crossing: Crossing, level: f64, previous: f64, price: f64) -> bool {
+    match crossing {
+        Crossing::Above => previous < level && price >= level,
+        Crossing::Below => previous > level && price <= level,
+    }
+}
+
+// Fire price rules whose level lies between the previous and this price
+pub fn on_price(app: &tauri::AppHandle, symbol: &str, price: f64) {
+    let previous = app
+        .state::<AlertState>()
+        .last_prices
+        .lock()
+        .unwrap()
+        .insert(symbol.to_uppercase(), price);
+    let Some(previous) = previous else {
+        return;
+    };
+
+    for rule in enabled_rules(app) {
+        let AlertCondition::PriceCross {
+            symbol: rule_symbol,
+            price: level,
+            crossing,
+        } = &rule.condition
+        else {
+            continue;
+        };
+        if !rule_symbol.eq_ignore_ascii_case(symbol) {
+            continue;
+        }
+
+        if crossed(*crossing, *level, previous, price) {
+            let message = format!(
+                "{} crossed {} {} at {}",
+                symbol,
+                if *crossing == Crossing::Above {
+                    "above"
+                } else {
+                    "below"
+                },
+                level,
+                price
+            );
+            log::info!("Alert {} fired: {}", rule.id, message);
+            notify(app, Some(&rule.id), rule.title(), &message);
+        }
+    }
+}
+
+// Check signal rules against a freshly generated signal
+pub fn on_signal(app: &tauri::AppHandle, signal: &UnifiedSignal) {
+    for rule in enabled_rules(app) {
+        let AlertCondition::SignalConfidence {
+            symbol,
+            min_confidence,
+            direction,
+        } = &rule.condition
+        else {
+            continue;
+        };
+        if !symbol.eq_ignore_ascii_case(&signal.symbol) {
+            continue;
+        }
+
+        let matches = match direction {
+            Some(direction) => signal.direction == *direction,
+            None => signal.direction != SignalDirection::Hold,
+        };
+        let mut holding = Vec::new();
+        if matches && signal.confidence >= *min_confidence {
+            holding.push((
+                String::new(),
+                format!(
+                    "{} {:?} signal at {:.0}% confidence",
+                    signal.symbol, signal.direction, signal.confidence
+                ),
+            ));
+        }
+        fire(app, &rule, holding);
+    }
+}
+
+// Open positions as (subject, symbol, description, PnL percent)
+async fn open_positions(app: &tauri::AppHandle) -> Vec<(String, String, String, f64)> {
+    let mut open: Vec<_> = gmx_monitor::positions(app)
+        .into_iter()
+        .map(|p| {
+            let side = if p.is_long { "long" } else { "short" };
+            (
+                format!("{}-{}-{}", p.user, p.token, side),
+                p.token_symbol.clone(),
+                format!("Vault {} {}", p.token_symbol, side),
+                p.pnl_percent,
+            )
+        })
+        .collect();
+
+    let paper = match paper::positions(app, false) {
+        Ok(paper) => paper,
+        Err(e) => {
+            log::warn!("Alerts could not load paper positions: {}", e);
+            return open;
+        }
+    };
+    for position in paper {
+        let price = match paper::current_price(app, &position.token_symbol).await {
+            Ok(price) => price,
+            Err(e) => {
+                log::warn!("No price for {}: {}", position.token_symbol, e);
+                continue;
+            }
+        };
+        open.push((
+            position.id.clone(),
+            position.token_symbol.clone(),
+            format!(
+                "Paper {} {:?}",
+                position.token_symbol, position.exits.direction
+            ),
+            position.unrealized(price) / position.entry_amount * 100.0,
+        ));
+    }
+    open
+}
+
+async fn check_positions(app: &tauri::AppHandle, rules: &[AlertRule]) {
+    let pnl_rules: Vec<_> = rules
+        .iter()
+        .filter(|r| matches!(r.condition, AlertCondition::PositionPnl { .. }))
+        .collect();
+    if pnl_rules.is_empty() {
+        return;
+    }
+
+    let open = open_positions(app).await;
+    for rule in pnl_rules {
+        let AlertCondition::PositionPnl {
+            symbol,
+            below_percent,
+        } = &rule.condition
+        else {
+            continue;
+        };
+        let holding = open
+            .iter()
+            .filter(|(_, position_symbol, _, pnl)| {
+                symbol
+                    .as_ref()
+                    .is_none_or(|s| s.eq_ignore_ascii_case(position_symbol))
+                    && *pnl < *below_percent
+            })
+            .map(|(subject, _, description, pnl)| {
+                (
+                    subject.clone(),
+                    format!("{} is at {:.2}% PnL", description, pnl),
+                )
+            })
+            .collect();
+        fire(app, rule, holding);
+    }
+}
+
+fn check_license(app: &tauri::AppHandle, rules: &[AlertRule]) {
+    let expires_at = match license::load(app) {
+        Ok(Some(stored)) => stored.token.expires_at(),
+        _ => return,
+    };
+    let left = expires_at - Utc::now();
+
+    for rule in rules {
+        let AlertCondition::LicenseExpiry { within_days } = &rule.condition else {
+            continue;
+        };
+        // validate() bounds the days, a hand-edited store may not
+        let Some(window) = chrono::Duration::try_days(*within_days) else {
+            continue;
+        };
+        let mut holding = Vec::new();
+        if left <= window {
+            let message = if left <= chrono::Duration::zero() {
+                "Your license has expired".to_string()
+            } else {
+                format!(
+                    "Your license expires in {} days, on {}",
+                    left.num_days(),
+                    expires_at.format("%Y-%m-%d")
+                )
+            };
+            holding.push((String::new(), message));
+        }
+        fire(app, rule, holding);
+    }
+}
+
+// Symbols nobody streams still need their crossings checked
+async fn poll_prices(app: &tauri::AppHandle, rules: &[AlertRule]) {
+    let mut symbols: Vec<String> = rules
+        .iter()
+        .filter_map(|r| match &r.condition {
+            AlertCondition::PriceCross { symbol, .. } => Some(symbol.to_uppercase()),
+            _ => None,
+        })
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+
+    for symbol in symbols {
+        match paper::current_price(app, &symbol).await {
+            Ok(price) => on_price(app, &symbol, price),
+            Err(e) => log::warn!("Alerts could not price {}: {}", symbol, e),
+        }
+    }
+}
+
+async fn check_signals(app: &tauri::AppHandle, rules: &[AlertRule]) {
+    let mut symbols: Vec<String> = rules
+        .iter()
+        .filter_map(|r| match &r.condition {
+            AlertCondition::SignalConfidence { symbol, .. } => Some(symbol.to_uppercase()),
+            _ => None,
+        })
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+
+    for symbol in symbols {
+        match signals::generate_signal(app, &symbol, &signals::DEFAULT_TIMEFRAMES).await {
+            Ok(signal) => on_signal(app, &signal),
+            Err(e) => log::warn!("Alerts could not generate a {} signal: {}", symbol, e),
+        }
+    }
+}
+
+// Evaluate the rules forever, started from run()'s setup hook
+pub async fn run(app: tauri::AppHandle) {
+    let listener = app.clone();
+    app.listen_any(TICK_EVENT, move |event| {
+        if let Ok(tick) = serde_json::from_str::<PriceTick>(event.payload()) {
+            on_price(&listener, &tick.symbol, tick.price);
+        }
+    });
+
+    let mut signals_checked: Option<Instant> = None;
+    loop {
+        tokio::time::sleep(EVALUATE_INTERVAL).await;
+
+        let rules = enabled_rules(&app);
+        if rules.is_empty() {
+            continue;
+        }
+        poll_prices(&app, &rules).await;
+        check_positions(&app, &rules).await;
+        check_license(&app, &rules);
+
+        if signals_checked.is_none_or(|at| at.elapsed() >= SIGNAL_INTERVAL) {
+            signals_checked = Some(Instant::now());
+            check_signals(&app, &rules).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(condition: AlertCondition) -> AlertRule {
+        AlertRule {
+            id: "alert-1".to_string(),
+            name: String::new(),
+            enabled: true,
+            condition,
+        }
+    }
+
+    fn holding(subjects: &[&str]) -> Vec<(String, String)> {
+        subjects
+            .iter()
+            .map(|s| (s.to_string(), format!("{} holds", s)))
+            .collect()
+    }
+
+    #[test]
+    fn rising_fires_once_until_the_condition_clears() {
+        let state = AlertState::default();
+        assert_eq!(state.rising("a", holding(&["x"])), ["x holds"]);
+        assert!(state.rising("a", holding(&["x"])).is_empty());
+        assert_eq!(state.rising("a", holding(&["x", "y"])), ["y holds"]);
+
+        // x cleared, so it fires again when it comes back
+        assert!(state.rising("a", holding(&["y"])).is_empty());
+        assert_eq!(state.rising("a", holding(&["x", "y"])), ["x holds"]);
+    }
+
+    #[test]
+    fn rising_keeps_rules_apart() {
+        let state = AlertState::default();
+        assert_eq!(state.rising("a", holding(&[""])), [" holds"]);
+        assert_eq!(state.rising("b", holding(&[""])), [" holds"]);
+        // Clearing b leaves a raised
+        assert!(state.rising("b", Vec::new()).is_empty());
+        assert!(state.rising("a", holding(&[""])).is_empty());
+
+        state.forget("a");
+        assert_eq!(state.rising("a", holding(&[""])), [" holds"]);
+    }
+
+    #[test]
+    fn crossings_need_the_level_between_the_prices() {
+        assert!(crossed(Crossing::Above, 100.0, 99.0, 100.0));
+        assert!(crossed(Crossing::Above, 100.0, 99.0, 101.0));
+        assert!(!crossed(Crossing::Above, 100.0, 100.0, 101.0));
+        assert!(!crossed(Crossing::Above, 100.0, 101.0, 99.0));
+
+        assert!(crossed(Crossing::Below, 100.0, 101.0, 100.0));
+        assert!(!crossed(Crossing::Below, 100.0, 100.0, 99.0));
+        assert!(!crossed(Crossing::Below, 100.0, 99.0, 101.0));
+    }
+
+    #[test]
+    fn validates_each_condition() {
+        let price = |price| AlertCondition::PriceCross {
+            symbol: "ETH".to_string(),
+            price,
+            crossing: Crossing::Above,
+        };
+        assert!(rule(price(3000.0)).validate().is_ok());
+        assert!(rule(price(0.0)).validate().is_err());
+        assert!(rule(price(f64::NAN)).validate().is_err());
+        assert!(rule(AlertCondition::PriceCross {
+            symbol: " ".to_string(),
+            price: 3000.0,
+            crossing: Crossing::Below,
+        })
+        .validate()
+        .is_err());
+
+        let confidence = |min_confidence| AlertCondition::SignalConfidence {
+            symbol: "BTC".to_string(),
+            min_confidence,
+            direction: None,
+        };
+        assert!(rule(confidence(70.0)).validate().is_ok());
+        assert!(rule(confidence(101.0)).validate().is_err());
+        assert!(rule(confidence(f64::NAN)).validate().is_err());
+
+        let pnl = |below_percent| AlertCondition::PositionPnl {
+            symbol: None,
+            below_percent,
+        };
+        assert!(rule(pnl(-20.0)).validate().is_ok());
+        assert!(rule(pnl(f64::NAN)).validate().is_err());
+        assert!(rule(pnl(f64::NEG_INFINITY)).validate().is_err());
+
+        let expiry = |within_days| AlertCondition::LicenseExpiry { within_days };
+        assert!(rule(expiry(0)).validate().is_ok());
+        assert!(rule(expiry(MAX_EXPIRY_DAYS)).validate().is_ok());
+        assert!(rule(expiry(-1)).validate().is_err());
+        assert!(rule(expiry(i64::MAX)).validate().is_err());
+    }
+}
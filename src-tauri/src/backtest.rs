@@ -0,0 +1,570 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::error::{AppError, AppResult};
+use crate::market_data::{self, Candle, Timeframe};
+use crate::risk::exits::{self, Direction, ExitReason, ExitRules, Position};
+use crate::signals::{self, SignalDirection};
+
+// Same as TRADE_FEE_PERCENT in src/lib/fees.ts, charged on every fill
+pub const TRADE_FEE_PERCENT: f64 = 0.5;
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 86_400.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BacktestConfig {
+    pub initial_balance: f64,
+    // Share of equity committed as margin per trade
+    pub position_size_percent: f64,
+    pub leverage: f64,
+    pub slippage_percent: f64,
+    pub fee_percent: f64,
+    // Minimum UnifiedSignal confidence to open a trade
+    pub min_confidence: f64,
+    pub allow_short: bool,
+    // Candles fed to the signal engine at each step
+    pub window: usize,
+    #[serde(flatten)]
+    pub exits: ExitRules,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            initial_balance: 1_000.0,
+            position_size_percent: 10.0,
+            leverage: 1.0,
+            slippage_percent: 0.1,
+            fee_percent: TRADE_FEE_PERCENT,
+            min_confidence: 60.0,
+            allow_short: true,
+            window: 100,
+            exits: ExitRules::default(),
+        }
+    }
+}
+
+impl BacktestConfig {
+    fn validate(&self) -> AppResult<()> {
+        let invalid = |message: &str| Err(AppError::InvalidInput(message.to_string()));
+        if self.initial_balance <= 0.0 {
+            return invalid("Initial balance must be positive");
+        }
+        if self.position_size_percent <= 0.0 || self.position_size_percent > 100.0 {
+            return invalid("Position size must be between 0 and 100%");
+        }
+        if self.leverage < 1.0 {
+            return invalid("Leverage must be at least 1");
+        }
+        if self.slippage_percent < 0.0 || self.fee_percent < 0.0 {
+            return invalid("Slippage and fees cannot be negative");
+        }
+        if self.window < 30 {
+            return invalid("Signal window must be at least 30 candles");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BacktestTrade {
+    pub direction: SignalDirection,
+    pub entry_time: i64,
+    pub entry_price: f64,
+    pub exit_time: i64,
+    pub exit_price: f64,
+    pub exit_reason: ExitReason,
+    pub confidence: f64,
+    pub margin: f64,
+    pub notional: f64,
+    pub fees: f64,
+    // Net of fees
+    pub pnl: f64,
+    // Net pnl against the margin
+    pub pnl_percent: f64,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct EquityPoint {
+    pub time: i64,
+    pub equity: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BacktestReport {
+    pub config: BacktestConfig,
+    pub timeframe: Timeframe,
+    pub candles: usize,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub final_equity: f64,
+    pub total_return_percent: f64,
+    pub total_fees: f64,
+    pub trades: Vec<BacktestTrade>,
+    pub win_rate: f64,
+    pub max_drawdown_percent: f64,
+    // Annualized from per-candle returns, zero risk-free rate
+    pub sharpe_ratio: f64,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+struct OpenTrade {
+    position: Position,
+    entry_time: i64,
+    confidence: f64,
+    margin: f64,
+    notional: f64,
+    quantity: f64,
+    entry_fee: f64,
+}
+
+impl OpenTrade {
+    fn unrealized(&self, price: f64) -> f64 {
+        self.position.pnl(price, self.quantity)
+    }
+}
+
+// Intra-candle price path: up candles are assumed to dip first, down
+// candles to spike first
+fn price_path(candle: &Candle) -> [f64; 4] {
+    if candle.close >= candle.open {
+        [candle.open, candle.low, candle.high, candle.close]
+    } else {
+        [candle.open, candle.high, candle.low, candle.close]
+    }
+}
+
+struct Simulation<'a> {
+    config: &'a BacktestConfig,
+    balance: f64,
+    total_fees: f64,
+    open: Option<OpenTrade>,
+    trades: Vec<BacktestTrade>,
+}
+
+impl Simulation<'_> {
+    fn enter(&mut self, long: bool, candle: &Candle, confidence: f64) {
+        let margin = self.balance * self.config.position_size_percent / 100.0;
+        let notional = margin * self.config.leverage;
+        let entry_price = exits::slip(candle.close, self.config.slippage_percent, long);
+        let entry_fee = notional * self.config.fee_percent / 100.0;
+        if margin <= entry_fee {
+            return;
+        }
+
+        let direction = if long {
+            Direction::Long
+        } else {
+            Direction::Short
+        };
+        self.open = Some(OpenTrade {
+            position: Position::open(
+                direction,
+                entry_price,
+                self.config.leverage,
+                &self.config.exits,
+            ),
+            entry_time: candle.time,
+            confidence,
+            margin,
+            notional,
+            quantity: notional / entry_price,
+            entry_fee,
+        });
+    }
+
+    fn exit(&mut self, time: i64, level: f64, reason: ExitReason) {
+        let Some(trade) = self.open.take() else {
+            return;
+        };
+
+        let (exit_price, pnl, exit_fee) = if reason == ExitReason::Liquidation {
+            // The whole margin is gone, nothing left to pay a fee from
+            (level, -trade.margin, 0.0)
+        } else {
+            let exit_price = exits::slip(
+                level,
+                self.config.slippage_percent,
+                !trade.position.is_long(),
+            );
+            let exit_fee = trade.quantity * exit_price * self.config.fee_percent / 100.0;
+            (exit_price, trade.unrealized(exit_price), exit_fee)
+        };
+
+        let fees = trade.entry_fee + exit_fee;
+        let net = pnl - fees;
+        self.balance += net;
+        self.total_fees += fees;
+        self.trades.push(BacktestTrade {
+            direction: if trade.position.is_long() {
+                SignalDirection::Long
+            } else {
+                SignalDirection::Short
+            },
+            entry_time: trade.entry_time,
+            entry_price: trade.position.entry_price,
+            exit_time: time,
+            exit_price,
+            exit_reason: reason,
+            confidence: trade.confidence,
+            margin: trade.margin,
+            notional: trade.notional,
+            fees,
+            pnl: net,
+            pnl_percent: net / trade.margin * 100.0,
+        });
+    }
+
+    // Walk the candle's price path through the exit rules
+    fn manage(&mut self, candle: &Candle) {
+        let Some(trade) = self.open.as_mut() else {
+            return;
+        };
+        let path = price_path(candle);
+        if let Some((step, decision)) = trade.position.evaluate(path) {
+            // A gap through a take profit or stop fills at the open
+            let fill = if step == 0 && decision.reason != ExitReason::Liquidation {
+                path[0]
+            } else {
+                decision.level
+            };
+            self.exit(candle.time, fill, decision.reason);
+        }
+    }
+
+    fn equity(&self, price: f64) -> f64 {
+        self.balance + self.open.as_ref().map_or(0.0, |t| t.unrealized(price))
+    }
+}
+
+fn max_drawdown_percent(curve: &[EquityPoint]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut max_drawdown: f64 = 0.0;
+    for point in curve {
+        peak = peak.max(point.equity);
+        if peak > 0.0 {
+            max_drawdown = max_drawdown.max((peak - point.equity) / peak * 100.0);
+        }
+    }
+    max_drawdown
+}
+
+fn sharpe_ratio(curve: &[EquityPoint], period_secs: f64) -> f64 {
+    let returns: Vec<f64> = curve
+        .windows(2)
+        .filter(|w| w[0].equity > 0.0)
+        .map(|w| w[1].equity / w[0].equity - 1.0)
+        .collect();
+    if returns.len() < 2 || period_secs <= 0.0 {
+        return 0.0;
+    }
+
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let std_dev = variance.sqrt();
+    if std_dev == 0.0 {
+        return 0.0;
+    }
+    mean / std_dev * (SECONDS_PER_YEAR / period_secs).sqrt()
+}
+
+// Median spacing between candles, in seconds
+fn candle_spacing(candles: &[Candle]) -> f64 {
+    let mut gaps: Vec<i64> = candles.windows(2).map(|w| w[1].time - w[0].time).collect();
+    gaps.sort_unstable();
+    gaps.get(gaps.len() / 2)
+        .map_or(0.0, |gap| *gap as f64 / 1_000.0)
+}
+
+fn closest_timeframe(spacing_secs: f64) -> Timeframe {
+    [
+        Timeframe::M1,
+        Timeframe::M5,
+        Timeframe::M15,
+        Timeframe::H1,
+        Timeframe::H4,
+        Timeframe::D1,
+    ]
+    .into_iter()
+    .min_by(|a, b| {
+        let distance = |t: &Timeframe| (t.seconds() as f64 - spacing_secs).abs();
+        distance(a).total_cmp(&distance(b))
+    })
+    .unwrap_or(Timeframe::H1)
+}
+
+// Replay `candles`, oldest first, through the signal engine and exit rules
+pub fn run(
+    candles: &[Candle],
+    timeframe: Option<Timeframe>,
+    config: &BacktestConfig,
+) -> AppResult<BacktestReport> {
+    config.validate()?;
+    if candles.len() <= config.window {
+        return Err(AppError::InvalidInput(format!(
+            "Need more than {} candles, got {}",
+            config.window,
+            candles.len()
+        )));
+    }
+
+    let spacing = candle_spacing(candles);
+    let timeframe = timeframe.unwrap_or_else(|| closest_timeframe(spacing));
+
+    let mut sim = Simulation {
+        config,
+        balance: config.initial_balance,
+        total_fees: 0.0,
+        open: None,
+        trades: Vec::new(),
+    };
+    let mut equity_curve = Vec::with_capacity(candles.len() - config.window);
+
+    for i in config.window..candles.len() {
+        let candle = &candles[i];
+        sim.manage(candle);
+
+        if sim.open.is_none() && sim.balance > 0.0 {
+            let window = &candles[i + 1 - config.window..=i];
+            let analysis = signals::analyze_timeframe(timeframe, window);
+            let signal = signals::combine("", vec![analysis], candle.time);
+
+            if signal.confidence >= config.min_confidence {
+                match signal.direction {
+                    SignalDirection::Long => sim.enter(true, candle, signal.confidence),
+                    SignalDirection::Short if config.allow_short => {
+                        sim.enter(false, candle, signal.confidence)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        equity_curve.push(EquityPoint {
+            time: candle.time,
+            equity: sim.equity(candle.close),
+        });
+    }
+
+    let last = &candles[candles.len() - 1];
+    sim.exit(last.time, last.close, ExitReason::EndOfData);
+    if let Some(point) = equity_curve.last_mut() {
+        point.equity = sim.balance;
+    }
+
+    let wins = sim.trades.iter().filter(|t| t.pnl > 0.0).count();
+    let win_rate = if sim.trades.is_empty() {
+        0.0
+    } else {
+        wins as f64 / sim.trades.len() as f64 * 100.0
+    };
+
+    Ok(BacktestReport {
+        config: config.clone(),
+        timeframe,
+        candles: candles.len(),
+        start_time: candles[0].time,
+        end_time: last.time,
+        final_equity: sim.balance,
+        total_return_percent: (sim.balance / config.initial_balance - 1.0) * 100.0,
+        total_fees: sim.total_fees,
+        win_rate,
+        max_drawdown_percent: max_drawdown_percent(&equity_curve),
+        sharpe_ratio: sharpe_ratio(&equity_curve, spacing),
+        trades: sim.trades,
+        equity_curve,
+    })
+}
+
+// time,open,high,low,close[,volume] with an optional header row
+fn parse_csv(text: &str) -> AppResult<Vec<Candle>> {
+    let mut candles = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let invalid = || AppError::InvalidInput(format!("Invalid candle on line {}", line_no + 1));
+        let number = |i: usize| fields.get(i).and_then(|f| f.parse::<f64>().ok());
+
+        let Some(time) = number(0) else {
+            if candles.is_empty() {
+                continue;
+            }
+            return Err(invalid());
+        };
+        candles.push(Candle {
+            time: time as i64,
+            open: number(1).ok_or_else(invalid)?,
+            high: number(2).ok_or_else(invalid)?,
+            low: number(3).ok_or_else(invalid)?,
+            close: number(4).ok_or_else(invalid)?,
+            volume: number(5).unwrap_or(0.0),
+        });
+    }
+    Ok(candles)
+}
+
+// Either Candle objects or exchange-style [time, open, high, low, close, volume] rows
+fn parse_json(text: &str) -> AppResult<Vec<Candle>> {
+    let value: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid candle JSON: {}", e)))?;
+
+    if value.get(0).is_some_and(serde_json::Value::is_object) {
+        return serde_json::from_value(value)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid candle JSON: {}", e)));
+    }
+    market_data::parse_binance(&value)
+        .map_err(|_| AppError::InvalidInput("Unrecognized candle JSON format".to_string()))
+}
+
+pub fn load_candles(path: &Path) -> AppResult<Vec<Candle>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| AppError::NotFound(format!("{}: {}", path.display(), e)))?;
+
+    let is_json = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or_else(|| text.trim_start().starts_with('['));
+    let mut candles = if is_json {
+        parse_json(&text)?
+    } else {
+        parse_csv(&text)?
+    };
+
+    // Seconds are common in exported files, the engine works in milliseconds
+    if candles.first().is_some_and(|c| c.time < 100_000_000_000) {
+        for candle in &mut candles {
+            candle.time *= 1_000;
+        }
+    }
+
+    candles.sort_by_key(|c| c.time);
+    candles.dedup_by_key(|c| c.time);
+    Ok(candles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 100 USDC margin at 2x, 1% slippage and 0.5% fees per fill
+    fn config() -> BacktestConfig {
+        BacktestConfig {
+            initial_balance: 1_000.0,
+            position_size_percent: 10.0,
+            leverage: 2.0,
+            slippage_percent: 1.0,
+            fee_percent: 0.5,
+            ..BacktestConfig::default()
+        }
+    }
+
+    fn round_trip(
+        config: &BacktestConfig,
+        long: bool,
+        exit: f64,
+        reason: ExitReason,
+    ) -> BacktestTrade {
+        let mut sim = Simulation {
+            config,
+            balance: config.initial_balance,
+            total_fees: 0.0,
+            open: None,
+            trades: Vec::new(),
+        };
+        let candle = Candle {
+            time: 0,
+            open: 100.0,
+            high: 100.0,
+            low: 100.0,
+            close: 100.0,
+            volume: 0.0,
+        };
+        sim.enter(long, &candle, 80.0);
+        sim.exit(1, exit, reason);
+
+        let trade = sim.trades.pop().unwrap();
+        assert_close(sim.balance, config.initial_balance + trade.pnl);
+        assert_close(sim.total_fees, trade.fees);
+        trade
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    fn curve(equities: &[f64]) -> Vec<EquityPoint> {
+        equities
+            .iter()
+            .enumerate()
+            .map(|(i, equity)| EquityPoint {
+                time: i as i64,
+                equity: *equity,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn long_pays_slippage_and_fees_on_both_fills() {
+        // Buys 200/101 at 101, sells at 108.9, fees 1 + 108.9/101
+        let trade = round_trip(&config(), true, 110.0, ExitReason::EndOfData);
+        assert_close(trade.entry_price, 101.0);
+        assert_close(trade.exit_price, 108.9);
+        assert_close(trade.fees, 209.9 / 101.0);
+        assert_close(trade.pnl, 1370.1 / 101.0);
+        assert_close(trade.pnl_percent, 1370.1 / 101.0);
+    }
+
+    #[test]
+    fn short_pays_slippage_and_fees_on_both_fills() {
+        // Sells 200/99 at 99, buys back at 90.9, fees 1 + 90.9/99
+        let trade = round_trip(&config(), false, 90.0, ExitReason::TakeProfit);
+        assert_close(trade.entry_price, 99.0);
+        assert_close(trade.exit_price, 90.9);
+        assert_close(trade.fees, 189.9 / 99.0);
+        assert_close(trade.pnl, 1430.1 / 99.0);
+    }
+
+    #[test]
+    fn liquidation_loses_the_margin_and_entry_fee() {
+        let trade = round_trip(&config(), true, 50.0, ExitReason::Liquidation);
+        assert_close(trade.exit_price, 50.0);
+        assert_close(trade.fees, 1.0);
+        assert_close(trade.pnl, -101.0);
+    }
+
+    #[test]
+    fn max_drawdown_is_from_the_running_peak() {
+        // 120 down to 60, the later high does not undo it
+        assert_close(
+            max_drawdown_percent(&curve(&[100.0, 120.0, 90.0, 110.0, 60.0, 130.0])),
+            50.0,
+        );
+        assert_close(max_drawdown_percent(&curve(&[100.0, 110.0, 120.0])), 0.0);
+        assert_close(max_drawdown_percent(&[]), 0.0);
+    }
+
+    #[test]
+    fn sharpe_is_annualized_from_sample_deviation() {
+        // Returns +10%, -10%, +10%: mean 1/30, sample deviation 1/sqrt(75),
+        // so sqrt(1/12) a period and 1 over twelve periods a year
+        let monthly = SECONDS_PER_YEAR / 12.0;
+        assert_close(
+            sharpe_ratio(&curve(&[100.0, 110.0, 99.0, 108.9]), monthly),
+            1.0,
+        );
+        assert_close(sharpe_ratio(&curve(&[100.0, 100.0, 100.0]), monthly), 0.0);
+        assert_close(sharpe_ratio(&curve(&[100.0, 110.0]), monthly), 0.0);
+    }
+}
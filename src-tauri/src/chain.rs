@@ -0,0 +1,980 @@
+use alloy::primitives::utils::format_units;
+use alloy::primitives::{address, Address, Bytes, B256, U256, U64};
+use alloy::sol_types::SolCall;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::error::{AppError, AppResult};
+use crate::http::HttpClient;
+use crate::settings;
+use crate::vaults::{self, VaultVersion};
+
+const CHAIN_SETTINGS_KEY: &str = "chain";
+
+// vault.ts: VAULT_ADDRESS, VAULT_CHAIN_ID, USDC_ADDRESSES and TOKEN_ADDRESSES
+pub const ARBITRUM_CHAIN_ID: u64 = 42161;
+const DEFAULT_RPC_URL: &str = "https://arb1.arbitrum.io/rpc";
+pub const VAULT_ADDRESS: Address = address!("7dE97f35887b2623dCad2ebA68197f58F7607854");
+const USDC_ADDRESS: Address = address!("af88d065e77c8cC2239327C5EDb3A432268e5831");
+const WETH_ADDRESS: Address = address!("82aF49447D8a07e3bd95BD0d56f35241523fBab1");
+const WBTC_ADDRESS: Address = address!("2f2a2543B76A4166549F7aaB2e75Bef0aefC5B0f");
+// GMX v1 vault the positions live in
+const GMX_VAULT_ADDRESS: Address = address!("489ee077994B6658eAfA855C308275EAd8097C4A");
+
+pub const USDC_DECIMALS: u8 = 6;
+// GMX prices and USD sizes
+pub const PRICE_DECIMALS: u8 = 30;
+
+// getUserStatus assumes 5% when the user never picked a risk level
+const DEFAULT_RISK_BPS: u64 = 500;
+const BPS: u64 = 10_000;
+
+// JSON-RPC error code geth and anvil use for reverts
+const EXECUTION_REVERTED: i64 = 3;
+// Node errors that another node would repeat
+const FINAL_ERRORS: &[&str] = &[
+    "revert",
+    "nonce too low",
+    "insufficient funds",
+    "already known",
+    "replacement transaction underpriced",
+    "max fee per gas less than block base fee",
+];
+
+// Generated bindings, the lints fire on macro output
+#[allow(clippy::too_many_arguments)]
+pub mod abi {
+    use alloy::sol;
+
+    sol! {
+        struct Settings {
+            bool autoTradeEnabled;
+            uint256 riskBps;
+            uint256 maxLeverage;
+            uint256 stopLossBps;
+            uint256 takeProfitBps;
+        }
+
+        struct Position {
+            bool isActive;
+            bool isLong;
+            address token;
+            uint256 collateral;
+            uint256 size;
+            uint256 leverage;
+            uint256 entryPrice;
+            uint256 stopLoss;
+            uint256 takeProfit;
+            uint256 timestamp;
+            bytes32 requestKey;
+            uint256 highestPrice;
+            uint256 lowestPrice;
+            uint256 trailingSlBps;
+            bool trailingActivated;
+            bool autoFeaturesEnabled;
+        }
+
+        interface IVault {
+            function balances(address user) external view returns (uint256);
+            function getSettings(address user) external view returns (Settings memory);
+            function getPosition(address user, address token) external view returns (Position memory);
+            function getWithdrawable(address user) external view returns (uint256);
+            function getExecutionFee() external view returns (uint256);
+            function reconcile(address user, address token) external;
+            function getHealthStatus() external view returns (
+                uint256 realBalance,
+                uint256 totalValueLocked,
+                bool isSolvent,
+                int256 surplus
+            );
+
+            function deposit(uint256 amount) external;
+            function withdraw(uint256 amount) external;
+            function userInstantClose(address token) external payable;
+            function emergencyWithdraw() external;
+            function setAutoTrade(bool enabled) external;
+            function setSettings(
+                uint256 riskBps,
+                uint256 maxLeverage,
+                uint256 stopLossBps,
+                uint256 takeProfitBps
+            ) external;
+
+            // V8 and later
+            event Deposit(address indexed user, uint256 amount);
+            event Withdraw(address indexed user, uint256 amount);
+            event EmergencyWithdraw(address indexed user, uint256 amount, uint256 requested);
+            event PositionOpened(
+                address indexed user,
+                address indexed token,
+                bool isLong,
+                uint256 collateral,
+                uint256 leverage
+            );
+            event PositionClosed(address indexed user, address indexed token, int256 pnl, string reason);
+            event PositionCancelled(address indexed user, address indexed token, uint256 refundAmount);
+            event UserInstantClose(
+                address indexed user,
+                address indexed token,
+                uint256 returnAmount,
+                int256 pnl
+            );
+            event PositionReconciled(
+                address indexed user,
+                address indexed token,
+                uint256 creditedAmount,
+                address reconciledBy
+            );
+            event AdminCredited(address indexed user, uint256 amount);
+        }
+
+        interface IVaultV7 {
+            event Deposited(address indexed user, uint256 amount, uint256 newBalance);
+            event Withdrawn(address indexed user, uint256 amount, uint256 newBalance);
+            event PositionRequested(
+                address indexed user,
+                address indexed indexToken,
+                bool isLong,
+                uint256 collateral,
+                uint256 sizeDelta,
+                uint256 leverage,
+                bytes32 requestKey
+            );
+            event PositionClosed(
+                address indexed user,
+                address indexed indexToken,
+                bool wasLong,
+                uint256 entryPrice,
+                uint256 exitPrice,
+                int256 pnl,
+                uint256 fee,
+                string reason
+            );
+        }
+
+        interface IGmxVault {
+            function getPosition(
+                address account,
+                address collateralToken,
+                address indexToken,
+                bool isLong
+            ) external view returns (
+                uint256 size,
+                uint256 collateral,
+                uint256 averagePrice,
+                uint256 entryFundingRate,
+                uint256 reserveAmount,
+                int256 realisedPnl,
+                uint256 lastIncreasedTime
+            );
+            function getMinPrice(address token) external view returns (uint256);
+            function getMaxPrice(address token) external view returns (uint256);
+            function cumulativeFundingRates(address token) external view returns (uint256);
+            function marginFeeBasisPoints() external view returns (uint256);
+            function liquidationFeeUsd() external view returns (uint256);
+            function maxLeverage() external view returns (uint256);
+        }
+
+        interface IERC20 {
+            function balanceOf(address owner) external view returns (uint256);
+            function allowance(address owner, address spender) external view returns (uint256);
+            function approve(address spender, uint256 amount) external returns (bool);
+
+            event Transfer(address indexed from, address indexed to, uint256 value);
+        }
+    }
+}
+
+use abi::{IVault, IERC20};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VaultToken {
+    pub symbol: String,
+    pub address: Address,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegacyVault {
+    pub label: String,
+    pub address: Address,
+    pub version: VaultVersion,
+}
+
+// Where vault reads go, persisted in settings.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ChainSettings {
+    // Tried in order, the next one takes over when a node fails. A local
+    // anvil fork or a recorded-response server works as long as it reports
+    // the same chain id.
+    pub rpc_urls: Vec<String>,
+    pub chain_id: u64,
+    pub vault_address: Address,
+    pub usdc_address: Address,
+    // Tokens the vault can hold positions in
+    pub tokens: Vec<VaultToken>,
+    pub gmx_vault_address: Address,
+    // Older deployments users may still have balances in
+    pub legacy_vaults: Vec<LegacyVault>,
+}
+
+impl Default for ChainSettings {
+    fn default() -> Self {
+        Self {
+            rpc_urls: vec![DEFAULT_RPC_URL.to_string()],
+            chain_id: ARBITRUM_CHAIN_ID,
+            vault_address: VAULT_ADDRESS,
+            usdc_address: USDC_ADDRESS,
+            tokens: vec![
+                VaultToken {
+                    symbol: "WETH".to_string(),
+                    address: WETH_ADDRESS,
+                },
+                VaultToken {
+                    symbol: "WBTC".to_string(),
+                    address: WBTC_ADDRESS,
+                },
+            ],
+            gmx_vault_address: GMX_VAULT_ADDRESS,
+            legacy_vaults: vaults::DEPLOYMENTS
+                .iter()
+                .filter(|d| d.chain_id == ARBITRUM_CHAIN_ID && d.address != VAULT_ADDRESS)
+                .map(|d| LegacyVault {
+                    label: d.label.to_string(),
+                    address: d.address,
+                    version: d.version,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ChainSettings {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        settings::load(app, CHAIN_SETTINGS_KEY)
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> AppResult<()> {
+        if self.rpc_urls.is_empty() {
+            return Err(AppError::InvalidInput(
+                "At least one RPC endpoint is required".into(),
+            ));
+        }
+        for url in &self.rpc_urls {
+            reqwest::Url::parse(url)
+                .map_err(|e| AppError::InvalidInput(format!("Invalid RPC URL {}: {}", url, e)))?;
+        }
+
+        settings::save(app, CHAIN_SETTINGS_KEY, self)?;
+        app.state::<ChainState>().reset();
+        Ok(())
+    }
+}
+
+// Endpoint health shared across calls, registered as Tauri state
+#[derive(Default)]
+pub struct ChainState {
+    // Last endpoint that answered, tried first next time
+    preferred: Mutex<Option<String>>,
+    // Endpoints whose eth_chainId matched the settings
+    verified: Mutex<HashSet<String>>,
+}
+
+impl ChainState {
+    fn reset(&self) {
+        *self.preferred.lock().unwrap() = None;
+        self.verified.lock().unwrap().clear();
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    // Null is a valid answer, e.g. a receipt that is not mined yet
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    // Reverts and rejected transactions come out the same on every node, so
+    // no point failing over
+    fn is_final(&self) -> bool {
+        let message = self.message.to_lowercase();
+        self.code == EXECUTION_REVERTED || FINAL_ERRORS.iter().any(|known| message.contains(known))
+    }
+}
+
+enum Attempt {
+    // This endpoint is unusable, try the next one
+    Failover(AppError),
+    // The node answered and the answer is an error
+    Final(AppError),
+}
+
+// JSON-RPC over the shared general client, failing over across
+// ChainSettings::rpc_urls
+pub struct RpcClient<'a> {
+    client: reqwest::Client,
+    settings: ChainSettings,
+    state: &'a ChainState,
+}
+
+impl<'a> RpcClient<'a> {
+    pub fn new(app: &'a tauri::AppHandle) -> Self {
+        Self {
+            client: app.state::<HttpClient>().general(),
+            settings: ChainSettings::load(app),
+            state: app.state::<ChainState>().inner(),
+        }
+    }
+
+    pub fn settings(&self) -> &ChainSettings {
+        &self.settings
+    }
+
+    // Preferred endpoint first, the rest in configured order
+    fn endpoints(&self) -> Vec<String> {
+        let preferred = self.state.preferred.lock().unwrap().clone();
+        let mut urls = self.settings.rpc_urls.clone();
+        if let Some(index) = preferred.and_then(|url| urls.iter().position(|u| *u == url)) {
+            urls[..=index].rotate_right(1);
+        }
+        urls
+    }
+
+    pub async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> AppResult<T> {
+        let mut last_error = AppError::InvalidInput("No RPC endpoints configured".into());
+
+        for url in self.endpoints() {
+            match self.attempt(&url, method, &params).await {
+                Ok(result) => {
+                    *self.state.preferred.lock().unwrap() = Some(url);
+                    return serde_json::from_value(result).map_err(|e| {
+                        AppError::ServerUnavailable(format!("Invalid {} result: {}", method, e))
+                    });
+                }
+                Err(Attempt::Final(e)) => return Err(e),
+                Err(Attempt::Failover(e)) => {
+                    log::warn!("RPC {} failed on {}: {}", method, url, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    async fn attempt(&self, url: &str, method: &str, params: &Value) -> Result<Value, Attempt> {
+        let verified = self.state.verified.lock().unwrap().contains(url);
+        if !verified {
+            let chain_id: U64 = serde_json::from_value(
+                self.post(url, "eth_chainId", &json!([])).await?,
+            )
+            .map_err(|e| {
+                Attempt::Failover(AppError::ServerUnavailable(format!(
+                    "Invalid eth_chainId result: {}",
+                    e
+                )))
+            })?;
+            if chain_id != U64::from(self.settings.chain_id) {
+                return Err(Attempt::Failover(AppError::InvalidInput(format!(
+                    "{} is on chain {}, expected {}",
+                    url, chain_id, self.settings.chain_id
+                ))));
+            }
+            self.state.verified.lock().unwrap().insert(url.to_string());
+        }
+
+        self.post(url, method, params).await
+    }
+
+    async fn post(&self, url: &str, method: &str, params: &Value) -> Result<Value, Attempt> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = self
+            .client
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| Attempt::Failover(e.into()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Attempt::Failover(AppError::ServerUnavailable(format!(
+                "RPC node returned {}",
+                status
+            ))));
+        }
+
+        let response: RpcResponse = response
+            .json()
+            .await
+            .map_err(|e| Attempt::Failover(e.into()))?;
+        match response.error {
+            Some(error) if error.is_final() => Err(Attempt::Final(AppError::ServerRejected(
+                format!("{}: {}", method, error.message),
+            ))),
+            Some(error) => Err(Attempt::Failover(AppError::ServerUnavailable(format!(
+                "{} ({}): {}",
+                method, error.code, error.message
+            )))),
+            None => Ok(response.result),
+        }
+    }
+
+    // eth_call against the latest block, decoded with the call's ABI
+    pub async fn call<C: SolCall>(&self, to: Address, call: C) -> AppResult<C::Return> {
+        let data = Bytes::from(call.abi_encode());
+        let output: Bytes = self
+            .request("eth_call", json!([{ "to": to, "data": data }, "latest"]))
+            .await?;
+        C::abi_decode_returns(&output).map_err(|e| {
+            AppError::ServerUnavailable(format!("Cannot decode {}: {}", C::SIGNATURE, e))
+        })
+    }
+}
+
+pub fn units(value: impl Into<alloy::primitives::utils::ParseUnits>, decimals: u8) -> f64 {
+    format_units(value, decimals)
+        .ok()
+        .and_then(|formatted| formatted.parse().ok())
+        .unwrap_or(0.0)
+}
+
+fn to_u64(value: U256) -> u64 {
+    value.try_into().unwrap_or(u64::MAX)
+}
+
+// vault.ts getUserStatus plus getWithdrawable and the wallet's USDC.
+// Amounts are USDC.
+#[derive(Debug, Serialize)]
+pub struct VaultUserStatus {
+    pub address: Address,
+    pub vault_address: Address,
+    pub balance: f64,
+    pub withdrawable: f64,
+    pub auto_trade_enabled: bool,
+    pub risk_level_bps: u64,
+    pub max_leverage: u64,
+    pub stop_loss_bps: u64,
+    pub take_profit_bps: u64,
+    pub max_trade_size: f64,
+    pub wallet_usdc_balance: f64,
+    pub usdc_allowance: f64,
+}
+
+// OnChainPosition with prices and size in USD and collateral in USDC
+#[derive(Debug, Serialize)]
+pub struct VaultPosition {
+    pub token_symbol: String,
+    pub token: Address,
+    pub is_long: bool,
+    pub collateral: f64,
+    pub size: f64,
+    pub leverage: u64,
+    pub entry_price: f64,
+    pub stop_loss: f64,
+    pub take_profit: f64,
+    pub highest_price: f64,
+    pub lowest_price: f64,
+    pub trailing_sl_bps: u64,
+    pub trailing_activated: bool,
+    pub auto_features_enabled: bool,
+    pub request_key: B256,
+    // Unix seconds
+    pub opened_at: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultHealth {
+    pub vault_address: Address,
+    pub real_balance: f64,
+    pub total_value_locked: f64,
+    pub is_solvent: bool,
+    // Negative when the vault owes more than it holds
+    pub surplus: f64,
+}
+
+pub fn parse_address(address: &str) -> AppResult<Address> {
+    address
+        .parse()
+        .map_err(|_| AppError::InvalidInput(format!("Invalid address: {}", address)))
+}
+
+pub async fn user_status(app: &tauri::AppHandle, user: &str) -> AppResult<VaultUserStatus> {
+    let user = parse_address(user)?;
+    let rpc = RpcClient::new(app);
+    let vault = rpc.settings().vault_address;
+    let usdc = rpc.settings().usdc_address;
+
+    let balance = rpc.call(vault, IVault::balancesCall { user }).await?;
+
+    // Older vaults lack both, vault.ts falls back the same way
+    let settings = match rpc.call(vault, IVault::getSettingsCall { user }).await {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+            log::warn!("getSettings failed, using defaults: {}", e);
+            None
+        }
+    };
+    let withdrawable = match rpc.call(vault, IVault::getWithdrawableCall { user }).await {
+        Ok(amount) => amount,
+        Err(e) => {
+            log::warn!("getWithdrawable failed, using balance: {}", e);
+            balance
+        }
+    };
+
+    let wallet_balance = rpc
+        .call(usdc, IERC20::balanceOfCall { owner: user })
+        .await?;
+    let allowance = rpc
+        .call(
+            usdc,
+            IERC20::allowanceCall {
+                owner: user,
+                spender: vault,
+            },
+        )
+        .await?;
+
+    let risk_level_bps = settings
+        .as_ref()
+        .map(|s| to_u64(s.riskBps))
+        .filter(|bps| *bps > 0)
+        .unwrap_or(DEFAULT_RISK_BPS);
+    let max_trade = balance * U256::from(risk_level_bps) / U256::from(BPS);
+
+    Ok(VaultUserStatus {
+        address: user,
+        vault_address: vault,
+        balance: units(balance, USDC_DECIMALS),
+        withdrawable: units(withdrawable, USDC_DECIMALS),
+        auto_trade_enabled: settings.as_ref().is_some_and(|s| s.autoTradeEnabled),
+        risk_level_bps,
+        max_leverage: settings.as_ref().map_or(0, |s| to_u64(s.maxLeverage)),
+        stop_loss_bps: settings.as_ref().map_or(0, |s| to_u64(s.stopLossBps)),
+        take_profit_bps: settings.as_ref().map_or(0, |s| to_u64(s.takeProfitBps)),
+        max_trade_size: units(max_trade, USDC_DECIMALS),
+        wallet_usdc_balance: units(wallet_balance, USDC_DECIMALS),
+        usdc_allowance: units(allowance, USDC_DECIMALS),
+    })
+}
+
+// Active positions across the configured tokens
+pub async fn positions(app: &tauri::AppHandle, user: &str) -> AppResult<Vec<VaultPosition>> {
+    let user = parse_address(user)?;
+    let rpc = RpcClient::new(app);
+    let vault = rpc.settings().vault_address;
+
+    let mut positions = Vec::new();
+    for token in &rpc.settings().tokens {
+        let position = rpc
+            .call(
+                vault,
+                IVault::getPositionCall {
+                    user,
+                    token: token.address,
+                },
+            )
+            .await?;
+        if !position.isActive {
+            continue;
+        }
+        positions.push(VaultPosition {
+            token_symbol: token.symbol.clone(),
+            token: token.address,
+            is_long: position.isLong,
+            collateral: units(position.collateral, USDC_DECIMALS),
+            size: units(position.size, PRICE_DECIMALS),
+            leverage: to_u64(position.leverage),
+            entry_price: units(position.entryPrice, PRICE_DECIMALS),
+            stop_loss: units(position.stopLoss, PRICE_DECIMALS),
+            take_profit: units(position.takeProfit, PRICE_DECIMALS),
+            highest_price: units(position.highestPrice, PRICE_DECIMALS),
+            lowest_price: units(position.lowestPrice, PRICE_DECIMALS),
+            trailing_sl_bps: to_u64(position.trailingSlBps),
+            trailing_activated: position.trailingActivated,
+            auto_features_enabled: position.autoFeaturesEnabled,
+            request_key: position.requestKey,
+            opened_at: to_u64(position.timestamp),
+        });
+    }
+
+    Ok(positions)
+}
+
+pub async fn health(app: &tauri::AppHandle) -> AppResult<VaultHealth> {
+    let rpc = RpcClient::new(app);
+    let vault = rpc.settings().vault_address;
+    let status = rpc.call(vault, IVault::getHealthStatusCall {}).await?;
+
+    Ok(VaultHealth {
+        vault_address: vault,
+        real_balance: units(status.realBalance, USDC_DECIMALS),
+        total_value_locked: units(status.totalValueLocked, USDC_DECIMALS),
+        is_solvent: status.isSolvent,
+        surplus: units(status.surplus, USDC_DECIMALS),
+    })
+}
+
+// Recorded JSON-RPC nodes for tests here and in tx
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    pub const ARBITRUM: &str = "0xa4b1";
+    // Nothing listens on port 1
+    pub const DEAD_NODE: &str = "http://127.0.0.1:1";
+
+    pub fn result(value: &str) -> (u16, Value) {
+        (200, json!({ "jsonrpc": "2.0", "id": 1, "result": value }))
+    }
+
+    pub fn error(code: i64, message: &str) -> (u16, Value) {
+        (
+            200,
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": code, "message": message } }),
+        )
+    }
+
+    // JSON-RPC node answering each method with a recorded (status, body) and
+    // logging the methods it was asked for
+    pub async fn node(
+        responses: Vec<(&'static str, (u16, Value))>,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let requests = log.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let body = loop {
+                    let read = socket.read(&mut buf).await.unwrap();
+                    if read == 0 {
+                        break None;
+                    }
+                    request.extend_from_slice(&buf[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    let Some(end) = text.find("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break Some(request[end + 4..end + 4 + length].to_vec());
+                    }
+                };
+                let Some(body) = body else { continue };
+
+                let call: Value = serde_json::from_slice(&body).unwrap();
+                let method = call["method"].as_str().unwrap_or_default().to_string();
+                requests.lock().unwrap().push(method.clone());
+                let (status, reply) = responses
+                    .iter()
+                    .find(|(m, _)| *m == method)
+                    .map(|(_, response)| response.clone())
+                    .unwrap_or_else(|| error(-32601, "the method does not exist"));
+                let reply = reply.to_string();
+                let response = format!(
+                    "HTTP/1.1 {} Recorded\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    reply.len(),
+                    reply
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (url, log)
+    }
+
+    pub fn client<'a>(urls: &[&str], state: &'a ChainState) -> RpcClient<'a> {
+        RpcClient {
+            client: reqwest::Client::new(),
+            settings: ChainSettings {
+                rpc_urls: urls.iter().map(|url| url.to_string()).collect(),
+                ..ChainSettings::default()
+            },
+            state,
+        }
+    }
+
+    pub fn methods(log: &Mutex<Vec<String>>) -> Vec<String> {
+        log.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn fails_over_past_a_dead_node() {
+        let (url, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            ("eth_blockNumber", result("0x10")),
+        ])
+        .await;
+        let state = ChainState::default();
+        let rpc = client(&[DEAD_NODE, &url], &state);
+
+        let block: U64 = rpc.request("eth_blockNumber", json!([])).await.unwrap();
+        assert_eq!(block, U64::from(16));
+
+        // The node that answered goes first next time
+        assert_eq!(*state.preferred.lock().unwrap(), Some(url.clone()));
+        assert_eq!(rpc.endpoints(), vec![url, DEAD_NODE.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn fails_over_on_node_errors() {
+        let (overloaded, overloaded_log) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            ("eth_blockNumber", (503, json!({}))),
+        ])
+        .await;
+        let (limited, limited_log) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            ("eth_blockNumber", error(-32005, "request rate exceeded")),
+        ])
+        .await;
+        let (healthy, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            ("eth_blockNumber", result("0x20")),
+        ])
+        .await;
+        let state = ChainState::default();
+        let rpc = client(&[&overloaded, &limited, &healthy], &state);
+
+        let block: U64 = rpc.request("eth_blockNumber", json!([])).await.unwrap();
+        assert_eq!(block, U64::from(32));
+        assert_eq!(methods(&overloaded_log), ["eth_chainId", "eth_blockNumber"]);
+        assert_eq!(methods(&limited_log), ["eth_chainId", "eth_blockNumber"]);
+    }
+
+    #[tokio::test]
+    async fn returns_the_last_error_when_every_node_fails() {
+        let (limited, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            ("eth_blockNumber", error(-32005, "request rate exceeded")),
+        ])
+        .await;
+        let state = ChainState::default();
+        let rpc = client(&[DEAD_NODE, &limited], &state);
+
+        let error = rpc
+            .request::<U64>("eth_blockNumber", json!([]))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, AppError::ServerUnavailable(m) if m.contains("rate exceeded")),
+            "{:?}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_a_node_on_another_chain() {
+        let (mainnet, mainnet_log) = node(vec![
+            ("eth_chainId", result("0x1")),
+            ("eth_blockNumber", result("0x99")),
+        ])
+        .await;
+        let (arbitrum, arbitrum_log) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            ("eth_blockNumber", result("0x10")),
+        ])
+        .await;
+        let state = ChainState::default();
+        let rpc = client(&[&mainnet, &arbitrum], &state);
+
+        let block: U64 = rpc.request("eth_blockNumber", json!([])).await.unwrap();
+        assert_eq!(block, U64::from(16));
+        assert_eq!(methods(&mainnet_log), ["eth_chainId"]);
+
+        // The chain id is only checked once per node
+        let _: U64 = rpc.request("eth_blockNumber", json!([])).await.unwrap();
+        assert_eq!(
+            methods(&arbitrum_log),
+            ["eth_chainId", "eth_blockNumber", "eth_blockNumber"]
+        );
+        let verified = state.verified.lock().unwrap();
+        assert!(verified.contains(&arbitrum) && !verified.contains(&mainnet));
+    }
+
+    #[tokio::test]
+    async fn stops_at_a_final_error() {
+        let (reverting, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            ("eth_call", error(3, "execution reverted: No position")),
+        ])
+        .await;
+        let (other, other_log) = node(vec![("eth_chainId", result(ARBITRUM))]).await;
+        let state = ChainState::default();
+        let rpc = client(&[&reverting, &other], &state);
+
+        let error = rpc
+            .call(
+                VAULT_ADDRESS,
+                IVault::getWithdrawableCall {
+                    user: Address::ZERO,
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::ServerRejected(_)), "{:?}", error);
+        assert!(methods(&other_log).is_empty());
+
+        // Known rejections are final whatever the code
+        let nonce = RpcError {
+            code: -32000,
+            message: "Nonce too low".to_string(),
+        };
+        assert!(nonce.is_final());
+    }
+
+    #[tokio::test]
+    async fn decodes_get_settings() {
+        let (url, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            (
+                "eth_call",
+                result(concat!(
+                    "0x",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                    "00000000000000000000000000000000000000000000000000000000000001f4",
+                    "0000000000000000000000000000000000000000000000000000000000000005",
+                    "00000000000000000000000000000000000000000000000000000000000000c8",
+                    "0000000000000000000000000000000000000000000000000000000000000190",
+                )),
+            ),
+        ])
+        .await;
+        let state = ChainState::default();
+        let rpc = client(&[&url], &state);
+
+        let settings = rpc
+            .call(
+                VAULT_ADDRESS,
+                IVault::getSettingsCall {
+                    user: Address::ZERO,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(settings.autoTradeEnabled);
+        assert_eq!(settings.riskBps, U256::from(500));
+        assert_eq!(settings.maxLeverage, U256::from(5));
+        assert_eq!(settings.stopLossBps, U256::from(200));
+        assert_eq!(settings.takeProfitBps, U256::from(400));
+    }
+
+    #[tokio::test]
+    async fn decodes_get_position() {
+        let (url, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            (
+                "eth_call",
+                result(concat!(
+                    "0x",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                    "0000000000000000000000000000000000000000000000000000000000000000",
+                    "00000000000000000000000082af49447d8a07e3bd95bd0d56f35241523fbab1",
+                    "0000000000000000000000000000000000000000000000000000000005f5e100",
+                    "00000000000000000000000000000000000018a6e32246c99c60ad8500000000",
+                    "0000000000000000000000000000000000000000000000000000000000000005",
+                    "000000000000000000000000000000000000629b8c891b267182b61400000000",
+                    "0000000000000000000000000000000000006789b9f65c81f72fa59500000000",
+                    "00000000000000000000000000000000000058bf31ae986f6628d71200000000",
+                    "000000000000000000000000000000000000000000000000000000006553f100",
+                    "abababababababababababababababababababababababababababababababab",
+                    "0000000000000000000000000000000000006319c4473b493214013a80000000",
+                    "000000000000000000000000000000000000602475d27a78aeac3e5380000000",
+                    "0000000000000000000000000000000000000000000000000000000000000064",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                )),
+            ),
+        ])
+        .await;
+        let state = ChainState::default();
+        let rpc = client(&[&url], &state);
+
+        let position = rpc
+            .call(
+                VAULT_ADDRESS,
+                IVault::getPositionCall {
+                    user: Address::ZERO,
+                    token: WETH_ADDRESS,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(position.isActive && !position.isLong);
+        assert_eq!(position.token, WETH_ADDRESS);
+        assert_eq!(units(position.collateral, USDC_DECIMALS), 100.0);
+        assert_eq!(units(position.size, PRICE_DECIMALS), 500.0);
+        assert_eq!(position.leverage, U256::from(5));
+        assert_eq!(units(position.entryPrice, PRICE_DECIMALS), 2000.0);
+        assert_eq!(units(position.stopLoss, PRICE_DECIMALS), 2100.0);
+        assert_eq!(units(position.takeProfit, PRICE_DECIMALS), 1800.0);
+        assert_eq!(position.timestamp, U256::from(1_700_000_000u64));
+        assert_eq!(position.requestKey, B256::repeat_byte(0xab));
+        assert_eq!(units(position.highestPrice, PRICE_DECIMALS), 2010.0);
+        assert_eq!(units(position.lowestPrice, PRICE_DECIMALS), 1950.0);
+        assert_eq!(position.trailingSlBps, U256::from(100));
+        assert!(position.trailingActivated && position.autoFeaturesEnabled);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_short_return() {
+        let (url, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            ("eth_call", result("0x01")),
+        ])
+        .await;
+        let state = ChainState::default();
+        let rpc = client(&[&url], &state);
+
+        let error = rpc
+            .call(
+                VAULT_ADDRESS,
+                IVault::getSettingsCall {
+                    user: Address::ZERO,
+                },
+            )
+            .await
+            .err()
+            .expect("a short return must not decode");
+        assert!(
+            matches!(error, AppError::ServerUnavailable(_)),
+            "{:?}",
+            error
+        );
+    }
+}
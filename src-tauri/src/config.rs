@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::error::{AppError, AppResult};
+
+// Backend profiles compiled into the binary. The webview can only pick
+// among URLs listed here, never supply its own.
+const PROFILES_JSON: &str = include_str!("../config/backend.json");
+
+// Profile baked in at build time with MONADIER_PROFILE=dev|staging|prod
+const BUILD_PROFILE: Option<&str> = option_env!("MONADIER_PROFILE");
+
+// Fill in what backend.json leaves empty, for projects whose URL and anon
+// key are supplied by the release pipeline
+const BUILD_SUPABASE_URL: Option<&str> = option_env!("MONADIER_SUPABASE_URL");
+const BUILD_ANON_KEY: Option<&str> = option_env!("MONADIER_SUPABASE_ANON_KEY");
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BackendConfig {
+    #[serde(default)]
+    pub profile: String,
+    pub supabase_url: String,
+    #[serde(default)]
+    pub alternate_urls: Vec<String>,
+    pub anon_key: String,
+    pub license_endpoint: String,
+    pub deactivate_endpoint: String,
+    // When set, only these roots are trusted for backend TLS
+    #[serde(default, skip_serializing)]
+    pub pinned_roots_pem: Vec<String>,
+}
+
+static ACTIVE: OnceLock<Result<BackendConfig, AppError>> = OnceLock::new();
+
+fn default_profile() -> String {
+    // Debug builds may switch profile at runtime, release builds never do
+    if cfg!(debug_assertions) {
+        if let Ok(profile) = std::env::var("MONADIER_PROFILE") {
+            return profile;
+        }
+    }
+
+    BUILD_PROFILE
+        .unwrap_or(if cfg!(debug_assertions) {
+            "dev"
+        } else {
+            "prod"
+        })
+        .to_string()
+}
+
+fn load(profile: &str) -> AppResult<BackendConfig> {
+    let mut profiles: HashMap<String, BackendConfig> = serde_json::from_str(PROFILES_JSON)
+        .map_err(|e| AppError::Internal(format!("invalid backend config: {}", e)))?;
+
+    let mut config = profiles
+        .remove(profile)
+        .ok_or_else(|| AppError::Internal(format!("unknown backend profile '{}'", profile)))?;
+
+    if config.supabase_url.is_empty() {
+        config.supabase_url = BUILD_SUPABASE_URL.unwrap_or_default().to_string();
+    }
+    if config.anon_key.is_empty() {
+        config.anon_key = BUILD_ANON_KEY.unwrap_or_default().to_string();
+    }
+    // Edge functions and PostgREST refuse every request without the key
+    if config.supabase_url.is_empty() || config.anon_key.is_empty() {
+        return Err(AppError::Internal(format!(
+            "backend profile '{}' is not configured, set MONADIER_SUPABASE_URL and MONADIER_SUPABASE_ANON_KEY",
+            profile
+        )));
+    }
+
+    config.profile = profile.to_string();
+    Ok(config)
+}
+
+fn normalize(url: &str) -> &str {
+    url.trim().trim_end_matches('/')
+}
+
+impl BackendConfig {
+    pub fn active() -> AppResult<&'static BackendConfig> {
+        ACTIVE
+            .get_or_init(|| load(&default_profile()))
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    pub fn allows(&self, url: &str) -> bool {
+        let url = normalize(url);
+        std::iter::once(&self.supabase_url)
+            .chain(self.alternate_urls.iter())
+            .any(|allowed| normalize(allowed) == url)
+    }
+
+    // Base URL to talk to, rejecting anything outside the allow-list
+    pub fn resolve_url(&self, requested: Option<&str>) -> AppResult<String> {
+        match requested {
+            Some(url) if !self.allows(url) => Err(AppError::InvalidInput(format!(
+                "Backend URL {} is not allowed",
+                url
+            ))),
+            Some(url) => Ok(normalize(url).to_string()),
+            None => Ok(normalize(&self.supabase_url).to_string()),
+        }
+    }
+
+    pub fn license_url(&self, base: &str) -> String {
+        format!("{}{}", normalize(base), self.license_endpoint)
+    }
+
+    pub fn deactivate_url(&self, base: &str) -> String {
+        format!("{}{}", normalize(base), self.deactivate_endpoint)
+    }
+
+    // Restrict a client to the pinned roots, if any, and attach the anon key
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> AppResult<reqwest::ClientBuilder> {
+        if !self.pinned_roots_pem.is_empty() {
+            builder = builder.tls_built_in_root_certs(false);
+            for pem in &self.pinned_roots_pem {
+                let cert = reqwest::Certificate::from_pem(pem.as_bytes()).map_err(|e| {
+                    AppError::Internal(format!("invalid pinned certificate: {}", e))
+                })?;
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        let key = reqwest::header::HeaderValue::from_str(&self.anon_key)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let bearer = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", self.anon_key))
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        headers.insert("apikey", key);
+        headers.insert(reqwest::header::AUTHORIZATION, bearer);
+
+        Ok(builder.default_headers(headers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dev_profile_is_complete() {
+        let config = load("dev").unwrap();
+        assert_eq!(config.profile, "dev");
+        assert!(!config.anon_key.is_empty());
+        assert!(config.allows("http://localhost:54321/"));
+    }
+
+    #[test]
+    fn incomplete_profiles_fail_to_load() {
+        if BUILD_SUPABASE_URL.is_none() {
+            assert!(matches!(load("staging"), Err(AppError::Internal(_))));
+        }
+        assert!(matches!(load("nope"), Err(AppError::Internal(_))));
+    }
+}
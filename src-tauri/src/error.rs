@@ -0,0 +1,122 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+// Error returned by every Tauri command. Serialized as
+// `{ code, message, retryable }` so the frontend can branch on `code`
+// instead of matching message text.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AppError {
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Server unavailable: {0}")]
+    ServerUnavailable(String),
+    #[error("Server rejected the request: {0}")]
+    ServerRejected(String),
+    #[error("License is invalid: {0}")]
+    LicenseInvalid(String),
+    #[error("License was activated on a different machine")]
+    MachineMismatch,
+    #[error("No license is activated")]
+    NotActivated,
+    #[error("Local data is corrupted: {0}")]
+    StoreCorrupted(String),
+    #[error("Storage error: {0}")]
+    Storage(String),
+    #[error("Secrets store is locked")]
+    SecretsLocked,
+    #[error("Wrong passphrase or different machine")]
+    WrongPassphrase,
+    #[error("Wallet is locked")]
+    WalletLocked,
+    #[error("Wrong wallet password")]
+    WrongPassword,
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Cryptographic operation failed: {0}")]
+    Crypto(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    // Stable identifier, never change an existing one
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Network(_) => "NETWORK_ERROR",
+            AppError::ServerUnavailable(_) => "SERVER_UNAVAILABLE",
+            AppError::ServerRejected(_) => "SERVER_REJECTED",
+            AppError::LicenseInvalid(_) => "LICENSE_INVALID",
+            AppError::MachineMismatch => "MACHINE_MISMATCH",
+            AppError::NotActivated => "LICENSE_NOT_ACTIVATED",
+            AppError::StoreCorrupted(_) => "STORE_CORRUPTED",
+            AppError::Storage(_) => "STORAGE_ERROR",
+            AppError::SecretsLocked => "SECRETS_LOCKED",
+            AppError::WrongPassphrase => "WRONG_PASSPHRASE",
+            AppError::WalletLocked => "WALLET_LOCKED",
+            AppError::WrongPassword => "WRONG_PASSWORD",
+            AppError::InvalidInput(_) => "INVALID_INPUT",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Crypto(_) => "CRYPTO_ERROR",
+            AppError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    // Whether trying the same call again later can succeed
+    pub fn retryable(&self) -> bool {
+        matches!(self, AppError::Network(_) | AppError::ServerUnavailable(_))
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("retryable", &self.retryable())?;
+        state.end()
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            AppError::ServerUnavailable(format!("Parse error: {}", e))
+        } else {
+            AppError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<tauri_plugin_store::Error> for AppError {
+    fn from(e: tauri_plugin_store::Error) -> Self {
+        AppError::Storage(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Storage(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::StoreCorrupted(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::Storage(e.to_string())
+    }
+}
@@ -0,0 +1,1040 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::error::{AppError, AppResult};
+use crate::journal::{self, FillSide, JournalFill, JournalFilter, JournalPosition};
+use crate::risk::exits::Direction;
+
+// Every position is settled in USDC
+const QUOTE_CURRENCY: &str = "USDC";
+
+// Exchange pairs like BTCUSDT are reported as the base asset
+const QUOTE_SUFFIXES: &[&str] = &["USDT", "USDC", "BUSD", "USD"];
+
+const EXCHANGE_NAME: &str = "Monadier";
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    // Every fill, one row each
+    Generic,
+    RealizedFifo,
+    RealizedLifo,
+    // Koinly universal CSV
+    Koinly,
+    // CoinTracking CSV import
+    #[serde(rename = "cointracking")]
+    CoinTracking,
+}
+
+impl ExportFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Generic => "monadier-trades.csv",
+            ExportFormat::RealizedFifo => "monadier-gains-fifo.csv",
+            ExportFormat::RealizedLifo => "monadier-gains-lifo.csv",
+            ExportFormat::Koinly => "monadier-koinly.csv",
+            ExportFormat::CoinTracking => "monadier-cointracking.csv",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ExportOptions {
+    // Calendar year in UTC, takes precedence over from/to
+    pub year: Option<i32>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub include_paper: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportSummary {
+    pub path: String,
+    pub rows: usize,
+    // Totals over the realized gains in range, FIFO unless LIFO was asked for
+    pub proceeds: f64,
+    pub cost_basis: f64,
+    pub fees: f64,
+    pub realized_gain: f64,
+}
+
+// One fill of a position, the unit everything else is built from
+#[derive(Debug, Clone)]
+pub struct TradeEvent {
+    pub position_id: String,
+    pub asset: String,
+    pub time: DateTime<Utc>,
+    pub side: FillSide,
+    // Of the position, a short opens with a sale
+    pub direction: Direction,
+    pub quantity: f64,
+    pub price: f64,
+    // USDC notional
+    pub amount: f64,
+    pub fee: f64,
+    pub tx_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GainKind {
+    Spot,
+    // Leveraged or short, taxed on the position's realized pnl
+    Margin,
+}
+
+#[derive(Debug, Clone)]
+pub struct RealizedGain {
+    pub kind: GainKind,
+    pub position_id: String,
+    pub asset: String,
+    pub quantity: f64,
+    // None when the sale had no matching purchase in the journal
+    pub acquired_at: Option<DateTime<Utc>>,
+    pub disposed_at: DateTime<Utc>,
+    pub proceeds: f64,
+    pub cost_basis: f64,
+    pub fees: f64,
+    pub gain: f64,
+    pub tx_hash: Option<String>,
+}
+
+struct Lot {
+    time: DateTime<Utc>,
+    quantity: f64,
+    unit_cost: f64,
+}
+
+fn parse_time(value: &str) -> AppResult<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|_| AppError::InvalidInput(format!("Invalid date {}", value)))
+}
+
+fn asset(symbol: &str) -> String {
+    let symbol = symbol.trim().to_uppercase().replace(['/', '-'], "");
+    QUOTE_SUFFIXES
+        .iter()
+        .find_map(|quote| symbol.strip_suffix(quote).filter(|base| !base.is_empty()))
+        .unwrap_or(&symbol)
+        .to_string()
+}
+
+fn is_spot(position: &JournalPosition) -> bool {
+    position.direction == Direction::Long && position.leverage <= 1.0
+}
+
+// Recorded fills, with the open and close filled in from the position
+// columns when the journal has no fill for them (e.g. rows pulled from
+// Supabase)
+pub fn trade_events(
+    position: &JournalPosition,
+    fills: &[JournalFill],
+    extra_fee: f64,
+) -> AppResult<Vec<TradeEvent>> {
+    let asset = asset(&position.token_symbol);
+    let notional = position.entry_amount * position.leverage;
+    let quantity = position
+        .token_amount
+        .unwrap_or(notional / position.entry_price);
+
+    let mut events = fills
+        .iter()
+        .map(|fill| {
+            Ok(TradeEvent {
+                position_id: position.id.clone(),
+                asset: asset.clone(),
+                time: parse_time(fill.created_at.as_deref().unwrap_or(&position.created_at))?,
+                side: fill.side,
+                direction: position.direction,
+                quantity: fill.token_amount.unwrap_or(fill.amount / fill.price),
+                price: fill.price,
+                amount: fill.amount,
+                fee: fill.fee,
+                tx_hash: fill.tx_hash.clone(),
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    if !events.iter().any(|e| e.side == FillSide::Open) {
+        events.push(TradeEvent {
+            position_id: position.id.clone(),
+            asset: asset.clone(),
+            time: parse_time(&position.created_at)?,
+            side: FillSide::Open,
+            direction: position.direction,
+            quantity,
+            price: position.entry_price,
+            amount: notional,
+            fee: 0.0,
+            tx_hash: position.entry_tx_hash.clone(),
+        });
+    }
+
+    let closed_at = position
+        .closed_at
+        .as_deref()
+        .filter(|_| position.status == "closed");
+    if let (Some(closed_at), Some(exit_price)) = (closed_at, position.exit_price) {
+        if !events.iter().any(|e| e.side == FillSide::Close) {
+            events.push(TradeEvent {
+                position_id: position.id.clone(),
+                asset: asset.clone(),
+                time: parse_time(closed_at)?,
+                side: FillSide::Close,
+                direction: position.direction,
+                quantity,
+                price: exit_price,
+                amount: quantity * exit_price,
+                fee: 0.0,
+                tx_hash: position.exit_tx_hash.clone(),
+            });
+        }
+    }
+
+    // Gas and other costs are capitalized into the purchase
+    if let Some(open) = events.iter_mut().find(|e| e.side == FillSide::Open) {
+        open.fee += extra_fee;
+    }
+
+    events.sort_by_key(|e| e.time);
+    Ok(events)
+}
+
+// Margin positions are realized as a whole when they close. profit_loss is
+// already net of fees.
+fn margin_gain(
+    position: &JournalPosition,
+    events: &[TradeEvent],
+    extra_fee: f64,
+) -> Option<RealizedGain> {
+    if position.status != "closed" {
+        return None;
+    }
+    let close = events.iter().rev().find(|e| e.side == FillSide::Close)?;
+    let open = events.iter().find(|e| e.side == FillSide::Open);
+    let gain = position.profit_loss.or_else(|| {
+        position
+            .exit_amount
+            .map(|exit_amount| exit_amount - position.entry_amount)
+    })? - extra_fee;
+    let fees: f64 = events.iter().map(|e| e.fee).sum();
+
+    Some(RealizedGain {
+        kind: GainKind::Margin,
+        position_id: position.id.clone(),
+        asset: close.asset.clone(),
+        quantity: close.quantity,
+        acquired_at: open.map(|e| e.time),
+        disposed_at: close.time,
+        proceeds: position.entry_amount + gain + fees,
+        cost_basis: position.entry_amount,
+        fees,
+        gain,
+        tx_hash: close.tx_hash.clone(),
+    })
+}
+
+// Match spot sales against earlier purchases of the same asset. Lots are
+// pooled across positions, as tax lots are.
+pub fn realize_spot(events: &[TradeEvent], lifo: bool) -> Vec<RealizedGain> {
+    let mut sorted: Vec<&TradeEvent> = events.iter().collect();
+    sorted.sort_by_key(|e| e.time);
+
+    let mut lots: HashMap<&str, VecDeque<Lot>> = HashMap::new();
+    let mut gains = Vec::new();
+
+    for event in sorted {
+        if event.quantity <= 0.0 {
+            continue;
+        }
+        let queue = lots.entry(event.asset.as_str()).or_default();
+
+        if event.side == FillSide::Open {
+            queue.push_back(Lot {
+                time: event.time,
+                quantity: event.quantity,
+                unit_cost: (event.amount + event.fee) / event.quantity,
+            });
+            continue;
+        }
+
+        let unit_proceeds = event.amount / event.quantity;
+        let unit_fee = event.fee / event.quantity;
+        let mut remaining = event.quantity;
+        let mut realize = |quantity: f64, acquired_at, unit_cost: f64| {
+            let proceeds = quantity * unit_proceeds;
+            let cost_basis = quantity * unit_cost;
+            let fees = quantity * unit_fee;
+            gains.push(RealizedGain {
+                kind: GainKind::Spot,
+                position_id: event.position_id.clone(),
+                asset: event.asset.clone(),
+                quantity,
+                acquired_at,
+                disposed_at: event.time,
+                proceeds,
+                cost_basis,
+                fees,
+                gain: proceeds - cost_basis - fees,
+                tx_hash: event.tx_hash.clone(),
+            });
+        };
+
+        while remaining > f64::EPSILON {
+            let lot = if lifo {
+                queue.back_mut()
+            } else {
+                queue.front_mut()
+            };
+            let Some(lot) = lot else {
+                break;
+            };
+
+            let matched = remaining.min(lot.quantity);
+            realize(matched, Some(lot.time), lot.unit_cost);
+            lot.quantity -= matched;
+            remaining -= matched;
+
+            if lot.quantity <= f64::EPSILON {
+                if lifo {
+                    queue.pop_back();
+                } else {
+                    queue.pop_front();
+                }
+            }
+        }
+
+        // Sold more than the journal ever bought, report a zero cost basis
+        if remaining > f64::EPSILON {
+            realize(remaining, None, 0.0);
+        }
+    }
+
+    gains
+}
+
+struct History {
+    events: Vec<TradeEvent>,
+    spot_events: Vec<TradeEvent>,
+    margin: Vec<RealizedGain>,
+}
+
+// The whole journal, purchases before the range still feed the cost basis
+fn load_history(app: &tauri::AppHandle, options: &ExportOptions) -> AppResult<History> {
+    let filter = JournalFilter {
+        paper: (!options.include_paper).then_some(false),
+        limit: Some(u32::MAX),
+        ..JournalFilter::default()
+    };
+    let extra_fees = journal::extra_fees(app)?;
+
+    let mut history = History {
+        events: Vec::new(),
+        spot_events: Vec::new(),
+        margin: Vec::new(),
+    };
+    for position in journal::positions(app, &filter)? {
+        if position.status == "failed" {
+            continue;
+        }
+        let extra_fee = extra_fees.get(&position.id).copied().unwrap_or(0.0);
+        let fills = journal::fills(app, &position.id)?;
+        let events = trade_events(&position, &fills, extra_fee)?;
+
+        if is_spot(&position) {
+            history.spot_events.extend(events.iter().cloned());
+        } else if let Some(gain) = margin_gain(&position, &events, extra_fee) {
+            history.margin.push(gain);
+        }
+        history.events.extend(events);
+    }
+    history.events.sort_by_key(|e| e.time);
+    Ok(history)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+fn amount(value: f64) -> String {
+    // Plenty for token quantities, trimmed so USDC reads naturally
+    let text = format!("{:.8}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+fn generic_csv(events: &[&TradeEvent]) -> String {
+    let mut out = csv_line(
+        &[
+            "Date", "Type", "Asset", "Quantity", "Price", "Amount", "Fee", "Currency", "Position",
+            "Tx Hash",
+        ]
+        .map(String::from),
+    );
+    for event in events {
+        out += &csv_line(&[
+            event.time.to_rfc3339(),
+            match (event.side, event.direction) {
+                (FillSide::Open, Direction::Long) | (FillSide::Close, Direction::Short) => "Buy",
+                (FillSide::Close, Direction::Long) | (FillSide::Open, Direction::Short) => "Sell",
+            }
+            .to_string(),
+            event.asset.clone(),
+            amount(event.quantity),
+            amount(event.price),
+            amount(event.amount),
+            amount(event.fee),
+            QUOTE_CURRENCY.to_string(),
+            event.position_id.clone(),
+            event.tx_hash.clone().unwrap_or_default(),
+        ]);
+    }
+    out
+}
+
+fn realized_csv(gains: &[&RealizedGain], method: &str) -> String {
+    let mut out = csv_line(
+        &[
+            "Asset",
+            "Quantity",
+            "Date Acquired",
+            "Date Sold",
+            "Proceeds",
+            "Cost Basis",
+            "Fees",
+            "Gain",
+            "Holding Days",
+            "Type",
+            "Method",
+            "Position",
+            "Tx Hash",
+        ]
+        .map(String::from),
+    );
+    for gain in gains {
+        out += &csv_line(&[
+            gain.asset.clone(),
+            amount(gain.quantity),
+            gain.acquired_at
+                .map(|time| time.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            gain.disposed_at.format("%Y-%m-%d").to_string(),
+            amount(gain.proceeds),
+            amount(gain.cost_basis),
+            amount(gain.fees),
+            amount(gain.gain),
+            gain.acquired_at
+                .map(|time| (gain.disposed_at - time).num_days().to_string())
+                .unwrap_or_default(),
+            match gain.kind {
+                GainKind::Spot => "spot",
+                GainKind::Margin => "margin",
+            }
+            .to_string(),
+            method.to_string(),
+            gain.position_id.clone(),
+            gain.tx_hash.clone().unwrap_or_default(),
+        ]);
+    }
+    out
+}
+
+// Koinly universal CSV
+fn koinly_csv(spot: &[&TradeEvent], margin: &[&RealizedGain]) -> String {
+    let mut rows: Vec<(DateTime<Utc>, Vec<String>)> = Vec::new();
+    let date = |time: &DateTime<Utc>| time.format("%Y-%m-%d %H:%M UTC").to_string();
+
+    for event in spot {
+        let (sent, sent_currency, received, received_currency) = match event.side {
+            FillSide::Open => (
+                event.amount,
+                QUOTE_CURRENCY,
+                event.quantity,
+                &event.asset[..],
+            ),
+            FillSide::Close => (
+                event.quantity,
+                &event.asset[..],
+                event.amount,
+                QUOTE_CURRENCY,
+            ),
+        };
+        rows.push((
+            event.time,
+            vec![
+                date(&event.time),
+                amount(sent),
+                sent_currency.to_string(),
+                amount(received),
+                received_currency.to_string(),
+                amount(event.fee),
+                QUOTE_CURRENCY.to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+                format!("{} position {}", EXCHANGE_NAME, event.position_id),
+                event.tx_hash.clone().unwrap_or_default(),
+            ],
+        ));
+    }
+
+    for gain in margin {
+        let (sent, received) = if gain.gain < 0.0 {
+            (amount(-gain.gain), String::new())
+        } else {
+            (String::new(), amount(gain.gain))
+        };
+        let currency = |value: &String| {
+            if value.is_empty() {
+                String::new()
+            } else {
+                QUOTE_CURRENCY.to_string()
+            }
+        };
+        rows.push((
+            gain.disposed_at,
+            vec![
+                date(&gain.disposed_at),
+                sent.clone(),
+                currency(&sent),
+                received.clone(),
+                currency(&received),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                "realized gain".to_string(),
+                format!(
+                    "{} {} margin position {}",
+                    EXCHANGE_NAME, gain.asset, gain.position_id
+                ),
+                gain.tx_hash.clone().unwrap_or_default(),
+            ],
+        ));
+    }
+
+    rows.sort_by_key(|(time, _)| *time);
+    let mut out = csv_line(
+        &[
+            "Date",
+            "Sent Amount",
+            "Sent Currency",
+            "Received Amount",
+            "Received Currency",
+            "Fee Amount",
+            "Fee Currency",
+            "Net Worth Amount",
+            "Net Worth Currency",
+            "Label",
+            "Description",
+            "TxHash",
+        ]
+        .map(String::from),
+    );
+    for (_, row) in rows {
+        out += &csv_line(&row);
+    }
+    out
+}
+
+// CoinTracking "Custom exchange import" CSV
+fn cointracking_csv(spot: &[&TradeEvent], margin: &[&RealizedGain]) -> String {
+    let mut rows: Vec<(DateTime<Utc>, Vec<String>)> = Vec::new();
+    let date = |time: &DateTime<Utc>| time.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    for event in spot {
+        let (buy, buy_currency, sell, sell_currency) = match event.side {
+            FillSide::Open => (
+                event.quantity,
+                &event.asset[..],
+                event.amount,
+                QUOTE_CURRENCY,
+            ),
+            FillSide::Close => (
+                event.amount,
+                QUOTE_CURRENCY,
+                event.quantity,
+                &event.asset[..],
+            ),
+        };
+        rows.push((
+            event.time,
+            vec![
+                "Trade".to_string(),
+                amount(buy),
+                buy_currency.to_string(),
+                amount(sell),
+                sell_currency.to_string(),
+                amount(event.fee),
+                QUOTE_CURRENCY.to_string(),
+                EXCHANGE_NAME.to_string(),
+                event.position_id.clone(),
+                String::new(),
+                date(&event.time),
+                event.tx_hash.clone().unwrap_or_default(),
+            ],
+        ));
+    }
+
+    for gain in margin {
+        let (kind, buy, sell) = if gain.gain < 0.0 {
+            ("Margin Loss", String::new(), amount(-gain.gain))
+        } else {
+            ("Margin Profit", amount(gain.gain), String::new())
+        };
+        let currency = |value: &String| {
+            if value.is_empty() {
+                String::new()
+            } else {
+                QUOTE_CURRENCY.to_string()
+            }
+        };
+        rows.push((
+            gain.disposed_at,
+            vec![
+                kind.to_string(),
+                buy.clone(),
+                currency(&buy),
+                sell.clone(),
+                currency(&sell),
+                String::new(),
+                String::new(),
+                EXCHANGE_NAME.to_string(),
+                gain.position_id.clone(),
+                format!("{} margin position", gain.asset),
+                date(&gain.disposed_at),
+                gain.tx_hash.clone().unwrap_or_default(),
+            ],
+        ));
+    }
+
+    rows.sort_by_key(|(time, _)| *time);
+    let mut out = csv_line(
+        &[
+            "Type",
+            "Buy Amount",
+            "Buy Currency",
+            "Sell Amount",
+            "Sell Currency",
+            "Fee",
+            "Fee Currency",
+            "Exchange",
+            "Trade-Group",
+            "Comment",
+            "Date",
+            "Tx-ID",
+        ]
+        .map(String::from),
+    );
+    for (_, row) in rows {
+        out += &csv_line(&row);
+    }
+    out
+}
+
+// Inclusive start and exclusive end, either may be open
+type DateRange = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+fn range(options: &ExportOptions) -> AppResult<DateRange> {
+    if let Some(year) = options.year {
+        let start = |year| {
+            NaiveDate::from_ymd_opt(year, 1, 1)
+                .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+                .ok_or_else(|| AppError::InvalidInput(format!("Invalid year {}", year)))
+        };
+        return Ok((Some(start(year)?), Some(start(year + 1)?)));
+    }
+    Ok((
+        options.from.as_deref().map(parse_time).transpose()?,
+        options.to.as_deref().map(parse_time).transpose()?,
+    ))
+}
+
+// Native save dialog opened from here, so only a path the user picked is
+// ever written. None when the dialog was cancelled. Blocks until it closes.
+pub fn choose_path(app: &tauri::AppHandle, format: ExportFormat) -> AppResult<Option<PathBuf>> {
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter("CSV", &["csv"])
+        .set_file_name(format.file_name())
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    file.into_path()
+        .map(Some)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid export path: {}", e)))
+}
+
+// Build the report and write it to `path`, from choose_path()
+pub fn export(
+    app: &tauri::AppHandle,
+    path: &Path,
+    format: ExportFormat,
+    options: &ExportOptions,
+) -> AppResult<ExportSummary> {
+    if !path.is_absolute() {
+        return Err(AppError::InvalidInput(
+            "Export path must be absolute".to_string(),
+        ));
+    }
+
+    let (from, to) = range(options)?;
+    let in_range = |time: &DateTime<Utc>| {
+        from.is_none_or(|from| *time >= from) && to.is_none_or(|to| *time < to)
+    };
+
+    let history = load_history(app, options)?;
+    let lifo = format == ExportFormat::RealizedLifo;
+    let spot_gains = realize_spot(&history.spot_events, lifo);
+    let mut gains: Vec<&RealizedGain> = spot_gains
+        .iter()
+        .chain(history.margin.iter())
+        .filter(|gain| in_range(&gain.disposed_at))
+        .collect();
+    gains.sort_by_key(|gain| gain.disposed_at);
+
+    let events: Vec<&TradeEvent> = history
+        .events
+        .iter()
+        .filter(|e| in_range(&e.time))
+        .collect();
+    let spot: Vec<&TradeEvent> = history
+        .spot_events
+        .iter()
+        .filter(|e| in_range(&e.time))
+        .collect();
+    let margin: Vec<&RealizedGain> = gains
+        .iter()
+        .copied()
+        .filter(|gain| gain.kind == GainKind::Margin)
+        .collect();
+
+    let (content, rows) = match format {
+        ExportFormat::Generic => (generic_csv(&events), events.len()),
+        ExportFormat::RealizedFifo => (realized_csv(&gains, "FIFO"), gains.len()),
+        ExportFormat::RealizedLifo => (realized_csv(&gains, "LIFO"), gains.len()),
+        ExportFormat::Koinly => (koinly_csv(&spot, &margin), spot.len() + margin.len()),
+        ExportFormat::CoinTracking => (cointracking_csv(&spot, &margin), spot.len() + margin.len()),
+    };
+    std::fs::write(path, content)?;
+
+    Ok(ExportSummary {
+        path: path.display().to_string(),
+        rows,
+        proceeds: gains.iter().map(|gain| gain.proceeds).sum(),
+        cost_basis: gains.iter().map(|gain| gain.cost_basis).sum(),
+        fees: gains.iter().map(|gain| gain.fees).sum(),
+        realized_gain: gains.iter().map(|gain| gain.gain).sum(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        parse_time(value).unwrap()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn event(
+        position_id: &str,
+        asset: &str,
+        at: &str,
+        side: FillSide,
+        direction: Direction,
+        quantity: f64,
+        price: f64,
+        fee: f64,
+        tx_hash: Option<&str>,
+    ) -> TradeEvent {
+        TradeEvent {
+            position_id: position_id.to_string(),
+            asset: asset.to_string(),
+            time: time(at),
+            side,
+            direction,
+            quantity,
+            price,
+            amount: quantity * price,
+            fee,
+            tx_hash: tx_hash.map(String::from),
+        }
+    }
+
+    fn margin(
+        position_id: &str,
+        asset: &str,
+        at: &str,
+        gain: f64,
+        tx_hash: Option<&str>,
+    ) -> RealizedGain {
+        RealizedGain {
+            kind: GainKind::Margin,
+            position_id: position_id.to_string(),
+            asset: asset.to_string(),
+            quantity: 1.0,
+            acquired_at: None,
+            disposed_at: time(at),
+            proceeds: 100.0 + gain,
+            cost_basis: 100.0,
+            fees: 0.0,
+            gain,
+            tx_hash: tx_hash.map(String::from),
+        }
+    }
+
+    // Two purchases at 1000 and 2000, then 1.5 sold at 3000
+    fn spot_events() -> Vec<TradeEvent> {
+        vec![
+            event(
+                "p1",
+                "ETH",
+                "2025-01-01T00:00:00Z",
+                FillSide::Open,
+                Direction::Long,
+                1.0,
+                1000.0,
+                1.0,
+                None,
+            ),
+            event(
+                "p2",
+                "ETH",
+                "2025-01-02T00:00:00Z",
+                FillSide::Open,
+                Direction::Long,
+                1.0,
+                2000.0,
+                2.0,
+                None,
+            ),
+            event(
+                "p3",
+                "ETH",
+                "2025-01-03T00:00:00Z",
+                FillSide::Close,
+                Direction::Long,
+                1.5,
+                3000.0,
+                3.0,
+                None,
+            ),
+        ]
+    }
+
+    // quantity, acquired_at, proceeds, cost_basis, fees, gain
+    type Row = (f64, Option<DateTime<Utc>>, f64, f64, f64, f64);
+
+    fn summary(gains: &[RealizedGain]) -> Vec<Row> {
+        gains
+            .iter()
+            .map(|g| {
+                (
+                    g.quantity,
+                    g.acquired_at,
+                    g.proceeds,
+                    g.cost_basis,
+                    g.fees,
+                    g.gain,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fifo_sells_the_oldest_lot_first() {
+        // Fees are capitalized into the lots, 1001 and 2002 a unit
+        assert_eq!(
+            summary(&realize_spot(&spot_events(), false)),
+            [
+                (1.0, Some(time("2025-01-01")), 3000.0, 1001.0, 2.0, 1997.0),
+                (0.5, Some(time("2025-01-02")), 1500.0, 1001.0, 1.0, 498.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn lifo_sells_the_newest_lot_first() {
+        assert_eq!(
+            summary(&realize_spot(&spot_events(), true)),
+            [
+                (1.0, Some(time("2025-01-02")), 3000.0, 2002.0, 2.0, 996.0),
+                (0.5, Some(time("2025-01-01")), 1500.0, 500.5, 1.0, 998.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_sales_have_no_cost_basis() {
+        let events = [
+            event(
+                "p1",
+                "SOL",
+                "2025-01-01T00:00:00Z",
+                FillSide::Open,
+                Direction::Long,
+                1.0,
+                100.0,
+                0.0,
+                None,
+            ),
+            event(
+                "p1",
+                "SOL",
+                "2025-01-02T00:00:00Z",
+                FillSide::Close,
+                Direction::Long,
+                3.0,
+                100.0,
+                0.0,
+                None,
+            ),
+        ];
+        assert_eq!(
+            summary(&realize_spot(&events, false)),
+            [
+                (1.0, Some(time("2025-01-01")), 100.0, 100.0, 0.0, 0.0),
+                (2.0, None, 200.0, 0.0, 0.0, 200.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn generic_csv_labels_shorts() {
+        let events = [
+            event(
+                "p4",
+                "BTC",
+                "2025-01-01T00:00:00Z",
+                FillSide::Open,
+                Direction::Short,
+                0.1,
+                50000.0,
+                5.0,
+                Some("0x01"),
+            ),
+            event(
+                "p4",
+                "BTC",
+                "2025-01-02T00:00:00Z",
+                FillSide::Close,
+                Direction::Short,
+                0.1,
+                48000.0,
+                4.8,
+                None,
+            ),
+            event(
+                "p5",
+                "ETH",
+                "2025-01-03T00:00:00Z",
+                FillSide::Open,
+                Direction::Long,
+                2.0,
+                1500.0,
+                1.5,
+                None,
+            ),
+        ];
+        let events: Vec<&TradeEvent> = events.iter().collect();
+        assert_eq!(
+            generic_csv(&events),
+            "Date,Type,Asset,Quantity,Price,Amount,Fee,Currency,Position,Tx Hash\n\
+             2025-01-01T00:00:00+00:00,Sell,BTC,0.1,50000,5000,5,USDC,p4,0x01\n\
+             2025-01-02T00:00:00+00:00,Buy,BTC,0.1,48000,4800,4.8,USDC,p4,\n\
+             2025-01-03T00:00:00+00:00,Buy,ETH,2,1500,3000,1.5,USDC,p5,\n"
+        );
+    }
+
+    fn report_inputs() -> (Vec<TradeEvent>, Vec<RealizedGain>) {
+        (
+            vec![
+                event(
+                    "p1",
+                    "ETH",
+                    "2025-01-01T00:00:00Z",
+                    FillSide::Open,
+                    Direction::Long,
+                    1.0,
+                    1000.0,
+                    1.0,
+                    Some("0xaa"),
+                ),
+                event(
+                    "p1",
+                    "ETH",
+                    "2025-03-01T12:30:00Z",
+                    FillSide::Close,
+                    Direction::Long,
+                    1.0,
+                    3000.0,
+                    3.0,
+                    None,
+                ),
+            ],
+            vec![
+                margin("p2", "BTC", "2025-02-01T08:00:00Z", -25.5, Some("0xbb")),
+                margin("p3", "SOL", "2025-04-01T00:00:00Z", 10.0, None),
+            ],
+        )
+    }
+
+    #[test]
+    fn koinly_csv_format() {
+        let (spot, gains) = report_inputs();
+        let spot: Vec<&TradeEvent> = spot.iter().collect();
+        let gains: Vec<&RealizedGain> = gains.iter().collect();
+        assert_eq!(
+            koinly_csv(&spot, &gains),
+            "Date,Sent Amount,Sent Currency,Received Amount,Received Currency,Fee Amount,Fee Currency,Net Worth Amount,Net Worth Currency,Label,Description,TxHash\n\
+             2025-01-01 00:00 UTC,1000,USDC,1,ETH,1,USDC,,,,Monadier position p1,0xaa\n\
+             2025-02-01 08:00 UTC,25.5,USDC,,,,,,,realized gain,Monadier BTC margin position p2,0xbb\n\
+             2025-03-01 12:30 UTC,1,ETH,3000,USDC,3,USDC,,,,Monadier position p1,\n\
+             2025-04-01 00:00 UTC,,,10,USDC,,,,,realized gain,Monadier SOL margin position p3,\n"
+        );
+    }
+
+    #[test]
+    fn cointracking_csv_format() {
+        let (spot, gains) = report_inputs();
+        let spot: Vec<&TradeEvent> = spot.iter().collect();
+        let gains: Vec<&RealizedGain> = gains.iter().collect();
+        assert_eq!(
+            cointracking_csv(&spot, &gains),
+            "Type,Buy Amount,Buy Currency,Sell Amount,Sell Currency,Fee,Fee Currency,Exchange,Trade-Group,Comment,Date,Tx-ID\n\
+             Trade,1,ETH,1000,USDC,1,USDC,Monadier,p1,,2025-01-01 00:00:00,0xaa\n\
+             Margin Loss,,,25.5,USDC,,,Monadier,p2,BTC margin position,2025-02-01 08:00:00,0xbb\n\
+             Trade,3000,USDC,1,ETH,3,USDC,Monadier,p1,,2025-03-01 12:30:00,\n\
+             Margin Profit,10,USDC,,,,,Monadier,p3,SOL margin position,2025-04-01 00:00:00,\n"
+        );
+    }
+}
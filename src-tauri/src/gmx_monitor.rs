@@ -0,0 +1,532 @@
+use alloy::primitives::{Address, U256};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::alerts;
+use crate::chain::abi::{IGmxVault, IVault};
+use crate::chain::{self, RpcClient, VaultToken, PRICE_DECIMALS, USDC_DECIMALS};
+use crate::error::AppResult;
+use crate::settings;
+use crate::wallet;
+
+const GMX_MONITOR_SETTINGS_KEY: &str = "gmx_monitor";
+
+pub const POSITIONS_EVENT: &str = "gmx://positions";
+pub const ALERT_EVENT: &str = "gmx://alert";
+
+const MIN_POLL_SECS: u64 = 5;
+const BPS: f64 = 10_000.0;
+// GMX funding rates carry 6 decimals
+const FUNDING_RATE_DECIMALS: u8 = 6;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GmxMonitorSettings {
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+    // Alert once the mark price is this close to liquidation, in percent
+    pub liquidation_alert_percent: f64,
+    // Alert once the loss reaches this share of the collateral, in percent
+    pub loss_alert_percent: f64,
+    // Watched on top of the unlocked wallet
+    pub addresses: Vec<Address>,
+}
+
+impl Default for GmxMonitorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_secs: 30,
+            liquidation_alert_percent: 5.0,
+            loss_alert_percent: 50.0,
+            addresses: Vec::new(),
+        }
+    }
+}
+
+impl GmxMonitorSettings {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        settings::load(app, GMX_MONITOR_SETTINGS_KEY)
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> AppResult<()> {
+        settings::save(app, GMX_MONITOR_SETTINGS_KEY, self)?;
+        Ok(())
+    }
+}
+
+// One vault position marked against GMX. Prices and size in USD,
+// collateral and PnL in USDC.
+#[derive(Debug, Serialize, Clone)]
+pub struct PositionHealth {
+    pub user: Address,
+    pub token_symbol: String,
+    pub token: Address,
+    pub is_long: bool,
+    pub collateral: f64,
+    pub size: f64,
+    pub leverage: u64,
+    pub entry_price: f64,
+    pub mark_price: f64,
+    pub pnl: f64,
+    pub pnl_percent: f64,
+    // Close fee plus funding owed
+    pub fees: f64,
+    pub liquidation_price: f64,
+    // Negative once the mark is past the liquidation price
+    pub liquidation_distance_percent: f64,
+    // Still open in the vault but closed on GMX, needs a reconcile
+    pub orphaned: bool,
+    pub checked_at: String,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    LiquidationNear,
+    LossLimit,
+    Orphaned,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GmxAlert {
+    pub kind: AlertKind,
+    pub message: String,
+    pub position: PositionHealth,
+}
+
+// Last poll and the alerts it left raised, registered as Tauri state
+#[derive(Default)]
+pub struct GmxMonitorState {
+    positions: Mutex<Vec<PositionHealth>>,
+    // An alert fires again only after its condition cleared
+    raised: Mutex<HashSet<(Address, Address, AlertKind)>>,
+}
+
+// GMX Vault parameters the liquidation check depends on
+struct GmxParams {
+    margin_fee_bps: f64,
+    liquidation_fee: f64,
+    max_leverage_bps: f64,
+    // USDC cumulative funding rate
+    funding_rate: U256,
+}
+
+async fn gmx_params(rpc: &RpcClient<'_>) -> AppResult<GmxParams> {
+    let gmx = rpc.settings().gmx_vault_address;
+    let margin_fee_bps = rpc
+        .call(gmx, IGmxVault::marginFeeBasisPointsCall {})
+        .await?;
+    let liquidation_fee = rpc.call(gmx, IGmxVault::liquidationFeeUsdCall {}).await?;
+    let max_leverage = rpc.call(gmx, IGmxVault::maxLeverageCall {}).await?;
+    let funding_rate = rpc
+        .call(
+            gmx,
+            IGmxVault::cumulativeFundingRatesCall {
+                token: rpc.settings().usdc_address,
+            },
+        )
+        .await?;
+
+    Ok(GmxParams {
+        margin_fee_bps: chain::units(margin_fee_bps, 0),
+        liquidation_fee: chain::units(liquidation_fee, PRICE_DECIMALS),
+        max_leverage_bps: chain::units(max_leverage, 0),
+        funding_rate,
+    })
+}
+
+// Liquidation price the way GMX Vault.validateLiquidation decides it: the
+// remaining collateral must cover fees plus the liquidation fee and keep
+// leverage under the maximum
+fn liquidation_price(
+    params: &GmxParams,
+    is_long: bool,
+    entry: f64,
+    size: f64,
+    collateral: f64,
+    fees: f64,
+) -> f64 {
+    if size <= 0.0 || entry <= 0.0 {
+        return 0.0;
+    }
+    // A zero max leverage would divide by zero, GMX never sets one
+    let leverage_floor = if params.max_leverage_bps > 0.0 {
+        size * BPS / params.max_leverage_bps
+    } else {
+        0.0
+    };
+    let min_remaining = (fees + params.liquidation_fee).max(leverage_floor);
+    let max_loss = collateral - min_remaining;
+    if is_long {
+        (entry * (1.0 - max_loss / size)).max(0.0)
+    } else {
+        entry * (1.0 + max_loss / size)
+    }
+}
+
+// calculateLivePnL in vault.ts, on the USD size
+fn pnl(is_long: bool, entry: f64, mark: f64, size: f64) -> f64 {
+    if entry <= 0.0 {
+        return 0.0;
+    }
+    let delta = if is_long { mark - entry } else { entry - mark };
+    size * delta / entry
+}
+
+// Close fee plus the funding accrued since the GMX position's entry rate.
+// An orphaned position has no GMX side left to owe funding.
+fn fees(params: &GmxParams, size: f64, entry_funding_rate: U256, orphaned: bool) -> f64 {
+    let funding = if orphaned {
+        0.0
+    } else {
+        let rate = params.funding_rate.saturating_sub(entry_funding_rate);
+        size * chain::units(rate, FUNDING_RATE_DECIMALS)
+    };
+    size * params.margin_fee_bps / BPS + funding
+}
+
+// Percent the mark still has to move to reach liquidation
+fn liquidation_distance(is_long: bool, mark: f64, liquidation: f64) -> f64 {
+    if mark <= 0.0 {
+        0.0
+    } else if is_long {
+        (mark - liquidation) / mark * 100.0
+    } else {
+        (liquidation - mark) / mark * 100.0
+    }
+}
+
+async fn check(
+    rpc: &RpcClient<'_>,
+    params: &GmxParams,
+    user: Address,
+    token: &VaultToken,
+) -> AppResult<Option<PositionHealth>> {
+    let settings = rpc.settings();
+    let vault = settings.vault_address;
+    let position = rpc
+        .call(
+            vault,
+            IVault::getPositionCall {
+                user,
+                token: token.address,
+            },
+        )
+        .await?;
+    if !position.isActive {
+        return Ok(None);
+    }
+
+    // The vault's GMX position pools every user on this token and side
+    let gmx = rpc
+        .call(
+            settings.gmx_vault_address,
+            IGmxVault::getPositionCall {
+                account: vault,
+                collateralToken: settings.usdc_address,
+                indexToken: token.address,
+                isLong: position.isLong,
+            },
+        )
+        .await?;
+    // GMX closes longs at the min price and shorts at the max
+    let price = if position.isLong {
+        rpc.call(
+            settings.gmx_vault_address,
+            IGmxVault::getMinPriceCall {
+                token: token.address,
+            },
+        )
+        .await?
+    } else {
+        rpc.call(
+            settings.gmx_vault_address,
+            IGmxVault::getMaxPriceCall {
+                token: token.address,
+            },
+        )
+        .await?
+    };
+
+    let collateral = chain::units(position.collateral, USDC_DECIMALS);
+    let size = chain::units(position.size, PRICE_DECIMALS);
+    let entry = chain::units(position.entryPrice, PRICE_DECIMALS);
+    let mark = chain::units(price, PRICE_DECIMALS);
+    let orphaned = gmx.size.is_zero();
+
+    let pnl = pnl(position.isLong, entry, mark, size);
+    let fees = fees(params, size, gmx.entryFundingRate, orphaned);
+    let liquidation = liquidation_price(params, position.isLong, entry, size, collateral, fees);
+    let distance = liquidation_distance(position.isLong, mark, liquidation);
+
+    Ok(Some(PositionHealth {
+        user,
+        token_symbol: token.symbol.clone(),
+        token: token.address,
+        is_long: position.isLong,
+        collateral,
+        size,
+        leverage: position.leverage.try_into().unwrap_or(u64::MAX),
+        entry_price: entry,
+        mark_price: mark,
+        pnl,
+        pnl_percent: if collateral > 0.0 {
+            pnl / collateral * 100.0
+        } else {
+            0.0
+        },
+        fees,
+        liquidation_price: liquidation,
+        liquidation_distance_percent: distance,
+        orphaned,
+        checked_at: Utc::now().to_rfc3339(),
+    }))
+}
+
+fn watched(app: &tauri::AppHandle, settings: &GmxMonitorSettings) -> Vec<Address> {
+    let mut users = settings.addresses.clone();
+    if let Some(unlocked) = wallet::unlocked_address(app) {
+        if !users.contains(&unlocked) {
+            users.push(unlocked);
+        }
+    }
+    users
+}
+
+async fn poll(app: &tauri::AppHandle, settings: &GmxMonitorSettings) -> AppResult<()> {
+    let users = watched(app, settings);
+    let mut positions = Vec::new();
+    if !users.is_empty() {
+        let rpc = RpcClient::new(app);
+        let params = gmx_params(&rpc).await?;
+        for user in users {
+            for token in &rpc.settings().tokens {
+                // One unreadable position must not hide the others
+                match check(&rpc, &params, user, token).await {
+                    Ok(Some(health)) => positions.push(health),
+                    Ok(None) => {}
+                    Err(e) => log::warn!("GMX monitor skipped {} {}: {}", user, token.symbol, e),
+                }
+            }
+        }
+    }
+
+    raise_alerts(app, settings, &positions);
+    *app.state::<GmxMonitorState>().positions.lock().unwrap() = positions.clone();
+    if let Err(e) = app.emit(POSITIONS_EVENT, positions) {
+        log::error!("Failed to emit {}: {}", POSITIONS_EVENT, e);
+    }
+    Ok(())
+}
+
+fn raise_alerts(
+    app: &tauri::AppHandle,
+    settings: &GmxMonitorSettings,
+    positions: &[PositionHealth],
+) {
+    let state = app.state::<GmxMonitorState>();
+    let mut raised = state.raised.lock().unwrap();
+    // Closed positions take their alerts with them
+    raised.retain(|(user, token, _)| {
+        positions
+            .iter()
+            .any(|p| p.user == *user && p.token == *token)
+    });
+
+    for position in positions {
+        let side = if position.is_long { "long" } else { "short" };
+        let checks = [
+            (
+                AlertKind::LiquidationNear,
+                !position.orphaned
+                    && position.liquidation_distance_percent <= settings.liquidation_alert_percent,
+                format!(
+                    "{} {} is {:.2}% from liquidation at {:.2}",
+                    position.token_symbol,
+                    side,
+                    position.liquidation_distance_percent,
+                    position.liquidation_price
+                ),
+            ),
+            (
+                AlertKind::LossLimit,
+                position.pnl_percent <= -settings.loss_alert_percent,
+                format!(
+                    "{} {} is down {:.2} USDC ({:.1}%)",
+                    position.token_symbol, side, -position.pnl, -position.pnl_percent
+                ),
+            ),
+            (
+                AlertKind::Orphaned,
+                position.orphaned,
+                format!(
+                    "{} {} was closed on GMX but is still open in the vault, reconcile it",
+                    position.token_symbol, side
+                ),
+            ),
+        ];
+
+        for (kind, triggered, message) in checks {
+            let key = (position.user, position.token, kind);
+            if !triggered {
+                raised.remove(&key);
+                continue;
+            }
+            if !raised.insert(key) {
+                continue;
+            }
+            log::warn!("GMX alert for {}: {}", position.user, message);
+            alerts::notify(app, None, "GMX position alert", &message);
+            let alert = GmxAlert {
+                kind,
+                message,
+                position: position.clone(),
+            };
+            if let Err(e) = app.emit(ALERT_EVENT, alert) {
+                log::error!("Failed to emit {}: {}", ALERT_EVENT, e);
+            }
+        }
+    }
+}
+
+// Poll forever, started from run()'s setup hook. Independent of the server
+// bot, so alerts still arrive when it is down.
+pub async fn run(app: tauri::AppHandle) {
+    loop {
+        let settings = GmxMonitorSettings::load(&app);
+        if settings.enabled {
+            if let Err(e) = poll(&app, &settings).await {
+                log::warn!("GMX monitor poll failed: {}", e);
+            }
+        }
+
+        let interval = settings.poll_interval_secs.max(MIN_POLL_SECS);
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+pub fn positions(app: &tauri::AppHandle) -> Vec<PositionHealth> {
+    app.state::<GmxMonitorState>()
+        .positions
+        .lock()
+        .unwrap()
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // GMX v1 on Arbitrum: 0.1% margin fee, 5 USD liquidation fee, 100x max
+    fn params() -> GmxParams {
+        GmxParams {
+            margin_fee_bps: 10.0,
+            liquidation_fee: 5.0,
+            max_leverage_bps: 1_000_000.0,
+            funding_rate: U256::ZERO,
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn liquidates_a_long_when_fees_outweigh_the_leverage_floor() {
+        // 10x long, 1000 USD at 2000 with 100 collateral and 2% funding owed.
+        // Fees 1 + 20 + 5 beat the 10 USD floor at 100x, leaving 74 USD of
+        // loss: 7.4% down.
+        let params = GmxParams {
+            funding_rate: U256::from(20_000),
+            ..params()
+        };
+        let fees = fees(&params, 1000.0, U256::ZERO, false);
+        assert!(close(fees, 21.0));
+        let price = liquidation_price(&params, true, 2000.0, 1000.0, 100.0, fees);
+        assert!(close(price, 1852.0));
+        assert!(close(pnl(true, 2000.0, price, 1000.0), -74.0));
+    }
+
+    #[test]
+    fn liquidates_a_short_at_the_max_leverage() {
+        // 100 collateral at 50x max: 1000 USD size must keep 20 USD, which
+        // beats 1 + 5 in fees, so the short survives an 8% move up.
+        let params = GmxParams {
+            max_leverage_bps: 500_000.0,
+            ..params()
+        };
+        let price = liquidation_price(&params, false, 2000.0, 1000.0, 100.0, 1.0);
+        assert!(close(price, 2160.0));
+        assert!(close(pnl(false, 2000.0, price, 1000.0), -80.0));
+        assert!(close(liquidation_distance(false, 2000.0, price), 8.0));
+    }
+
+    #[test]
+    fn underwater_long_liquidates_above_entry() {
+        // The 10 USD floor exceeds the collateral, so it is already past
+        let price = liquidation_price(&params(), true, 2000.0, 1000.0, 4.0, 1.0);
+        assert!(close(price, 2012.0));
+        assert!(liquidation_distance(true, 2000.0, price) < 0.0);
+    }
+
+    #[test]
+    fn long_liquidation_price_never_goes_negative() {
+        // Over-collateralised, the long can never lose enough
+        let price = liquidation_price(&params(), true, 2000.0, 100.0, 200.0, 0.0);
+        assert_eq!(price, 0.0);
+    }
+
+    #[test]
+    fn zero_parameters_do_not_divide_by_zero() {
+        let zero = GmxParams {
+            margin_fee_bps: 0.0,
+            liquidation_fee: 0.0,
+            max_leverage_bps: 0.0,
+            funding_rate: U256::ZERO,
+        };
+        let price = liquidation_price(&zero, true, 2000.0, 1000.0, 100.0, 0.0);
+        assert!(close(price, 1800.0));
+        assert_eq!(
+            liquidation_price(&params(), true, 0.0, 1000.0, 100.0, 0.0),
+            0.0
+        );
+        assert_eq!(
+            liquidation_price(&params(), false, 2000.0, 0.0, 100.0, 0.0),
+            0.0
+        );
+        assert_eq!(pnl(true, 0.0, 2000.0, 1000.0), 0.0);
+        assert_eq!(liquidation_distance(true, 0.0, 1800.0), 0.0);
+    }
+
+    #[test]
+    fn pnl_follows_the_side() {
+        assert!(close(pnl(true, 2000.0, 2100.0, 1000.0), 50.0));
+        assert!(close(pnl(false, 2000.0, 2100.0, 1000.0), -50.0));
+        assert!(close(pnl(false, 2000.0, 1900.0, 1000.0), 50.0));
+    }
+
+    #[test]
+    fn fees_add_funding_accrued_since_entry() {
+        // Cumulative rate moved from 1000 to 1250, 0.025% of the size
+        let params = GmxParams {
+            funding_rate: U256::from(1250),
+            ..params()
+        };
+        let fees = fees(&params, 1000.0, U256::from(1000), false);
+        assert!(close(fees, 1.0 + 0.25));
+        // A rate below the entry rate owes nothing rather than underflowing
+        assert!(close(
+            super::fees(&params, 1000.0, U256::from(2000), false),
+            1.0
+        ));
+        assert!(close(
+            super::fees(&params, 1000.0, U256::from(1000), true),
+            1.0
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::config::BackendConfig;
+use crate::error::{AppError, AppResult};
+use crate::settings;
+
+const HTTP_SETTINGS_KEY: &str = "http";
+
+const USER_AGENT: &str = concat!("Monadier-Desktop/", env!("CARGO_PKG_VERSION"));
+
+// Ceiling for a single backoff sleep
+const MAX_BACKOFF_MS: u64 = 8_000;
+
+// User-tunable network settings, persisted in settings.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HttpSettings {
+    // Explicit proxy, e.g. http://proxy.corp:3128. When unset the system
+    // and HTTP(S)_PROXY / NO_PROXY settings apply.
+    pub proxy_url: Option<String>,
+    pub connect_timeout_secs: u64,
+    pub read_timeout_secs: u64,
+    // Extra attempts for idempotent requests
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            proxy_url: None,
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            max_retries: 3,
+            retry_base_delay_ms: 500,
+        }
+    }
+}
+
+impl HttpSettings {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        settings::load(app, HTTP_SETTINGS_KEY)
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> AppResult<()> {
+        settings::save(app, HTTP_SETTINGS_KEY, self)?;
+        Ok(())
+    }
+
+    fn builder(&self) -> AppResult<reqwest::ClientBuilder> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .read_timeout(Duration::from_secs(self.read_timeout_secs));
+
+        if let Some(proxy_url) = self.proxy_url.as_deref().filter(|url| !url.is_empty()) {
+            let proxy = reqwest::Proxy::all(proxy_url)
+                .map_err(|e| AppError::InvalidInput(format!("Invalid proxy URL: {}", e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        Ok(builder)
+    }
+}
+
+struct Clients {
+    settings: HttpSettings,
+    // Exchanges, RPC nodes and anything else that is not our backend
+    general: reqwest::Client,
+    // Supabase backend, with pinned roots and the anon key headers
+    backend: reqwest::Client,
+}
+
+impl Clients {
+    fn build(settings: HttpSettings) -> AppResult<Self> {
+        let build_error = |e: reqwest::Error| AppError::Internal(format!("HTTP client: {}", e));
+
+        let general = settings.builder()?.build().map_err(build_error)?;
+        let backend = match BackendConfig::active() {
+            Ok(config) => config
+                .apply(settings.builder()?)?
+                .build()
+                .map_err(build_error)?,
+            Err(e) => {
+                log::warn!("Backend config unavailable, using plain client: {}", e);
+                general.clone()
+            }
+        };
+
+        Ok(Self {
+            settings,
+            general,
+            backend,
+        })
+    }
+}
+
+// Shared HTTP clients, registered as Tauri state in run()
+pub struct HttpClient(RwLock<Clients>);
+
+impl HttpClient {
+    pub fn new(settings: HttpSettings) -> AppResult<Self> {
+        Ok(Self(RwLock::new(Clients::build(settings)?)))
+    }
+
+    pub fn settings(&self) -> HttpSettings {
+        self.0.read().unwrap().settings.clone()
+    }
+
+    // Rebuild the clients after the user changed proxy or timeouts
+    pub fn reconfigure(&self, settings: HttpSettings) -> AppResult<()> {
+        let clients = Clients::build(settings)?;
+        *self.0.write().unwrap() = clients;
+        Ok(())
+    }
+
+    pub fn general(&self) -> reqwest::Client {
+        self.0.read().unwrap().general.clone()
+    }
+
+    pub fn backend(&self) -> reqwest::Client {
+        self.0.read().unwrap().backend.clone()
+    }
+
+    // Send an idempotent request, retrying transport errors, 429 and 5xx
+    // with exponential backoff. The last response is returned as is.
+    pub async fn send_idempotent(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> AppResult<reqwest::Response> {
+        let settings = self.settings();
+        let mut attempt = 0;
+
+        loop {
+            let result = request().send().await;
+
+            let retry = match &result {
+                Ok(response) => {
+                    let status = response.status();
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+
+            if !retry || attempt >= settings.max_retries {
+                return result.map_err(AppError::from);
+            }
+
+            let delay = backoff_delay(settings.retry_base_delay_ms, attempt);
+            log::info!(
+                "Retrying request in {}ms (attempt {}/{})",
+                delay.as_millis(),
+                attempt + 1,
+                settings.max_retries
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+// base * 2^attempt plus up to 25% jitter, capped
+pub fn backoff_delay(base_ms: u64, attempt: u32) -> Duration {
+    let exponential = base_ms.saturating_mul(1u64 << attempt.min(16));
+    let capped = exponential.min(MAX_BACKOFF_MS);
+    let jitter = rand::random::<u64>() % (capped / 4 + 1);
+    Duration::from_millis(capped + jitter)
+}
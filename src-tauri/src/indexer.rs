@@ -0,0 +1,1098 @@
+use alloy::primitives::{Address, Bytes, B256, U64};
+use alloy::sol_types::SolEvent;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use tauri::{Emitter, Manager};
+
+use crate::chain::abi::{IVault, IVaultV7, IERC20};
+use crate::chain::{self, ChainSettings, RpcClient, USDC_DECIMALS};
+use crate::error::{AppError, AppResult};
+use crate::journal;
+use crate::settings;
+use crate::vaults::{self, VaultVersion};
+
+const INDEXER_SETTINGS_KEY: &str = "indexer";
+
+pub const PROGRESS_EVENT: &str = "indexer://progress";
+
+// check_deposits.ts scans from here, before any of the vaults existed
+const DEFAULT_START_BLOCK: u64 = 280_000_000;
+
+const DEFAULT_BLOCK_RANGE: u64 = 50_000;
+// Ranges halve down to this when a node refuses a large eth_getLogs
+const MIN_BLOCK_RANGE: u64 = 500;
+// Arbitrum reorgs are rare and shallow
+const DEFAULT_CONFIRMATIONS: u64 = 20;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexedVault {
+    pub label: String,
+    pub address: Address,
+    // Nothing before this block is fetched
+    pub start_block: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct IndexerSettings {
+    pub vaults: Vec<IndexedVault>,
+    pub block_range: u64,
+    pub confirmations: u64,
+}
+
+impl Default for IndexerSettings {
+    fn default() -> Self {
+        Self {
+            vaults: default_vaults(&ChainSettings::default()),
+            block_range: DEFAULT_BLOCK_RANGE,
+            confirmations: DEFAULT_CONFIRMATIONS,
+        }
+    }
+}
+
+// Every deployment on the chain that emits the events decode_log knows,
+// V7 and later, then the configured vault
+fn default_vaults(chain: &ChainSettings) -> Vec<IndexedVault> {
+    let vault = |label: &str, address| IndexedVault {
+        label: label.to_string(),
+        address,
+        start_block: DEFAULT_START_BLOCK,
+    };
+    let mut vaults: Vec<IndexedVault> = vaults::DEPLOYMENTS
+        .iter()
+        .filter(|d| d.chain_id == chain.chain_id && d.address != chain.vault_address)
+        .filter(|d| {
+            !matches!(
+                d.version,
+                VaultVersion::V1
+                    | VaultVersion::V2
+                    | VaultVersion::V3
+                    | VaultVersion::V5
+                    | VaultVersion::V6
+            )
+        })
+        .map(|d| vault(d.label, d.address))
+        .collect();
+    vaults.push(vault("Current", chain.vault_address));
+    vaults
+}
+
+impl IndexerSettings {
+    // Never saved settings index the deployments of the configured chain
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        settings::load::<Option<Self>>(app, INDEXER_SETTINGS_KEY)
+            .unwrap_or_else(|| Self {
+                vaults: default_vaults(&ChainSettings::load(app)),
+                ..Self::default()
+            })
+            .checked()
+    }
+
+    // The store is editable by hand, a range below the minimum falls back
+    // to the default
+    fn checked(mut self) -> Self {
+        if self.block_range < MIN_BLOCK_RANGE {
+            log::warn!(
+                "Ignoring stored block range {}, using {}",
+                self.block_range,
+                DEFAULT_BLOCK_RANGE
+            );
+            self.block_range = DEFAULT_BLOCK_RANGE;
+        }
+        self
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> AppResult<()> {
+        if self.block_range < MIN_BLOCK_RANGE {
+            return Err(AppError::InvalidInput(format!(
+                "Block range must be at least {}",
+                MIN_BLOCK_RANGE
+            )));
+        }
+        settings::save(app, INDEXER_SETTINGS_KEY, self)?;
+        Ok(())
+    }
+}
+
+// Held for the duration of a sync so two never write the same cursor
+#[derive(Default)]
+pub struct IndexerState(tokio::sync::Mutex<()>);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IndexProgress {
+    pub label: String,
+    pub vault: Address,
+    pub last_block: u64,
+    pub head: u64,
+    pub events: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcLog {
+    topics: Vec<B256>,
+    data: Bytes,
+    block_number: U64,
+    transaction_hash: B256,
+    log_index: U64,
+    #[serde(default)]
+    removed: bool,
+}
+
+// One decoded log as stored in vault_events. Amounts are USDC, the pnl of
+// a close is signed.
+struct VaultEvent {
+    kind: &'static str,
+    user: Address,
+    token: Option<Address>,
+    amount: f64,
+    detail: Option<Value>,
+}
+
+fn usdc(value: impl Into<alloy::primitives::utils::ParseUnits>) -> f64 {
+    chain::units(value, USDC_DECIMALS)
+}
+
+fn decode<E: SolEvent>(log: &RpcLog) -> AppResult<E> {
+    E::decode_raw_log(log.topics.iter().copied(), &log.data).map_err(|e| {
+        AppError::ServerUnavailable(format!(
+            "Cannot decode {} in {}: {}",
+            E::SIGNATURE,
+            log.transaction_hash,
+            e
+        ))
+    })
+}
+
+// Every vault event the ledger cares about, V7 and V8+ layouts
+const VAULT_TOPICS: &[B256] = &[
+    IVault::Deposit::SIGNATURE_HASH,
+    IVault::Withdraw::SIGNATURE_HASH,
+    IVault::EmergencyWithdraw::SIGNATURE_HASH,
+    IVault::PositionOpened::SIGNATURE_HASH,
+    IVault::PositionClosed::SIGNATURE_HASH,
+    IVault::PositionCancelled::SIGNATURE_HASH,
+    IVault::UserInstantClose::SIGNATURE_HASH,
+    IVault::PositionReconciled::SIGNATURE_HASH,
+    IVault::AdminCredited::SIGNATURE_HASH,
+    IVaultV7::Deposited::SIGNATURE_HASH,
+    IVaultV7::Withdrawn::SIGNATURE_HASH,
+    IVaultV7::PositionRequested::SIGNATURE_HASH,
+    IVaultV7::PositionClosed::SIGNATURE_HASH,
+];
+
+fn decode_log(vault: Address, log: &RpcLog) -> AppResult<Option<VaultEvent>> {
+    let Some(topic) = log.topics.first() else {
+        return Ok(None);
+    };
+    let event = |kind, user, token, amount, detail| {
+        Ok(Some(VaultEvent {
+            kind,
+            user,
+            token,
+            amount,
+            detail,
+        }))
+    };
+
+    match *topic {
+        IVault::Deposit::SIGNATURE_HASH => {
+            let e = decode::<IVault::Deposit>(log)?;
+            event("deposit", e.user, None, usdc(e.amount), None)
+        }
+        IVaultV7::Deposited::SIGNATURE_HASH => {
+            let e = decode::<IVaultV7::Deposited>(log)?;
+            event("deposit", e.user, None, usdc(e.amount), None)
+        }
+        IVault::Withdraw::SIGNATURE_HASH => {
+            let e = decode::<IVault::Withdraw>(log)?;
+            event("withdraw", e.user, None, usdc(e.amount), None)
+        }
+        IVaultV7::Withdrawn::SIGNATURE_HASH => {
+            let e = decode::<IVaultV7::Withdrawn>(log)?;
+            event("withdraw", e.user, None, usdc(e.amount), None)
+        }
+        IVault::EmergencyWithdraw::SIGNATURE_HASH => {
+            let e = decode::<IVault::EmergencyWithdraw>(log)?;
+            let detail = json!({ "requested": usdc(e.requested) });
+            event(
+                "emergency_withdraw",
+                e.user,
+                None,
+                usdc(e.amount),
+                Some(detail),
+            )
+        }
+        IVault::PositionOpened::SIGNATURE_HASH => {
+            let e = decode::<IVault::PositionOpened>(log)?;
+            let detail = json!({ "is_long": e.isLong, "leverage": e.leverage.to_string() });
+            event(
+                "position_opened",
+                e.user,
+                Some(e.token),
+                usdc(e.collateral),
+                Some(detail),
+            )
+        }
+        IVaultV7::PositionRequested::SIGNATURE_HASH => {
+            let e = decode::<IVaultV7::PositionRequested>(log)?;
+            let detail = json!({ "is_long": e.isLong, "leverage": e.leverage.to_string() });
+            event(
+                "position_opened",
+                e.user,
+                Some(e.indexToken),
+                usdc(e.collateral),
+                Some(detail),
+            )
+        }
+        IVault::PositionClosed::SIGNATURE_HASH => {
+            let e = decode::<IVault::PositionClosed>(log)?;
+            let detail = json!({ "reason": e.reason });
+            event(
+                "position_closed",
+                e.user,
+                Some(e.token),
+                usdc(e.pnl),
+                Some(detail),
+            )
+        }
+        // V7 only emits this from finalizeClose, so it always settles
+        IVaultV7::PositionClosed::SIGNATURE_HASH => {
+            let e = decode::<IVaultV7::PositionClosed>(log)?;
+            let detail = json!({ "reason": e.reason, "settled": true });
+            event(
+                "position_closed",
+                e.user,
+                Some(e.indexToken),
+                usdc(e.pnl),
+                Some(detail),
+            )
+        }
+        IVault::PositionCancelled::SIGNATURE_HASH => {
+            let e = decode::<IVault::PositionCancelled>(log)?;
+            event(
+                "position_cancelled",
+                e.user,
+                Some(e.token),
+                usdc(e.refundAmount),
+                None,
+            )
+        }
+        IVault::UserInstantClose::SIGNATURE_HASH => {
+            let e = decode::<IVault::UserInstantClose>(log)?;
+            let detail = json!({ "pnl": usdc(e.pnl) });
+            event(
+                "user_instant_close",
+                e.user,
+                Some(e.token),
+                usdc(e.returnAmount),
+                Some(detail),
+            )
+        }
+        IVault::PositionReconciled::SIGNATURE_HASH => {
+            let e = decode::<IVault::PositionReconciled>(log)?;
+            let detail = json!({ "reconciled_by": e.reconciledBy });
+            event(
+                "position_reconciled",
+                e.user,
+                Some(e.token),
+                usdc(e.creditedAmount),
+                Some(detail),
+            )
+        }
+        IVault::AdminCredited::SIGNATURE_HASH => {
+            let e = decode::<IVault::AdminCredited>(log)?;
+            event("admin_credited", e.user, None, usdc(e.amount), None)
+        }
+        // Raw USDC movements, the counterparty goes in `user`
+        IERC20::Transfer::SIGNATURE_HASH => {
+            let e = decode::<IERC20::Transfer>(log)?;
+            if e.to == vault {
+                event("transfer_in", e.from, None, usdc(e.value), None)
+            } else if e.from == vault {
+                event("transfer_out", e.to, None, usdc(e.value), None)
+            } else {
+                Ok(None)
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+async fn get_logs(rpc: &RpcClient<'_>, filter: Value) -> AppResult<Vec<RpcLog>> {
+    rpc.request("eth_getLogs", json!([filter])).await
+}
+
+// Vault events plus USDC transfers into and out of the vault
+async fn fetch_range(
+    rpc: &RpcClient<'_>,
+    vault: Address,
+    from: u64,
+    to: u64,
+) -> AppResult<Vec<RpcLog>> {
+    let usdc = rpc.settings().usdc_address;
+    let from = U64::from(from);
+    let to = U64::from(to);
+    let vault_topic = vault.into_word();
+    let transfer = IERC20::Transfer::SIGNATURE_HASH;
+
+    let mut logs = get_logs(
+        rpc,
+        json!({ "fromBlock": from, "toBlock": to, "address": vault, "topics": [VAULT_TOPICS] }),
+    )
+    .await?;
+    logs.extend(
+        get_logs(
+            rpc,
+            json!({ "fromBlock": from, "toBlock": to, "address": usdc, "topics": [transfer, null, vault_topic] }),
+        )
+        .await?,
+    );
+    logs.extend(
+        get_logs(
+            rpc,
+            json!({ "fromBlock": from, "toBlock": to, "address": usdc, "topics": [transfer, vault_topic] }),
+        )
+        .await?,
+    );
+    Ok(logs)
+}
+
+fn cursor(app: &tauri::AppHandle, vault: Address) -> AppResult<Option<u64>> {
+    journal::with_conn(app, |conn| {
+        Ok(conn
+            .query_row(
+                "SELECT last_block FROM index_cursors WHERE vault = ?1",
+                params![vault.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?)
+    })
+}
+
+// Events and the cursor move together, so a crash mid-range refetches it
+fn store_range(
+    app: &tauri::AppHandle,
+    vault: Address,
+    logs: &[RpcLog],
+    last_block: u64,
+) -> AppResult<usize> {
+    let mut decoded = Vec::new();
+    for log in logs.iter().filter(|log| !log.removed) {
+        if let Some(event) = decode_log(vault, log)? {
+            decoded.push((log, event));
+        }
+    }
+
+    journal::with_conn(app, |conn| {
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        for (log, event) in &decoded {
+            inserted += tx.execute(
+                "INSERT OR IGNORE INTO vault_events
+                 (vault, block_number, log_index, tx_hash, kind, user, token, amount, detail)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    vault.to_string(),
+                    log.block_number.to::<u64>(),
+                    log.log_index.to::<u64>(),
+                    log.transaction_hash.to_string(),
+                    event.kind,
+                    event.user.to_string(),
+                    event.token.map(|token| token.to_string()),
+                    event.amount,
+                    event.detail.as_ref().map(Value::to_string),
+                ],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO index_cursors (vault, last_block, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(vault) DO UPDATE SET last_block = excluded.last_block,
+                 updated_at = excluded.updated_at",
+            params![vault.to_string(), last_block, journal::now()],
+        )?;
+        tx.commit()?;
+        Ok(inserted)
+    })
+}
+
+// Pull every configured vault up to the confirmed head, resuming from the
+// stored cursors
+pub async fn sync(app: &tauri::AppHandle) -> AppResult<Vec<IndexProgress>> {
+    let state = app.state::<IndexerState>();
+    let _running = state
+        .0
+        .try_lock()
+        .map_err(|_| AppError::InvalidInput("Indexing is already running".into()))?;
+
+    let settings = IndexerSettings::load(app);
+    let rpc = RpcClient::new(app);
+    let latest: U64 = rpc.request("eth_blockNumber", json!([])).await?;
+    let head = latest.to::<u64>().saturating_sub(settings.confirmations);
+
+    let mut reports = Vec::new();
+    for vault in &settings.vaults {
+        let mut from = cursor(app, vault.address)?.map_or(vault.start_block, |last| last + 1);
+        let mut range = settings.block_range;
+        let mut report = IndexProgress {
+            label: vault.label.clone(),
+            vault: vault.address,
+            last_block: from.saturating_sub(1),
+            head,
+            events: 0,
+        };
+
+        while from <= head {
+            let to = from.saturating_add(range - 1).min(head);
+            match fetch_range(&rpc, vault.address, from, to).await {
+                Ok(logs) => {
+                    report.events += store_range(app, vault.address, &logs, to)?;
+                    report.last_block = to;
+                    if let Err(e) = app.emit(PROGRESS_EVENT, &report) {
+                        log::warn!("Failed to emit indexer progress: {}", e);
+                    }
+                    from = to + 1;
+                    range = range.saturating_mul(2).min(settings.block_range);
+                }
+                Err(e) if range > MIN_BLOCK_RANGE => {
+                    log::info!(
+                        "eth_getLogs {}..{} for {} failed, narrowing: {}",
+                        from,
+                        to,
+                        vault.label,
+                        e
+                    );
+                    range = (range / 2).max(MIN_BLOCK_RANGE);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        log::info!(
+            "Indexed {} up to block {}, {} new events",
+            vault.label,
+            report.last_block,
+            report.events
+        );
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
+
+#[derive(Debug, Serialize)]
+pub struct LedgerEntry {
+    pub vault: Address,
+    pub block_number: u64,
+    pub tx_hash: String,
+    pub kind: String,
+    pub token: Option<String>,
+    // As emitted, the pnl for closes
+    pub amount: f64,
+    // Effect on the user's vault balance
+    pub balance_change: f64,
+    pub balance: f64,
+    pub in_positions: f64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultLedger {
+    pub label: String,
+    pub vault: Address,
+    pub deposited: f64,
+    pub withdrawn: f64,
+    pub realized_pnl: f64,
+    // Reconstructed from events
+    pub balance: f64,
+    // Collateral in positions that never settled
+    pub in_positions: f64,
+    // USDC the user sent without a Deposit event
+    pub uncredited: f64,
+    pub on_chain_balance: Option<f64>,
+    // On-chain minus reconstructed balance. Opening fees are not in any
+    // event, so a small negative value is expected.
+    pub unexplained: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserLedger {
+    pub user: Address,
+    pub vaults: Vec<VaultLedger>,
+    pub entries: Vec<LedgerEntry>,
+}
+
+struct EventRow {
+    vault: String,
+    block_number: u64,
+    tx_hash: String,
+    kind: String,
+    token: Option<String>,
+    amount: f64,
+    detail: Option<Value>,
+}
+
+fn user_events(app: &tauri::AppHandle, user: Address) -> AppResult<Vec<EventRow>> {
+    journal::with_conn(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT vault, block_number, tx_hash, kind, token, amount, detail
+             FROM vault_events WHERE user = ?1 ORDER BY block_number, log_index",
+        )?;
+        let rows = statement.query_map(params![user.to_string()], |row| {
+            let detail: Option<String> = row.get(6)?;
+            Ok(EventRow {
+                vault: row.get(0)?,
+                block_number: row.get(1)?,
+                tx_hash: row.get(2)?,
+                kind: row.get(3)?,
+                token: row.get(4)?,
+                amount: row.get(5)?,
+                detail: detail.and_then(|detail| serde_json::from_str(&detail).ok()),
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    })
+}
+
+#[derive(Default)]
+struct Running {
+    summary: Option<VaultLedger>,
+    // Open collateral per token
+    open: HashMap<String, f64>,
+}
+
+// Replay one user's events per vault, the way the contracts move `balances`
+fn replay(
+    rows: Vec<EventRow>,
+    settings: &IndexerSettings,
+) -> AppResult<(Vec<VaultLedger>, Vec<LedgerEntry>)> {
+    // Instant closes and reconciles credit on their own event and emit a
+    // PositionClosed alongside, which must not count twice
+    let kinds_in_tx = |kinds: &[&str]| -> HashSet<String> {
+        rows.iter()
+            .filter(|row| kinds.contains(&row.kind.as_str()))
+            .map(|row| row.tx_hash.clone())
+            .collect()
+    };
+    let credited_elsewhere = kinds_in_tx(&["user_instant_close", "position_reconciled"]);
+    let deposits = kinds_in_tx(&["deposit"]);
+    let withdrawals = kinds_in_tx(&["withdraw", "emergency_withdraw"]);
+
+    let mut vaults: HashMap<String, Running> = HashMap::new();
+    let mut entries = Vec::new();
+    for row in rows {
+        let running = vaults.entry(row.vault.clone()).or_default();
+        let vault: Address = row
+            .vault
+            .parse()
+            .map_err(|_| AppError::StoreCorrupted(format!("Bad vault address {}", row.vault)))?;
+        let summary = running.summary.get_or_insert_with(|| VaultLedger {
+            label: settings
+                .vaults
+                .iter()
+                .find(|v| v.address == vault)
+                .map_or_else(|| vault.to_string(), |v| v.label.clone()),
+            vault,
+            deposited: 0.0,
+            withdrawn: 0.0,
+            realized_pnl: 0.0,
+            balance: 0.0,
+            in_positions: 0.0,
+            uncredited: 0.0,
+            on_chain_balance: None,
+            unexplained: None,
+        });
+        let token = row.token.clone().unwrap_or_default();
+        let detail = |key: &str| {
+            row.detail
+                .as_ref()
+                .and_then(|detail| detail.get(key).cloned())
+        };
+
+        let mut note = None;
+        let balance_change = match row.kind.as_str() {
+            "deposit" | "admin_credited" => {
+                summary.deposited += row.amount;
+                row.amount
+            }
+            "withdraw" | "emergency_withdraw" => {
+                summary.withdrawn += row.amount;
+                -row.amount
+            }
+            "position_opened" => {
+                *running.open.entry(token).or_default() += row.amount;
+                summary.in_positions += row.amount;
+                -row.amount
+            }
+            "position_closed" if credited_elsewhere.contains(&row.tx_hash) => 0.0,
+            "position_closed" => {
+                let settled = detail("settled").and_then(|v| v.as_bool()) == Some(true);
+                // V8+ keepers emit a zero-pnl close when they request it,
+                // finalizeClose emits the real one later
+                if !settled && row.amount == 0.0 {
+                    note = Some("Close requested, not settled yet".to_string());
+                    0.0
+                } else {
+                    let collateral = running.open.remove(&token).unwrap_or(0.0);
+                    summary.in_positions -= collateral;
+                    summary.realized_pnl += row.amount;
+                    (collateral + row.amount).max(0.0)
+                }
+            }
+            "user_instant_close" | "position_reconciled" | "position_cancelled" => {
+                let collateral = running.open.remove(&token).unwrap_or(0.0);
+                summary.in_positions -= collateral;
+                summary.realized_pnl += row.amount - collateral;
+                row.amount
+            }
+            "transfer_in" if !deposits.contains(&row.tx_hash) => {
+                summary.uncredited += row.amount;
+                note = Some("USDC sent to the vault without a deposit".to_string());
+                0.0
+            }
+            "transfer_out" if !withdrawals.contains(&row.tx_hash) => {
+                note = Some("USDC sent from the vault outside a withdrawal".to_string());
+                0.0
+            }
+            _ => 0.0,
+        };
+        if note.is_none() {
+            note = detail("reason").and_then(|v| v.as_str().map(str::to_string));
+        }
+
+        summary.balance += balance_change;
+        entries.push(LedgerEntry {
+            vault,
+            block_number: row.block_number,
+            tx_hash: row.tx_hash,
+            kind: row.kind,
+            token: row.token,
+            amount: row.amount,
+            balance_change,
+            balance: summary.balance,
+            in_positions: summary.in_positions,
+            note,
+        });
+    }
+
+    let mut summaries: Vec<VaultLedger> = vaults
+        .into_values()
+        .filter_map(|running| running.summary)
+        .collect();
+    summaries.sort_by(|a, b| a.label.cmp(&b.label));
+    Ok((summaries, entries))
+}
+
+// Replay the user's indexed events and compare with what the vaults report now
+pub async fn ledger(app: &tauri::AppHandle, user: &str) -> AppResult<UserLedger> {
+    let user: Address = user
+        .parse()
+        .map_err(|_| AppError::InvalidInput(format!("Invalid address: {}", user)))?;
+    let settings = IndexerSettings::load(app);
+    let (mut summaries, entries) = replay(user_events(app, user)?, &settings)?;
+
+    let rpc = RpcClient::new(app);
+    for summary in &mut summaries {
+        match rpc.call(summary.vault, IVault::balancesCall { user }).await {
+            Ok(balance) => {
+                let on_chain = usdc(balance);
+                summary.on_chain_balance = Some(on_chain);
+                summary.unexplained = Some(on_chain - summary.balance);
+            }
+            Err(e) => log::warn!("balances() on {} failed: {}", summary.vault, e),
+        }
+    }
+
+    Ok(UserLedger {
+        user,
+        vaults: summaries,
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{keccak256, I256, U256};
+
+    const VAULT: Address = Address::repeat_byte(0xaa);
+    const USER: Address = Address::repeat_byte(0x11);
+    const TOKEN: Address = Address::repeat_byte(0x22);
+
+    fn uint(value: u64) -> B256 {
+        U256::from(value).into()
+    }
+
+    fn int(value: i64) -> B256 {
+        I256::try_from(value).unwrap().into_raw().into()
+    }
+
+    // Offset, length and padded bytes of a string that follows `head` words
+    fn string(head: u64, value: &str) -> Vec<B256> {
+        let mut padded = value.as_bytes().to_vec();
+        padded.resize(value.len().div_ceil(32) * 32, 0);
+        let mut words = vec![uint(head * 32), uint(value.len() as u64)];
+        words.extend(padded.chunks(32).map(B256::from_slice));
+        words
+    }
+
+    // A log as eth_getLogs returns it, with the topic hashed from the
+    // Solidity signature rather than taken from the bindings
+    fn log(signature: &str, indexed: &[Address], words: &[B256]) -> RpcLog {
+        let mut topics = vec![keccak256(signature)];
+        topics.extend(indexed.iter().map(|address| address.into_word()));
+        serde_json::from_value(json!({
+            "address": VAULT,
+            "topics": topics,
+            "data": Bytes::from(words.concat()),
+            "blockNumber": "0x10b0c2a0",
+            "transactionHash": B256::repeat_byte(0x99),
+            "logIndex": "0x3",
+            "removed": false
+        }))
+        .unwrap()
+    }
+
+    fn decoded(log: &RpcLog) -> VaultEvent {
+        decode_log(VAULT, log).unwrap().expect("not a ledger event")
+    }
+
+    #[test]
+    fn decodes_v8_events() {
+        let deposit = decoded(&log(
+            "Deposit(address,uint256)",
+            &[USER],
+            &[uint(25_000_000)],
+        ));
+        assert_eq!(
+            (deposit.kind, deposit.user, deposit.amount),
+            ("deposit", USER, 25.0)
+        );
+
+        let emergency = decoded(&log(
+            "EmergencyWithdraw(address,uint256,uint256)",
+            &[USER],
+            &[uint(9_000_000), uint(10_000_000)],
+        ));
+        assert_eq!(emergency.kind, "emergency_withdraw");
+        assert_eq!(emergency.amount, 9.0);
+        assert_eq!(emergency.detail, Some(json!({ "requested": 10.0 })));
+
+        let opened = decoded(&log(
+            "PositionOpened(address,address,bool,uint256,uint256)",
+            &[USER, TOKEN],
+            &[uint(1), uint(40_000_000), uint(3)],
+        ));
+        assert_eq!(
+            (opened.kind, opened.token, opened.amount),
+            ("position_opened", Some(TOKEN), 40.0)
+        );
+        assert_eq!(
+            opened.detail,
+            Some(json!({ "is_long": true, "leverage": "3" }))
+        );
+
+        let mut words = vec![int(-1_500_000)];
+        words.extend(string(2, "stop_loss"));
+        let closed = decoded(&log(
+            "PositionClosed(address,address,int256,string)",
+            &[USER, TOKEN],
+            &words,
+        ));
+        assert_eq!((closed.kind, closed.amount), ("position_closed", -1.5));
+        assert_eq!(closed.detail, Some(json!({ "reason": "stop_loss" })));
+
+        let instant = decoded(&log(
+            "UserInstantClose(address,address,uint256,int256)",
+            &[USER, TOKEN],
+            &[uint(38_000_000), int(-2_000_000)],
+        ));
+        assert_eq!((instant.kind, instant.amount), ("user_instant_close", 38.0));
+        assert_eq!(instant.detail, Some(json!({ "pnl": -2.0 })));
+
+        let reconciled = decoded(&log(
+            "PositionReconciled(address,address,uint256,address)",
+            &[USER, TOKEN],
+            &[uint(41_000_000), VAULT.into_word()],
+        ));
+        assert_eq!(
+            (reconciled.kind, reconciled.amount),
+            ("position_reconciled", 41.0)
+        );
+
+        let cancelled = decoded(&log(
+            "PositionCancelled(address,address,uint256)",
+            &[USER, TOKEN],
+            &[uint(40_000_000)],
+        ));
+        assert_eq!(
+            (cancelled.kind, cancelled.amount),
+            ("position_cancelled", 40.0)
+        );
+    }
+
+    #[test]
+    fn decodes_v7_events() {
+        let deposit = decoded(&log(
+            "Deposited(address,uint256,uint256)",
+            &[USER],
+            &[uint(25_000_000), uint(75_000_000)],
+        ));
+        assert_eq!(
+            (deposit.kind, deposit.user, deposit.amount),
+            ("deposit", USER, 25.0)
+        );
+
+        let withdraw = decoded(&log(
+            "Withdrawn(address,uint256,uint256)",
+            &[USER],
+            &[uint(5_000_000), uint(70_000_000)],
+        ));
+        assert_eq!((withdraw.kind, withdraw.amount), ("withdraw", 5.0));
+
+        let requested = decoded(&log(
+            "PositionRequested(address,address,bool,uint256,uint256,uint256,bytes32)",
+            &[USER, TOKEN],
+            &[
+                uint(0),
+                uint(40_000_000),
+                uint(80_000_000),
+                uint(2),
+                B256::repeat_byte(0x42),
+            ],
+        ));
+        assert_eq!(
+            (requested.kind, requested.token, requested.amount),
+            ("position_opened", Some(TOKEN), 40.0)
+        );
+        assert_eq!(
+            requested.detail,
+            Some(json!({ "is_long": false, "leverage": "2" }))
+        );
+
+        let mut words = vec![uint(1), uint(3_000), uint(3_100), int(0), uint(100_000)];
+        words.extend(string(6, "take_profit"));
+        let closed = decoded(&log(
+            "PositionClosed(address,address,bool,uint256,uint256,int256,uint256,string)",
+            &[USER, TOKEN],
+            &words,
+        ));
+        assert_eq!((closed.kind, closed.amount), ("position_closed", 0.0));
+        assert_eq!(
+            closed.detail,
+            Some(json!({ "reason": "take_profit", "settled": true }))
+        );
+    }
+
+    #[test]
+    fn decodes_usdc_transfers_relative_to_the_vault() {
+        let signature = "Transfer(address,address,uint256)";
+        let into = decoded(&log(signature, &[USER, VAULT], &[uint(50_000_000)]));
+        assert_eq!(
+            (into.kind, into.user, into.amount),
+            ("transfer_in", USER, 50.0)
+        );
+
+        let out = decoded(&log(signature, &[VAULT, USER], &[uint(5_000_000)]));
+        assert_eq!((out.kind, out.user), ("transfer_out", USER));
+
+        let elsewhere = log(signature, &[USER, TOKEN], &[uint(1)]);
+        assert!(decode_log(VAULT, &elsewhere).unwrap().is_none());
+    }
+
+    #[test]
+    fn skips_unknown_and_rejects_truncated_logs() {
+        assert!(decode_log(VAULT, &log("Paused(address)", &[USER], &[]))
+            .unwrap()
+            .is_none());
+        let truncated = log("Deposit(address,uint256)", &[USER], &[]);
+        assert!(decode_log(VAULT, &truncated).is_err());
+    }
+
+    fn row(
+        tx: u8,
+        kind: &str,
+        token: Option<Address>,
+        amount: f64,
+        detail: Option<Value>,
+    ) -> EventRow {
+        EventRow {
+            vault: VAULT.to_string(),
+            block_number: tx as u64,
+            tx_hash: B256::repeat_byte(tx).to_string(),
+            kind: kind.to_string(),
+            token: token.map(|token| token.to_string()),
+            amount,
+            detail,
+        }
+    }
+
+    fn entry_change(entries: &[LedgerEntry]) -> Vec<(&str, f64, Option<&str>)> {
+        entries
+            .iter()
+            .map(|e| (e.kind.as_str(), e.balance_change, e.note.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn replays_a_user_history() {
+        let other = Address::repeat_byte(0x33);
+        let rows = vec![
+            row(1, "deposit", None, 100.0, None),
+            row(1, "transfer_in", None, 100.0, None),
+            row(2, "transfer_in", None, 50.0, None),
+            row(3, "position_opened", Some(TOKEN), 40.0, None),
+            // V8+ keeper request, the real close follows
+            row(
+                4,
+                "position_closed",
+                Some(TOKEN),
+                0.0,
+                Some(json!({ "reason": "requested" })),
+            ),
+            row(
+                5,
+                "position_closed",
+                Some(TOKEN),
+                10.0,
+                Some(json!({ "reason": "take_profit" })),
+            ),
+            row(6, "position_opened", Some(other), 30.0, None),
+            // The instant close credits, its PositionClosed must not again
+            row(
+                7,
+                "user_instant_close",
+                Some(other),
+                25.0,
+                Some(json!({ "pnl": -5.0 })),
+            ),
+            row(
+                7,
+                "position_closed",
+                Some(other),
+                -5.0,
+                Some(json!({ "reason": "user_close" })),
+            ),
+            row(8, "withdraw", None, 20.0, None),
+            row(8, "transfer_out", None, 20.0, None),
+            row(9, "transfer_out", None, 5.0, None),
+        ];
+        let (summaries, entries) = replay(rows, &IndexerSettings::default()).unwrap();
+
+        assert_eq!(
+            entry_change(&entries),
+            [
+                ("deposit", 100.0, None),
+                ("transfer_in", 0.0, None),
+                (
+                    "transfer_in",
+                    0.0,
+                    Some("USDC sent to the vault without a deposit")
+                ),
+                ("position_opened", -40.0, None),
+                (
+                    "position_closed",
+                    0.0,
+                    Some("Close requested, not settled yet")
+                ),
+                ("position_closed", 50.0, Some("take_profit")),
+                ("position_opened", -30.0, None),
+                ("user_instant_close", 25.0, None),
+                ("position_closed", 0.0, Some("user_close")),
+                ("withdraw", -20.0, None),
+                ("transfer_out", 0.0, None),
+                (
+                    "transfer_out",
+                    0.0,
+                    Some("USDC sent from the vault outside a withdrawal")
+                ),
+            ]
+        );
+
+        assert_eq!(summaries.len(), 1);
+        let summary = &summaries[0];
+        assert_eq!(summary.vault, VAULT);
+        assert_eq!(summary.deposited, 100.0);
+        assert_eq!(summary.withdrawn, 20.0);
+        assert_eq!(summary.realized_pnl, 5.0);
+        assert_eq!(summary.balance, 85.0);
+        assert_eq!(summary.in_positions, 0.0);
+        assert_eq!(summary.uncredited, 50.0);
+        assert_eq!(entries.last().unwrap().balance, 85.0);
+    }
+
+    #[test]
+    fn settled_zero_pnl_close_returns_the_collateral() {
+        let rows = vec![
+            row(1, "deposit", None, 100.0, None),
+            row(2, "position_opened", Some(TOKEN), 40.0, None),
+            row(
+                3,
+                "position_closed",
+                Some(TOKEN),
+                0.0,
+                Some(json!({ "settled": true })),
+            ),
+        ];
+        let (summaries, entries) = replay(rows, &IndexerSettings::default()).unwrap();
+        assert_eq!(entries[2].balance_change, 40.0);
+        assert_eq!(summaries[0].balance, 100.0);
+        assert_eq!(summaries[0].in_positions, 0.0);
+    }
+
+    #[test]
+    fn unsettled_close_keeps_the_collateral_in_positions() {
+        let rows = vec![
+            row(1, "deposit", None, 100.0, None),
+            row(2, "position_opened", Some(TOKEN), 40.0, None),
+            row(3, "position_closed", Some(TOKEN), 0.0, None),
+        ];
+        let (summaries, _) = replay(rows, &IndexerSettings::default()).unwrap();
+        assert_eq!(summaries[0].balance, 60.0);
+        assert_eq!(summaries[0].in_positions, 40.0);
+    }
+
+    #[test]
+    fn stored_block_range_below_the_minimum_falls_back() {
+        let settings = IndexerSettings {
+            block_range: 0,
+            ..IndexerSettings::default()
+        }
+        .checked();
+        assert_eq!(settings.block_range, DEFAULT_BLOCK_RANGE);
+
+        let settings = IndexerSettings {
+            block_range: MIN_BLOCK_RANGE,
+            ..IndexerSettings::default()
+        }
+        .checked();
+        assert_eq!(settings.block_range, MIN_BLOCK_RANGE);
+    }
+
+    #[test]
+    fn defaults_cover_every_indexable_deployment() {
+        let labels: Vec<String> = IndexerSettings::default()
+            .vaults
+            .into_iter()
+            .map(|v| v.label)
+            .collect();
+        assert_eq!(
+            labels,
+            ["V7 Original", "V7", "V8 Legacy", "V8", "V8.3", "Current"]
+        );
+    }
+
+    #[test]
+    fn defaults_follow_the_configured_chain() {
+        let base = ChainSettings {
+            chain_id: 8453,
+            ..ChainSettings::default()
+        };
+        let vaults = default_vaults(&base);
+        // V1 to V3 predate the indexed events, only the configured vault is left
+        assert_eq!(vaults.len(), 1);
+        assert_eq!(vaults[0].address, base.vault_address);
+    }
+}
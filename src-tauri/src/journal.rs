@@ -0,0 +1,1256 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::chain;
+use crate::config::BackendConfig;
+use crate::error::{AppError, AppResult};
+use crate::http::HttpClient;
+use crate::license;
+use crate::risk::exits::Direction;
+use crate::signals::UnifiedSignal;
+
+const JOURNAL_FILE: &str = "journal.db";
+
+// Applied in order, `PRAGMA user_version` records how many have run.
+// Never edit a shipped migration, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    r#"
+CREATE TABLE positions (
+    id TEXT PRIMARY KEY,
+    wallet_address TEXT,
+    chain_id INTEGER,
+    token_symbol TEXT NOT NULL,
+    direction TEXT NOT NULL DEFAULT 'LONG',
+    leverage REAL NOT NULL DEFAULT 1,
+    entry_price REAL NOT NULL,
+    entry_amount REAL NOT NULL,
+    token_amount REAL,
+    entry_tx_hash TEXT,
+    exit_price REAL,
+    exit_amount REAL,
+    exit_tx_hash TEXT,
+    profit_loss REAL,
+    profit_loss_percent REAL,
+    status TEXT NOT NULL DEFAULT 'open',
+    close_reason TEXT,
+    paper INTEGER NOT NULL DEFAULT 0,
+    origin TEXT NOT NULL DEFAULT 'local',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    closed_at TEXT,
+    synced_at TEXT
+);
+CREATE INDEX idx_positions_symbol ON positions(token_symbol);
+CREATE INDEX idx_positions_status ON positions(status);
+CREATE INDEX idx_positions_closed_at ON positions(closed_at);
+
+CREATE TABLE fills (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    position_id TEXT NOT NULL REFERENCES positions(id) ON DELETE CASCADE,
+    side TEXT NOT NULL,
+    price REAL NOT NULL,
+    amount REAL NOT NULL,
+    token_amount REAL,
+    fee REAL NOT NULL DEFAULT 0,
+    tx_hash TEXT,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX idx_fills_position ON fills(position_id);
+
+CREATE TABLE fees (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    position_id TEXT REFERENCES positions(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    amount REAL NOT NULL,
+    asset TEXT NOT NULL DEFAULT 'USDC',
+    tx_hash TEXT,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX idx_fees_position ON fees(position_id);
+
+CREATE TABLE signals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    symbol TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    payload TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX idx_signals_symbol ON signals(symbol, created_at);
+
+CREATE TABLE license_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    detail TEXT,
+    created_at TEXT NOT NULL
+);
+"#,
+    r#"
+CREATE TABLE vault_events (
+    vault TEXT NOT NULL,
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    user TEXT,
+    token TEXT,
+    amount REAL NOT NULL DEFAULT 0,
+    detail TEXT,
+    PRIMARY KEY (vault, tx_hash, log_index)
+);
+CREATE INDEX idx_vault_events_user ON vault_events(user, block_number, log_index);
+
+CREATE TABLE index_cursors (
+    vault TEXT PRIMARY KEY,
+    last_block INTEGER NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#,
+    r#"
+CREATE TABLE alert_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rule_id TEXT,
+    title TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+"#,
+];
+
+const POSITION_STATUSES: &[&str] = &["open", "closing", "closed", "failed"];
+
+// Supabase `in.(...)` filters go in the URL, keep them short
+const SYNC_WALLET_BATCH: usize = 20;
+// PostgREST caps a response at its max-rows, 1000 by default
+const SYNC_PAGE_SIZE: usize = 1000;
+
+// Open lazily so a broken journal never keeps the app from starting
+#[derive(Default)]
+pub struct Journal(Mutex<Option<Connection>>);
+
+fn migrate(conn: &mut Connection) -> AppResult<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(AppError::StoreCorrupted(format!(
+            "journal schema {} is newer than this app",
+            version
+        )));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+pub fn with_conn<T>(
+    app: &tauri::AppHandle,
+    f: impl FnOnce(&mut Connection) -> AppResult<T>,
+) -> AppResult<T> {
+    let journal = app.state::<Journal>();
+    let mut guard = journal.0.lock().unwrap();
+    if guard.is_none() {
+        let dir = app.path().app_data_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let mut conn = Connection::open(dir.join(JOURNAL_FILE))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        migrate(&mut conn)?;
+        *guard = Some(conn);
+    }
+    f(guard.as_mut().expect("journal opened above"))
+}
+
+pub fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// Stored timestamps are UTC with a fixed precision so that they sort and
+// compare as text. A bare date means midnight UTC.
+fn normalize_time(value: &str) -> AppResult<String> {
+    let time = match DateTime::parse_from_rfc3339(value) {
+        Ok(time) => time.with_timezone(&Utc),
+        Err(e) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+            .map_err(|_| AppError::InvalidInput(format!("Invalid timestamp {}: {}", value, e)))?,
+    };
+    Ok(time.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+fn direction_str(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Long => "LONG",
+        Direction::Short => "SHORT",
+    }
+}
+
+fn parse_direction(value: &str) -> Direction {
+    // Rows written before SHORT support have no direction, same as positions.ts
+    if value.eq_ignore_ascii_case("SHORT") {
+        Direction::Short
+    } else {
+        Direction::Long
+    }
+}
+
+// A row of the local `positions` table. Names follow the Supabase table.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalPosition {
+    pub id: String,
+    #[serde(default)]
+    pub wallet_address: Option<String>,
+    #[serde(default)]
+    pub chain_id: Option<i64>,
+    pub token_symbol: String,
+    pub direction: Direction,
+    #[serde(default = "default_leverage")]
+    pub leverage: f64,
+    pub entry_price: f64,
+    // USDC committed
+    pub entry_amount: f64,
+    #[serde(default)]
+    pub token_amount: Option<f64>,
+    #[serde(default)]
+    pub entry_tx_hash: Option<String>,
+    #[serde(default)]
+    pub exit_price: Option<f64>,
+    #[serde(default)]
+    pub exit_amount: Option<f64>,
+    #[serde(default)]
+    pub exit_tx_hash: Option<String>,
+    #[serde(default)]
+    pub profit_loss: Option<f64>,
+    #[serde(default)]
+    pub profit_loss_percent: Option<f64>,
+    pub status: String,
+    #[serde(default)]
+    pub close_reason: Option<String>,
+    #[serde(default)]
+    pub paper: bool,
+    // "local" or "supabase", set by the journal
+    #[serde(default, skip_deserializing)]
+    pub origin: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub closed_at: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub synced_at: Option<String>,
+}
+
+fn default_leverage() -> f64 {
+    1.0
+}
+
+impl JournalPosition {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            wallet_address: row.get("wallet_address")?,
+            chain_id: row.get("chain_id")?,
+            token_symbol: row.get("token_symbol")?,
+            direction: parse_direction(&row.get::<_, String>("direction")?),
+            leverage: row.get("leverage")?,
+            entry_price: row.get("entry_price")?,
+            entry_amount: row.get("entry_amount")?,
+            token_amount: row.get("token_amount")?,
+            entry_tx_hash: row.get("entry_tx_hash")?,
+            exit_price: row.get("exit_price")?,
+            exit_amount: row.get("exit_amount")?,
+            exit_tx_hash: row.get("exit_tx_hash")?,
+            profit_loss: row.get("profit_loss")?,
+            profit_loss_percent: row.get("profit_loss_percent")?,
+            status: row.get("status")?,
+            close_reason: row.get("close_reason")?,
+            paper: row.get("paper")?,
+            origin: row.get("origin")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+            closed_at: row.get("closed_at")?,
+            synced_at: row.get("synced_at")?,
+        })
+    }
+
+    fn validate(&mut self) -> AppResult<()> {
+        if self.id.trim().is_empty() || self.token_symbol.trim().is_empty() {
+            return Err(AppError::InvalidInput(
+                "Position id and symbol are required".to_string(),
+            ));
+        }
+        if !POSITION_STATUSES.contains(&self.status.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown position status {}",
+                self.status
+            )));
+        }
+        self.token_symbol = self.token_symbol.trim().to_uppercase();
+        self.wallet_address = self.wallet_address.as_ref().map(|a| a.to_lowercase());
+        self.created_at = normalize_time(&self.created_at)?;
+        self.updated_at = Some(match &self.updated_at {
+            Some(time) => normalize_time(time)?,
+            None => now(),
+        });
+        self.closed_at = self.closed_at.as_deref().map(normalize_time).transpose()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FillSide {
+    Open,
+    Close,
+}
+
+impl FillSide {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FillSide::Open => "open",
+            FillSide::Close => "close",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalFill {
+    #[serde(default, skip_deserializing)]
+    pub id: i64,
+    pub position_id: String,
+    pub side: FillSide,
+    pub price: f64,
+    // USDC notional of the fill
+    pub amount: f64,
+    #[serde(default)]
+    pub token_amount: Option<f64>,
+    // Trading fee in USDC, gas and funding go to the fees table
+    #[serde(default)]
+    pub fee: f64,
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+// Costs that are not part of a fill: gas, funding, bridging
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalFee {
+    #[serde(default, skip_deserializing)]
+    pub id: i64,
+    #[serde(default)]
+    pub position_id: Option<String>,
+    pub kind: String,
+    pub amount: f64,
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+fn default_asset() -> String {
+    "USDC".to_string()
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct JournalSignal {
+    pub id: i64,
+    pub symbol: String,
+    pub direction: String,
+    pub confidence: f64,
+    // The full UnifiedSignal as generated
+    pub payload: serde_json::Value,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LicenseEvent {
+    pub id: i64,
+    pub kind: String,
+    pub detail: Option<String>,
+    pub created_at: String,
+}
+
+// One delivered alert, rule_id is None for built-in alerts
+#[derive(Debug, Serialize, Clone)]
+pub struct AlertEvent {
+    pub id: i64,
+    pub rule_id: Option<String>,
+    pub title: String,
+    pub message: String,
+    pub created_at: String,
+}
+
+// Dates bound the close time, or the open time for positions still open
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct JournalFilter {
+    pub symbol: Option<String>,
+    pub status: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub paper: Option<bool>,
+    pub wallet_address: Option<String>,
+    pub limit: Option<u32>,
+}
+
+impl JournalFilter {
+    // WHERE clause over the `p` alias plus its parameters
+    fn to_sql(&self) -> AppResult<(String, Vec<Value>)> {
+        let mut clauses = vec!["1 = 1".to_string()];
+        let mut values = Vec::new();
+
+        if let Some(symbol) = &self.symbol {
+            clauses.push("p.token_symbol = ?".to_string());
+            values.push(Value::Text(symbol.trim().to_uppercase()));
+        }
+        if let Some(status) = &self.status {
+            clauses.push("p.status = ?".to_string());
+            values.push(Value::Text(status.clone()));
+        }
+        if let Some(from) = &self.from {
+            clauses.push("COALESCE(p.closed_at, p.created_at) >= ?".to_string());
+            values.push(Value::Text(normalize_time(from)?));
+        }
+        if let Some(to) = &self.to {
+            clauses.push("COALESCE(p.closed_at, p.created_at) < ?".to_string());
+            values.push(Value::Text(normalize_time(to)?));
+        }
+        if let Some(paper) = self.paper {
+            clauses.push("p.paper = ?".to_string());
+            values.push(Value::Integer(paper as i64));
+        }
+        if let Some(wallet) = &self.wallet_address {
+            clauses.push("p.wallet_address = ?".to_string());
+            values.push(Value::Text(wallet.to_lowercase()));
+        }
+
+        Ok((clauses.join(" AND "), values))
+    }
+}
+
+// Same columns as the `user_trading_stats` view, over closed positions
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct PnlSummary {
+    pub total_trades: i64,
+    pub winning_trades: i64,
+    pub losing_trades: i64,
+    pub win_rate: f64,
+    pub total_pnl: f64,
+    pub avg_pnl: f64,
+    pub best_trade: f64,
+    pub worst_trade: f64,
+    pub avg_pnl_percent: f64,
+    // Fill fees plus gas and other costs
+    pub total_fees: f64,
+    pub by_symbol: Vec<SymbolPnl>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SymbolPnl {
+    pub symbol: String,
+    pub trades: i64,
+    pub pnl: f64,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SyncReport {
+    pub pulled: usize,
+    // Remote rows that did not pass validation and were left out
+    pub skipped: Vec<SyncSkip>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SyncSkip {
+    pub table: String,
+    pub id: Option<String>,
+    pub error: String,
+}
+
+fn upsert_position(conn: &Connection, position: &JournalPosition) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO positions (
+            id, wallet_address, chain_id, token_symbol, direction, leverage,
+            entry_price, entry_amount, token_amount, entry_tx_hash,
+            exit_price, exit_amount, exit_tx_hash, profit_loss, profit_loss_percent,
+            status, close_reason, paper, origin, created_at, updated_at, closed_at, synced_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                  ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)
+        ON CONFLICT(id) DO UPDATE SET
+            wallet_address = excluded.wallet_address,
+            chain_id = excluded.chain_id,
+            token_symbol = excluded.token_symbol,
+            direction = excluded.direction,
+            leverage = excluded.leverage,
+            entry_price = excluded.entry_price,
+            entry_amount = excluded.entry_amount,
+            token_amount = excluded.token_amount,
+            entry_tx_hash = excluded.entry_tx_hash,
+            exit_price = excluded.exit_price,
+            exit_amount = excluded.exit_amount,
+            exit_tx_hash = excluded.exit_tx_hash,
+            profit_loss = excluded.profit_loss,
+            profit_loss_percent = excluded.profit_loss_percent,
+            status = excluded.status,
+            close_reason = excluded.close_reason,
+            paper = excluded.paper,
+            updated_at = excluded.updated_at,
+            closed_at = excluded.closed_at,
+            synced_at = excluded.synced_at",
+        params![
+            position.id,
+            position.wallet_address,
+            position.chain_id,
+            position.token_symbol,
+            direction_str(position.direction),
+            position.leverage,
+            position.entry_price,
+            position.entry_amount,
+            position.token_amount,
+            position.entry_tx_hash,
+            position.exit_price,
+            position.exit_amount,
+            position.exit_tx_hash,
+            position.profit_loss,
+            position.profit_loss_percent,
+            position.status,
+            position.close_reason,
+            position.paper,
+            position.origin,
+            position.created_at,
+            position.updated_at,
+            position.closed_at,
+            position.synced_at,
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_fill(conn: &Connection, fill: &JournalFill) -> AppResult<i64> {
+    let created_at = match &fill.created_at {
+        Some(time) => normalize_time(time)?,
+        None => now(),
+    };
+    conn.execute(
+        "INSERT INTO fills (position_id, side, price, amount, token_amount, fee, tx_hash, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            fill.position_id,
+            fill.side.as_str(),
+            fill.price,
+            fill.amount,
+            fill.token_amount,
+            fill.fee,
+            fill.tx_hash,
+            created_at,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+// Insert or update a position, with the fill that changed it if any
+pub fn record_trade(
+    app: &tauri::AppHandle,
+    mut position: JournalPosition,
+    fill: Option<JournalFill>,
+) -> AppResult<()> {
+    position.validate()?;
+    position.origin = "local".to_string();
+    position.synced_at = None;
+
+    with_conn(app, |conn| {
+        let tx = conn.transaction()?;
+        let origin: Option<String> = tx
+            .query_row(
+                "SELECT origin FROM positions WHERE id = ?1",
+                [&position.id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(origin) = origin {
+            position.origin = origin;
+        }
+        upsert_position(&tx, &position)?;
+        if let Some(fill) = &fill {
+            insert_fill(&tx, fill)?;
+        }
+        tx.commit()?;
+        Ok(())
+    })
+}
+
+pub fn record_fill(app: &tauri::AppHandle, fill: JournalFill) -> AppResult<i64> {
+    with_conn(app, |conn| insert_fill(conn, &fill))
+}
+
+pub fn record_fee(app: &tauri::AppHandle, fee: JournalFee) -> AppResult<i64> {
+    let created_at = match &fee.created_at {
+        Some(time) => normalize_time(time)?,
+        None => now(),
+    };
+    with_conn(app, |conn| {
+        conn.execute(
+            "INSERT INTO fees (position_id, kind, amount, asset, tx_hash, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                fee.position_id,
+                fee.kind,
+                fee.amount,
+                fee.asset,
+                fee.tx_hash,
+                created_at
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    })
+}
+
+pub fn record_signal(app: &tauri::AppHandle, signal: &UnifiedSignal) -> AppResult<i64> {
+    let payload = serde_json::to_value(signal)?;
+    let direction = payload["direction"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    with_conn(app, |conn| {
+        conn.execute(
+            "INSERT INTO signals (symbol, direction, confidence, payload, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                signal.symbol,
+                direction,
+                signal.confidence,
+                payload.to_string(),
+                now()
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    })
+}
+
+// Best effort, a failed journal write never fails the license flow
+pub fn record_license_event(app: &tauri::AppHandle, kind: &str, detail: Option<&str>) {
+    let result = with_conn(app, |conn| {
+        conn.execute(
+            "INSERT INTO license_events (kind, detail, created_at) VALUES (?1, ?2, ?3)",
+            params![kind, detail, now()],
+        )?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to journal license event {}: {}", kind, e);
+    }
+}
+
+pub fn record_alert(
+    app: &tauri::AppHandle,
+    rule_id: Option<&str>,
+    title: &str,
+    message: &str,
+) -> AlertEvent {
+    let created_at = now();
+    let result = with_conn(app, |conn| {
+        conn.execute(
+            "INSERT INTO alert_events (rule_id, title, message, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![rule_id, title, message, created_at],
+        )?;
+        Ok(conn.last_insert_rowid())
+    });
+    let id = result.unwrap_or_else(|e| {
+        log::warn!("Failed to journal alert {}: {}", title, e);
+        0
+    });
+    AlertEvent {
+        id,
+        rule_id: rule_id.map(str::to_string),
+        title: title.to_string(),
+        message: message.to_string(),
+        created_at,
+    }
+}
+
+pub fn positions(
+    app: &tauri::AppHandle,
+    filter: &JournalFilter,
+) -> AppResult<Vec<JournalPosition>> {
+    let (clause, mut values) = filter.to_sql()?;
+    values.push(Value::Integer(filter.limit.unwrap_or(500) as i64));
+    let sql = format!(
+        "SELECT p.* FROM positions p WHERE {} ORDER BY p.created_at DESC LIMIT ?",
+        clause
+    );
+
+    with_conn(app, |conn| {
+        let mut statement = conn.prepare(&sql)?;
+        let rows = statement
+            .query_map(params_from_iter(values), JournalPosition::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+}
+
+pub fn fills(app: &tauri::AppHandle, position_id: &str) -> AppResult<Vec<JournalFill>> {
+    with_conn(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT id, position_id, side, price, amount, token_amount, fee, tx_hash, created_at
+             FROM fills WHERE position_id = ?1 ORDER BY created_at",
+        )?;
+        let rows = statement
+            .query_map([position_id], |row| {
+                let side: String = row.get(2)?;
+                Ok(JournalFill {
+                    id: row.get(0)?,
+                    position_id: row.get(1)?,
+                    side: if side == "close" {
+                        FillSide::Close
+                    } else {
+                        FillSide::Open
+                    },
+                    price: row.get(3)?,
+                    amount: row.get(4)?,
+                    token_amount: row.get(5)?,
+                    fee: row.get(6)?,
+                    tx_hash: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+}
+
+pub fn signals(
+    app: &tauri::AppHandle,
+    symbol: Option<&str>,
+    limit: u32,
+) -> AppResult<Vec<JournalSignal>> {
+    with_conn(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT id, symbol, direction, confidence, payload, created_at FROM signals
+             WHERE ?1 IS NULL OR symbol = ?1 ORDER BY created_at DESC LIMIT ?2",
+        )?;
+        let rows = statement
+            .query_map(params![symbol, limit], |row| {
+                let payload: String = row.get(4)?;
+                Ok(JournalSignal {
+                    id: row.get(0)?,
+                    symbol: row.get(1)?,
+                    direction: row.get(2)?,
+                    confidence: row.get(3)?,
+                    payload: serde_json::from_str(&payload).unwrap_or_default(),
+                    created_at: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+}
+
+pub fn license_events(app: &tauri::AppHandle, limit: u32) -> AppResult<Vec<LicenseEvent>> {
+    with_conn(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT id, kind, detail, created_at FROM license_events
+             ORDER BY created_at DESC LIMIT ?1",
+        )?;
+        let rows = statement
+            .query_map([limit], |row| {
+                Ok(LicenseEvent {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    detail: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+}
+
+pub fn alert_events(app: &tauri::AppHandle, limit: u32) -> AppResult<Vec<AlertEvent>> {
+    with_conn(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT id, rule_id, title, message, created_at FROM alert_events
+             ORDER BY created_at DESC LIMIT ?1",
+        )?;
+        let rows = statement
+            .query_map([limit], |row| {
+                Ok(AlertEvent {
+                    id: row.get(0)?,
+                    rule_id: row.get(1)?,
+                    title: row.get(2)?,
+                    message: row.get(3)?,
+                    created_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+}
+
+// Non-fill costs per position, for cost basis
+pub fn extra_fees(app: &tauri::AppHandle) -> AppResult<HashMap<String, f64>> {
+    with_conn(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT position_id, SUM(amount) FROM fees
+             WHERE position_id IS NOT NULL GROUP BY position_id",
+        )?;
+        let totals = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(totals)
+    })
+}
+
+pub fn pnl(app: &tauri::AppHandle, filter: &JournalFilter) -> AppResult<PnlSummary> {
+    let (clause, values) = filter.to_sql()?;
+    let closed = format!("p.closed_at IS NOT NULL AND {}", clause);
+
+    with_conn(app, |conn| {
+        let mut summary = conn.query_row(
+            &format!(
+                "SELECT COUNT(*),
+                    COALESCE(SUM(p.profit_loss > 0), 0),
+                    COALESCE(SUM(p.profit_loss < 0), 0),
+                    COALESCE(SUM(p.profit_loss), 0),
+                    COALESCE(AVG(p.profit_loss), 0),
+                    COALESCE(MAX(p.profit_loss), 0),
+                    COALESCE(MIN(p.profit_loss), 0),
+                    COALESCE(AVG(p.profit_loss_percent), 0)
+                 FROM positions p WHERE {}",
+                closed
+            ),
+            params_from_iter(values.iter()),
+            |row| {
+                Ok(PnlSummary {
+                    total_trades: row.get(0)?,
+                    winning_trades: row.get(1)?,
+                    losing_trades: row.get(2)?,
+                    total_pnl: row.get(3)?,
+                    avg_pnl: row.get(4)?,
+                    best_trade: row.get(5)?,
+                    worst_trade: row.get(6)?,
+                    avg_pnl_percent: row.get(7)?,
+                    ..PnlSummary::default()
+                })
+            },
+        )?;
+        if summary.total_trades > 0 {
+            summary.win_rate = summary.winning_trades as f64 / summary.total_trades as f64 * 100.0;
+        }
+
+        // Fees of every matching position, open ones included
+        let fee_values = values.iter().chain(values.iter());
+        summary.total_fees = conn.query_row(
+            &format!(
+                "SELECT
+                    (SELECT COALESCE(SUM(f.fee), 0) FROM fills f
+                        JOIN positions p ON p.id = f.position_id WHERE {0})
+                  + (SELECT COALESCE(SUM(f.amount), 0) FROM fees f
+                        JOIN positions p ON p.id = f.position_id WHERE {0})",
+                clause
+            ),
+            params_from_iter(fee_values),
+            |row| row.get(0),
+        )?;
+
+        let mut statement = conn.prepare(&format!(
+            "SELECT p.token_symbol, COUNT(*), COALESCE(SUM(p.profit_loss), 0)
+             FROM positions p WHERE {} GROUP BY p.token_symbol ORDER BY 3 DESC",
+            closed
+        ))?;
+        summary.by_symbol = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok(SymbolPnl {
+                    symbol: row.get(0)?,
+                    trades: row.get(1)?,
+                    pnl: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(summary)
+    })
+}
+
+// A `positions` row as PostgREST returns it
+#[derive(Debug, Deserialize)]
+struct RemotePosition {
+    id: String,
+    wallet_address: String,
+    chain_id: Option<i64>,
+    token_symbol: String,
+    direction: Option<String>,
+    leverage_multiplier: Option<f64>,
+    entry_price: f64,
+    entry_amount: f64,
+    token_amount: Option<f64>,
+    entry_tx_hash: Option<String>,
+    exit_price: Option<f64>,
+    exit_amount: Option<f64>,
+    exit_tx_hash: Option<String>,
+    profit_loss: Option<f64>,
+    profit_loss_percent: Option<f64>,
+    status: String,
+    close_reason: Option<String>,
+    created_at: String,
+    updated_at: Option<String>,
+    closed_at: Option<String>,
+}
+
+impl RemotePosition {
+    fn into_journal(self, synced_at: &str) -> AppResult<JournalPosition> {
+        let mut position = JournalPosition {
+            id: self.id,
+            wallet_address: Some(self.wallet_address),
+            chain_id: self.chain_id,
+            token_symbol: self.token_symbol,
+            direction: parse_direction(self.direction.as_deref().unwrap_or_default()),
+            leverage: self.leverage_multiplier.unwrap_or(1.0),
+            entry_price: self.entry_price,
+            entry_amount: self.entry_amount,
+            token_amount: self.token_amount,
+            entry_tx_hash: self.entry_tx_hash,
+            exit_price: self.exit_price,
+            exit_amount: self.exit_amount,
+            exit_tx_hash: self.exit_tx_hash,
+            profit_loss: self.profit_loss,
+            profit_loss_percent: self.profit_loss_percent,
+            status: self.status,
+            close_reason: self.close_reason,
+            paper: false,
+            origin: "supabase".to_string(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            closed_at: self.closed_at,
+            synced_at: Some(synced_at.to_string()),
+        };
+        position.validate()?;
+        Ok(position)
+    }
+}
+
+// A `trade_history` row, the bot's record of a closed position. It
+// outlives the `positions` row it was written from.
+#[derive(Debug, Deserialize)]
+struct RemoteTrade {
+    id: String,
+    position_id: Option<String>,
+    wallet_address: String,
+    chain_id: Option<i64>,
+    token_symbol: String,
+    direction: Option<String>,
+    leverage: Option<f64>,
+    entry_price: f64,
+    entry_amount: f64,
+    entry_tx_hash: Option<String>,
+    exit_price: Option<f64>,
+    exit_amount: Option<f64>,
+    exit_tx_hash: Option<String>,
+    profit_loss: Option<f64>,
+    profit_loss_percent: Option<f64>,
+    close_reason: Option<String>,
+    opened_at: Option<String>,
+    closed_at: Option<String>,
+    created_at: String,
+}
+
+impl RemoteTrade {
+    fn into_journal(self, synced_at: &str) -> AppResult<JournalPosition> {
+        let mut position = JournalPosition {
+            // Keyed like the position it closed, so both pulls meet in one row
+            id: self.position_id.unwrap_or(self.id),
+            wallet_address: Some(self.wallet_address),
+            chain_id: self.chain_id,
+            token_symbol: self.token_symbol,
+            direction: parse_direction(self.direction.as_deref().unwrap_or_default()),
+            leverage: self.leverage.unwrap_or(1.0),
+            entry_price: self.entry_price,
+            entry_amount: self.entry_amount,
+            token_amount: None,
+            entry_tx_hash: self.entry_tx_hash,
+            exit_price: self.exit_price,
+            exit_amount: self.exit_amount,
+            exit_tx_hash: self.exit_tx_hash,
+            profit_loss: self.profit_loss,
+            profit_loss_percent: self.profit_loss_percent,
+            status: "closed".to_string(),
+            close_reason: self.close_reason,
+            paper: false,
+            origin: "supabase".to_string(),
+            created_at: self.opened_at.unwrap_or_else(|| self.created_at.clone()),
+            updated_at: Some(self.created_at.clone()),
+            closed_at: Some(self.closed_at.unwrap_or(self.created_at)),
+            synced_at: Some(synced_at.to_string()),
+        };
+        position.validate()?;
+        Ok(position)
+    }
+}
+
+// Every row of `table` for the wallets, a page at a time
+async fn fetch_rows(
+    http: &HttpClient,
+    base: &str,
+    table: &str,
+    wallets: &[String],
+) -> AppResult<Vec<serde_json::Value>> {
+    let client = http.backend();
+    let mut rows = Vec::new();
+    for batch in wallets.chunks(SYNC_WALLET_BATCH) {
+        for page in 0.. {
+            // Ordered on the key so that pages do not shift under inserts
+            let url = format!(
+                "{}/rest/v1/{}?select=*&wallet_address=in.({})&order=created_at.asc,id.asc&limit={}&offset={}",
+                base,
+                table,
+                batch.join(","),
+                SYNC_PAGE_SIZE,
+                page * SYNC_PAGE_SIZE
+            );
+            let response = http.send_idempotent(|| client.get(&url)).await?;
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                return Err(if status.is_server_error() {
+                    AppError::ServerUnavailable(error_text)
+                } else {
+                    AppError::ServerRejected(error_text)
+                });
+            }
+            let page_rows = response.json::<Vec<serde_json::Value>>().await?;
+            let full = page_rows.len() == SYNC_PAGE_SIZE;
+            rows.extend(page_rows);
+            if !full {
+                break;
+            }
+        }
+    }
+    Ok(rows)
+}
+
+// Parse one pulled row, reporting instead of failing the whole pull
+fn parse_row<T: serde::de::DeserializeOwned>(
+    table: &str,
+    row: serde_json::Value,
+    into_journal: impl FnOnce(T) -> AppResult<JournalPosition>,
+    skipped: &mut Vec<SyncSkip>,
+) -> Option<JournalPosition> {
+    let id = row.get("id").and_then(|id| id.as_str()).map(str::to_string);
+    let parsed = serde_json::from_value::<T>(row)
+        .map_err(AppError::from)
+        .and_then(into_journal);
+    match parsed {
+        Ok(position) => Some(position),
+        Err(e) => {
+            log::warn!("Skipped {} row {:?}: {}", table, id, e);
+            skipped.push(SyncSkip {
+                table: table.to_string(),
+                id,
+                error: e.to_string(),
+            });
+            None
+        }
+    }
+}
+
+// Trades first, so that a position still in `positions` replaces the
+// history row written from it
+fn merge_remote(
+    positions: Vec<serde_json::Value>,
+    trades: Vec<serde_json::Value>,
+    synced_at: &str,
+) -> (Vec<JournalPosition>, Vec<SyncSkip>) {
+    let mut skipped = Vec::new();
+    let mut merged: HashMap<String, JournalPosition> = HashMap::new();
+    for row in trades {
+        let trade = parse_row(
+            "trade_history",
+            row,
+            |t: RemoteTrade| t.into_journal(synced_at),
+            &mut skipped,
+        );
+        if let Some(position) = trade {
+            merged.insert(position.id.clone(), position);
+        }
+    }
+    for row in positions {
+        let position = parse_row(
+            "positions",
+            row,
+            |p: RemotePosition| p.into_journal(synced_at),
+            &mut skipped,
+        );
+        if let Some(position) = position {
+            merged.insert(position.id.clone(), position);
+        }
+    }
+    let mut merged: Vec<_> = merged.into_values().collect();
+    merged.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+    (merged, skipped)
+}
+
+async fn pull(
+    app: &tauri::AppHandle,
+    http: &HttpClient,
+    base: &str,
+    wallets: &[String],
+) -> AppResult<SyncReport> {
+    let positions = fetch_rows(http, base, "positions", wallets).await?;
+    let trades = fetch_rows(http, base, "trade_history", wallets).await?;
+    let (positions, skipped) = merge_remote(positions, trades, &now());
+    let pulled = with_conn(app, |conn| {
+        let tx = conn.transaction()?;
+        for position in &positions {
+            upsert_position(&tx, position)?;
+        }
+        tx.commit()?;
+        Ok(positions.len())
+    })?;
+    Ok(SyncReport { pulled, skipped })
+}
+
+// Pull the wallets' Supabase positions and trade history into the journal.
+// Sync is pull-only: local trades stay local, since `positions` and
+// `trade_history` only take writes from the bot's service role.
+pub async fn sync(app: &tauri::AppHandle, wallets: &[String]) -> AppResult<SyncReport> {
+    // Checked before they go into the PostgREST filter
+    let wallets = wallets
+        .iter()
+        .map(|w| Ok(chain::parse_address(w.trim())?.to_string().to_lowercase()))
+        .collect::<AppResult<Vec<_>>>()?;
+    if wallets.is_empty() {
+        return Err(AppError::InvalidInput(
+            "At least one wallet address is required".to_string(),
+        ));
+    }
+
+    let base = license::server_url(app, BackendConfig::active()?)?;
+    let http = app.state::<HttpClient>();
+    pull(app, &http, &base, &wallets).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const WALLET: &str = "0x00000000000000000000000000000000000000aa";
+
+    fn position(id: &str, status: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "wallet_address": WALLET,
+            "chain_id": 42161,
+            "token_symbol": "eth",
+            "direction": "LONG",
+            "leverage_multiplier": 2,
+            "entry_price": 3000.0,
+            "entry_amount": 100.0,
+            "token_amount": 0.066,
+            "entry_tx_hash": null,
+            "exit_price": null,
+            "exit_amount": null,
+            "exit_tx_hash": null,
+            "profit_loss": null,
+            "profit_loss_percent": null,
+            "status": status,
+            "close_reason": null,
+            "created_at": "2026-01-02T10:00:00Z",
+            "updated_at": null,
+            "closed_at": null
+        })
+    }
+
+    fn trade(id: &str, position_id: Option<&str>) -> serde_json::Value {
+        json!({
+            "id": id,
+            "position_id": position_id,
+            "wallet_address": WALLET,
+            "chain_id": 42161,
+            "token_symbol": "BTC",
+            "direction": "SHORT",
+            "leverage": 3,
+            "entry_price": 60000.0,
+            "entry_amount": 50.0,
+            "exit_price": 59000.0,
+            "exit_amount": 52.5,
+            "profit_loss": 2.5,
+            "profit_loss_percent": 5.0,
+            "close_reason": "take_profit",
+            "opened_at": "2026-01-01T08:00:00Z",
+            "closed_at": "2026-01-01T12:00:00Z",
+            "created_at": "2026-01-01T12:00:01Z"
+        })
+    }
+
+    #[test]
+    fn history_rows_become_closed_positions() {
+        let (merged, skipped) = merge_remote(Vec::new(), vec![trade("t1", Some("p1"))], &now());
+        assert!(skipped.is_empty());
+        let position = &merged[0];
+        assert_eq!(position.id, "p1");
+        assert_eq!(position.status, "closed");
+        assert_eq!(position.direction, Direction::Short);
+        assert_eq!(position.leverage, 3.0);
+        assert_eq!(position.profit_loss, Some(2.5));
+        assert_eq!(position.created_at, "2026-01-01T08:00:00.000Z");
+        assert_eq!(
+            position.closed_at.as_deref(),
+            Some("2026-01-01T12:00:00.000Z")
+        );
+        assert_eq!(position.origin, "supabase");
+    }
+
+    #[test]
+    fn live_positions_replace_their_history_rows() {
+        let (merged, _) = merge_remote(
+            vec![position("p1", "closed")],
+            vec![trade("t1", Some("p1")), trade("t2", None)],
+            &now(),
+        );
+        let ids: Vec<_> = merged.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["t2", "p1"]);
+        assert_eq!(merged[1].token_symbol, "ETH");
+        assert_eq!(merged[1].token_amount, Some(0.066));
+    }
+
+    #[test]
+    fn invalid_rows_are_skipped_and_reported() {
+        let mut missing_price = position("p2", "open");
+        missing_price.as_object_mut().unwrap().remove("entry_price");
+        let (merged, skipped) = merge_remote(
+            vec![
+                position("p1", "open"),
+                position("p3", "liquidating"),
+                missing_price,
+            ],
+            vec![json!({ "id": "t1" })],
+            &now(),
+        );
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].id, "p1");
+
+        let reported: Vec<_> = skipped
+            .iter()
+            .map(|s| (s.table.as_str(), s.id.as_deref()))
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                ("trade_history", Some("t1")),
+                ("positions", Some("p3")),
+                ("positions", Some("p2")),
+            ]
+        );
+        assert!(skipped[1].error.contains("liquidating"));
+    }
+}
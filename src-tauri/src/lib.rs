@@ -1,143 +1,681 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use tauri_plugin_store::StoreExt;
+mod alerts;
+mod backtest;
+mod chain;
+mod config;
+mod error;
+mod export;
+mod gmx_monitor;
+mod http;
+mod indexer;
+mod journal;
+mod license;
+mod license_monitor;
+mod machine_id;
+mod market_data;
+mod paper;
+mod price_stream;
+mod recovery;
+mod risk;
+mod secrets;
+mod settings;
+mod signals;
+mod tray;
+mod tx;
+mod vaults;
+mod wallet;
 
-// License validation response from Supabase
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LicenseValidation {
-    pub valid: bool,
-    pub plan_tier: Option<String>,
-    pub error: Option<String>,
+use alerts::{AlertRule, AlertState};
+use backtest::{BacktestConfig, BacktestReport};
+use chain::{ChainSettings, ChainState, VaultHealth, VaultPosition, VaultUserStatus};
+use config::BackendConfig;
+use error::{AppError, AppResult};
+use export::{ExportFormat, ExportOptions, ExportSummary};
+use gmx_monitor::{GmxMonitorSettings, GmxMonitorState, PositionHealth};
+use http::{HttpClient, HttpSettings};
+use indexer::{IndexProgress, IndexerSettings, IndexerState, UserLedger};
+use journal::{
+    AlertEvent, Journal, JournalFee, JournalFill, JournalFilter, JournalPosition, JournalSignal,
+    LicenseEvent, PnlSummary, SyncReport,
+};
+use license::{LicenseValidation, ServerVerdict, StoredLicense};
+use license_monitor::MonitorConfig;
+use machine_id::MachineFingerprint;
+use market_data::{Candle, MarketDataCache, Timeframe};
+use paper::{PaperOrder, PaperPosition, PaperSettings, PaperState, PaperSummary};
+use price_stream::{Feed, PriceStreams, StreamInfo};
+use recovery::{RecoveryReport, RecoveryStep};
+use secrets::{SecretsState, SecretsStatus};
+use signals::UnifiedSignal;
+use tauri::Manager;
+use tray::TraySettings;
+use tx::{GasQuote, TxRecord, TxState, VaultAction};
+use vaults::VaultDeployment;
+use wallet::{UnsignedTransaction, WalletInfo, WalletState};
+
+// Get unique machine identifier. The first call collects the fingerprint,
+// which reads files and runs system commands.
+#[tauri::command]
+async fn get_machine_id(app: tauri::AppHandle) -> AppResult<String> {
+    tauri::async_runtime::spawn_blocking(move || license::resolve_machine_id(&app))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+// Get stored license from local storage
+#[tauri::command]
+async fn get_stored_license(app: tauri::AppHandle) -> AppResult<Option<StoredLicense>> {
+    let stored = match license::load(&app)? {
+        Some(stored) => stored,
+        None => return Ok(None),
+    };
+
+    // Verify signature, machine ID and expiry on every read
+    let grace = MonitorConfig::load(&app).grace_period();
+    if let Err(e) = stored
+        .token
+        .verify(&license::resolve_machine_id(&app), grace)
+    {
+        log::warn!("Stored license rejected: {}", e);
+        return Ok(None);
+    }
+
+    Ok(Some(stored))
+}
+
+// Activate a code for this machine and store the signed token
+async fn activate(
+    app: &tauri::AppHandle,
+    license_code: &str,
+    server_url: &str,
+) -> AppResult<LicenseValidation> {
+    let machine_id = license::resolve_machine_id(app);
+    let public_key = license::device_public_key(&license::device_key(app)?);
+
+    let config = BackendConfig::active()?;
+    let http = app.state::<HttpClient>();
+    let (validation, token) = match license::check_with_server(
+        &http,
+        config,
+        server_url,
+        license_code,
+        &machine_id,
+        &public_key,
+    )
+    .await
+    {
+        ServerVerdict::Valid(validation, token) => (*validation, token),
+        ServerVerdict::Rejected(error) => return Ok(LicenseValidation::rejected(error)),
+        ServerVerdict::Unavailable(error) => return Err(error),
+    };
+
+    // Signature checks out, store the token locally
+    let now = chrono::Utc::now().to_rfc3339();
+    let stored_license = StoredLicense {
+        token,
+        activated_at: now.clone(),
+        fingerprint: MachineFingerprint::current().components,
+        last_validated_at: Some(now),
+    };
+    license::save(app, &stored_license)?;
+
+    // Remember where to re-validate from the background monitor
+    license::set_server_url(app, server_url)?;
+    journal::record_license_event(app, "activated", validation.plan_tier.as_deref());
+
+    Ok(validation)
+}
+
+// Release the server-side machine binding, the local license is kept
+async fn release(app: &tauri::AppHandle) -> AppResult<(StoredLicense, String)> {
+    let stored = license::load(app)?.ok_or(AppError::NotActivated)?;
+    let config = BackendConfig::active()?;
+    let server_url = license::server_url(app, config)?;
+
+    license::release_on_server(
+        &app.state::<HttpClient>(),
+        config,
+        &server_url,
+        &stored.token.code,
+        &stored.token.machine_id,
+        &license::device_key(app)?,
+    )
+    .await?;
+
+    Ok((stored, server_url))
+}
+
+// Drop the local license and the device key bound to it
+fn forget(app: &tauri::AppHandle) -> AppResult<()> {
+    license::clear(app)?;
+    license::reset_device_key(app)?;
+    journal::record_license_event(app, "deactivated", None);
+    Ok(())
 }
 
-// Stored license info
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct StoredLicense {
-    pub code: String,
-    pub plan_tier: String,
-    pub activated_at: String,
-    pub machine_id: String,
+// Validate license code against Supabase. The URL is optional and must be
+// one of the compiled-in backend URLs.
+#[tauri::command]
+async fn validate_license(
+    app: tauri::AppHandle,
+    license_code: String,
+    supabase_url: Option<String>,
+) -> AppResult<LicenseValidation> {
+    let server_url = BackendConfig::active()?.resolve_url(supabase_url.as_deref())?;
+    activate(&app, &license_code, &server_url).await
 }
 
-// Get unique machine identifier
+// Active backend profile, so the webview doesn't need its own copy
 #[tauri::command]
-fn get_machine_id() -> String {
-    // Create a simple machine fingerprint based on hostname and username
-    let hostname = hostname::get()
-        .map(|h| h.to_string_lossy().to_string())
-        .unwrap_or_else(|_| "unknown".to_string());
+fn get_backend_config() -> AppResult<BackendConfig> {
+    BackendConfig::active().cloned()
+}
 
-    let username = whoami::username();
+// Clear stored license (for logout/deactivation)
+#[tauri::command]
+async fn clear_license(app: tauri::AppHandle) -> AppResult<()> {
+    license::clear(&app)
+}
 
-    // Create a hash of the machine info
-    let machine_info = format!("{}:{}", hostname, username);
-    let hash = simple_hash(&machine_info);
+// Free the license so it can be activated on another machine
+#[tauri::command]
+async fn deactivate_license(app: tauri::AppHandle) -> AppResult<()> {
+    release(&app).await?;
+    forget(&app)
+}
 
-    format!("DSK-{}", hash)
+// Rebind the license to this machine's current hardware identity
+#[tauri::command]
+async fn transfer_license(app: tauri::AppHandle) -> AppResult<LicenseValidation> {
+    let (stored, server_url) = release(&app).await?;
+    license::rebind(
+        &stored,
+        || forget(&app),
+        || activate(&app, &stored.token.code, &server_url),
+        |stored| license::save(&app, stored),
+    )
+    .await
 }
 
-// Simple hash function for machine ID
-fn simple_hash(input: &str) -> String {
-    let mut hash: u64 = 0;
-    for (i, c) in input.chars().enumerate() {
-        hash = hash.wrapping_add((c as u64).wrapping_mul((i + 1) as u64));
-        hash = hash.wrapping_mul(31);
-    }
-    format!("{:016X}", hash)
+// Background license re-validation settings
+#[tauri::command]
+fn get_license_monitor_config(app: tauri::AppHandle) -> MonitorConfig {
+    MonitorConfig::load(&app)
 }
 
-// Get stored license from local storage
 #[tauri::command]
-async fn get_stored_license(app: tauri::AppHandle) -> Result<Option<StoredLicense>, String> {
-    let store = app.store("license.json").map_err(|e: tauri_plugin_store::Error| e.to_string())?;
+fn set_license_monitor_config(app: tauri::AppHandle, config: MonitorConfig) -> AppResult<()> {
+    config.save(&app)
+}
 
-    match store.get("license") {
-        Some(value) => {
-            let license: StoredLicense = serde_json::from_value(value.clone())
-                .map_err(|e: serde_json::Error| e.to_string())?;
+// Encrypted secrets store
+#[tauri::command]
+fn secrets_status(app: tauri::AppHandle) -> AppResult<SecretsStatus> {
+    secrets::status(&app)
+}
 
-            // Verify machine ID matches
-            let current_machine_id = get_machine_id();
-            if license.machine_id != current_machine_id {
-                return Ok(None); // License was activated on different machine
-            }
+// Argon2 key derivation, kept off the async runtime
+#[tauri::command]
+async fn secrets_unlock(app: tauri::AppHandle, passphrase: Option<String>) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || secrets::unlock(&app, passphrase.as_deref()))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
 
-            Ok(Some(license))
-        }
-        None => Ok(None),
-    }
+#[tauri::command]
+fn secrets_lock(app: tauri::AppHandle) -> AppResult<()> {
+    secrets::lock(&app)
 }
 
-// Validate license code against Supabase
 #[tauri::command]
-async fn validate_license(
+async fn secrets_change_passphrase(
     app: tauri::AppHandle,
-    license_code: String,
-    supabase_url: String,
-) -> Result<LicenseValidation, String> {
-    let machine_id = get_machine_id();
-
-    // Call Supabase edge function to validate license
-    let client = reqwest::Client::new();
-    let url = format!("{}/functions/v1/validate-desktop-license", supabase_url);
-
-    let mut body = HashMap::new();
-    body.insert("licenseCode", license_code.clone());
-    body.insert("machineId", machine_id.clone());
-
-    let response = client
-        .post(&url)
-        .json(&body)
-        .send()
+    passphrase: Option<String>,
+) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        secrets::change_passphrase(&app, passphrase.as_deref())
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+// Entries are decrypted on read and the store file is rewritten on change
+#[tauri::command]
+async fn secret_get(app: tauri::AppHandle, name: String) -> AppResult<Option<String>> {
+    secrets::check_public_name(&name)?;
+    tauri::async_runtime::spawn_blocking(move || secrets::get(&app, &name))
         .await
-        .map_err(|e: reqwest::Error| format!("Network error: {}", e))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Ok(LicenseValidation {
-            valid: false,
-            plan_tier: None,
-            error: Some(format!("Validation failed: {}", error_text)),
-        });
-    }
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
 
-    let validation: LicenseValidation = response
-        .json()
+#[tauri::command]
+async fn secret_set(app: tauri::AppHandle, name: String, value: String) -> AppResult<()> {
+    secrets::check_public_name(&name)?;
+    tauri::async_runtime::spawn_blocking(move || secrets::set(&app, &name, &value))
         .await
-        .map_err(|e: reqwest::Error| format!("Parse error: {}", e))?;
-
-    // If valid, store the license locally
-    if validation.valid {
-        if let Some(ref plan_tier) = validation.plan_tier {
-            let stored_license = StoredLicense {
-                code: license_code,
-                plan_tier: plan_tier.clone(),
-                activated_at: chrono::Utc::now().to_rfc3339(),
-                machine_id,
-            };
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+#[tauri::command]
+async fn secret_delete(app: tauri::AppHandle, name: String) -> AppResult<()> {
+    secrets::check_public_name(&name)?;
+    tauri::async_runtime::spawn_blocking(move || secrets::delete(&app, &name))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+// Local keystore wallet
+// Keystore scrypt takes seconds, keep it off the async runtime
+#[tauri::command]
+async fn wallet_create(app: tauri::AppHandle, password: String) -> AppResult<WalletInfo> {
+    tauri::async_runtime::spawn_blocking(move || wallet::create(&app, &password))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+#[tauri::command]
+async fn wallet_import(
+    app: tauri::AppHandle,
+    password: String,
+    private_key: Option<String>,
+    keystore_json: Option<String>,
+) -> AppResult<WalletInfo> {
+    tauri::async_runtime::spawn_blocking(move || match (private_key, keystore_json) {
+        (Some(private_key), None) => wallet::import_private_key(&app, &private_key, &password),
+        (None, Some(keystore_json)) => wallet::import_keystore(&app, &keystore_json, &password),
+        _ => Err(AppError::InvalidInput(
+            "Provide either a private key or a keystore JSON".to_string(),
+        )),
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+#[tauri::command]
+fn wallet_list(app: tauri::AppHandle) -> AppResult<Vec<WalletInfo>> {
+    wallet::list(&app)
+}
+
+#[tauri::command]
+async fn wallet_unlock(
+    app: tauri::AppHandle,
+    address: String,
+    password: String,
+) -> AppResult<WalletInfo> {
+    tauri::async_runtime::spawn_blocking(move || wallet::unlock(&app, &address, &password))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+#[tauri::command]
+fn wallet_lock(app: tauri::AppHandle) {
+    wallet::lock(&app)
+}
+
+#[tauri::command]
+async fn wallet_export(app: tauri::AppHandle, address: String) -> AppResult<String> {
+    tauri::async_runtime::spawn_blocking(move || wallet::export(&app, &address))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+#[tauri::command]
+fn sign_message(app: tauri::AppHandle, message: String) -> AppResult<String> {
+    wallet::sign_message(&app, &message)
+}
+
+#[tauri::command]
+fn sign_typed_data(
+    app: tauri::AppHandle,
+    typed_data: alloy::dyn_abi::TypedData,
+) -> AppResult<String> {
+    wallet::sign_typed_data(&app, &typed_data)
+}
+
+#[tauri::command]
+fn sign_transaction(app: tauri::AppHandle, tx: UnsignedTransaction) -> AppResult<String> {
+    wallet::sign_transaction(&app, tx)
+}
+
+// Network settings (proxy, timeouts, retries)
+#[tauri::command]
+fn get_http_settings(http: tauri::State<'_, HttpClient>) -> HttpSettings {
+    http.settings()
+}
+
+#[tauri::command]
+fn set_http_settings(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, HttpClient>,
+    settings: HttpSettings,
+) -> AppResult<()> {
+    http.reconfigure(settings.clone())?;
+    settings.save(&app)
+}
 
-            let store = app.store("license.json").map_err(|e: tauri_plugin_store::Error| e.to_string())?;
+// OHLCV candles, raced across Binance, Bybit, KuCoin and OKX
+#[tauri::command]
+async fn fetch_candles(
+    app: tauri::AppHandle,
+    symbol: String,
+    timeframe: Timeframe,
+    limit: u32,
+) -> AppResult<Vec<Candle>> {
+    market_data::fetch_candles(&app, &symbol, timeframe, limit).await
+}
 
-            store.set(
-                "license",
-                serde_json::to_value(&stored_license).map_err(|e: serde_json::Error| e.to_string())?,
-            );
-            let _ = store.save();
-        }
+// Multi-timeframe signal computed locally, same engine as the bot-service
+#[tauri::command]
+async fn generate_signal(
+    app: tauri::AppHandle,
+    symbol: String,
+    timeframes: Option<Vec<Timeframe>>,
+) -> AppResult<UnifiedSignal> {
+    let timeframes = timeframes.unwrap_or_else(|| signals::DEFAULT_TIMEFRAMES.to_vec());
+    let signal = signals::generate_signal(&app, &symbol, &timeframes).await?;
+    if let Err(e) = journal::record_signal(&app, &signal) {
+        log::warn!("Failed to journal signal for {}: {}", symbol, e);
     }
+    alerts::on_signal(&app, &signal);
+    Ok(signal)
+}
 
-    Ok(validation)
+// Live prices, pushed as price://tick and price://candle events
+#[tauri::command]
+fn price_subscribe(
+    app: tauri::AppHandle,
+    symbol: String,
+    timeframe: Timeframe,
+    feed: Option<Feed>,
+    url: Option<String>,
+) -> AppResult<StreamInfo> {
+    price_stream::subscribe(&app, feed.unwrap_or(Feed::Binance), &symbol, timeframe, url)
+}
+
+#[tauri::command]
+fn price_unsubscribe(
+    app: tauri::AppHandle,
+    symbol: String,
+    timeframe: Timeframe,
+    feed: Option<Feed>,
+) {
+    price_stream::unsubscribe(&app, feed.unwrap_or(Feed::Binance), &symbol, timeframe)
 }
 
-// Clear stored license (for logout/deactivation)
 #[tauri::command]
-async fn clear_license(app: tauri::AppHandle) -> Result<(), String> {
-    let store = app.store("license.json").map_err(|e: tauri_plugin_store::Error| e.to_string())?;
+fn price_unsubscribe_all(app: tauri::AppHandle) {
+    price_stream::unsubscribe_all(&app)
+}
 
-    let _ = store.delete("license");
-    let _ = store.save();
+#[tauri::command]
+fn price_subscriptions(app: tauri::AppHandle) -> Vec<StreamInfo> {
+    price_stream::list(&app)
+}
 
-    Ok(())
+// Replay a CSV or JSON candle file through the signal engine and exit rules
+#[tauri::command]
+async fn run_backtest(
+    path: String,
+    timeframe: Option<Timeframe>,
+    config: Option<BacktestConfig>,
+) -> AppResult<BacktestReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let candles = backtest::load_candles(std::path::Path::new(&path))?;
+        backtest::run(&candles, timeframe, &config.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+// Paper trading: same open/close/list surface as the vault positions, but
+// filled against live prices with a virtual USDC balance
+#[tauri::command]
+async fn paper_open_position(app: tauri::AppHandle, order: PaperOrder) -> AppResult<PaperPosition> {
+    paper::open_position(&app, order).await
+}
+
+#[tauri::command]
+async fn paper_close_position(app: tauri::AppHandle, id: String) -> AppResult<PaperPosition> {
+    paper::close_position(&app, &id).await
+}
+
+#[tauri::command]
+fn paper_positions(
+    app: tauri::AppHandle,
+    include_closed: Option<bool>,
+) -> AppResult<Vec<PaperPosition>> {
+    paper::positions(&app, include_closed.unwrap_or(false))
+}
+
+#[tauri::command]
+async fn paper_account(app: tauri::AppHandle) -> AppResult<PaperSummary> {
+    paper::summary(&app).await
+}
+
+#[tauri::command]
+fn paper_get_settings(app: tauri::AppHandle) -> AppResult<PaperSettings> {
+    paper::settings(&app)
+}
+
+#[tauri::command]
+fn paper_set_settings(app: tauri::AppHandle, settings: PaperSettings) -> AppResult<()> {
+    paper::set_settings(&app, settings)
+}
+
+#[tauri::command]
+fn paper_reset(app: tauri::AppHandle, starting_balance: Option<f64>) -> AppResult<()> {
+    paper::reset(&app, starting_balance)
+}
+
+// Local trade journal: live positions are recorded by the webview, paper
+// trades, generated signals and license events are recorded automatically
+#[tauri::command]
+fn journal_record_position(
+    app: tauri::AppHandle,
+    position: JournalPosition,
+    fill: Option<JournalFill>,
+) -> AppResult<()> {
+    journal::record_trade(&app, position, fill)
+}
+
+#[tauri::command]
+fn journal_record_fill(app: tauri::AppHandle, fill: JournalFill) -> AppResult<i64> {
+    journal::record_fill(&app, fill)
+}
+
+#[tauri::command]
+fn journal_record_fee(app: tauri::AppHandle, fee: JournalFee) -> AppResult<i64> {
+    journal::record_fee(&app, fee)
+}
+
+#[tauri::command]
+fn journal_positions(
+    app: tauri::AppHandle,
+    filter: Option<JournalFilter>,
+) -> AppResult<Vec<JournalPosition>> {
+    journal::positions(&app, &filter.unwrap_or_default())
+}
+
+#[tauri::command]
+fn journal_fills(app: tauri::AppHandle, position_id: String) -> AppResult<Vec<JournalFill>> {
+    journal::fills(&app, &position_id)
+}
+
+#[tauri::command]
+fn journal_pnl(app: tauri::AppHandle, filter: Option<JournalFilter>) -> AppResult<PnlSummary> {
+    journal::pnl(&app, &filter.unwrap_or_default())
+}
+
+#[tauri::command]
+fn journal_signals(
+    app: tauri::AppHandle,
+    symbol: Option<String>,
+    limit: Option<u32>,
+) -> AppResult<Vec<JournalSignal>> {
+    journal::signals(&app, symbol.as_deref(), limit.unwrap_or(100))
+}
+
+#[tauri::command]
+fn journal_license_events(
+    app: tauri::AppHandle,
+    limit: Option<u32>,
+) -> AppResult<Vec<LicenseEvent>> {
+    journal::license_events(&app, limit.unwrap_or(100))
+}
+
+// Pull-only: brings the wallets' Supabase positions and trade history into
+// the journal, local trades are never uploaded
+#[tauri::command]
+async fn journal_sync(app: tauri::AppHandle, wallets: Vec<String>) -> AppResult<SyncReport> {
+    journal::sync(&app, &wallets).await
+}
+
+// Vault reads over JSON-RPC, same calls as vault.ts
+#[tauri::command]
+async fn vault_user_status(app: tauri::AppHandle, user: String) -> AppResult<VaultUserStatus> {
+    chain::user_status(&app, &user).await
+}
+
+#[tauri::command]
+async fn vault_positions(app: tauri::AppHandle, user: String) -> AppResult<Vec<VaultPosition>> {
+    chain::positions(&app, &user).await
+}
+
+#[tauri::command]
+async fn vault_health(app: tauri::AppHandle) -> AppResult<VaultHealth> {
+    chain::health(&app).await
+}
+
+// Vault writes, signed with the unlocked wallet
+#[tauri::command]
+async fn vault_estimate(app: tauri::AppHandle, action: VaultAction) -> AppResult<GasQuote> {
+    tx::estimate(&app, &action).await
+}
+
+#[tauri::command]
+async fn vault_send(app: tauri::AppHandle, action: VaultAction) -> AppResult<TxRecord> {
+    tx::send(&app, &action).await
+}
+
+#[tauri::command]
+fn vault_transactions(app: tauri::AppHandle) -> Vec<TxRecord> {
+    tx::transactions(&app)
+}
+
+// RPC endpoints and contract addresses
+#[tauri::command]
+fn get_chain_settings(app: tauri::AppHandle) -> ChainSettings {
+    ChainSettings::load(&app)
+}
+
+#[tauri::command]
+fn set_chain_settings(app: tauri::AppHandle, settings: ChainSettings) -> AppResult<()> {
+    settings.save(&app)
+}
+
+// Vault event index in the journal, for tracing where a user's USDC went
+#[tauri::command]
+async fn indexer_sync(app: tauri::AppHandle) -> AppResult<Vec<IndexProgress>> {
+    indexer::sync(&app).await
+}
+
+#[tauri::command]
+async fn vault_ledger(app: tauri::AppHandle, user: String) -> AppResult<UserLedger> {
+    indexer::ledger(&app, &user).await
+}
+
+// Every known vault deployment and its contract generation
+#[tauri::command]
+fn vault_registry() -> Vec<VaultDeployment> {
+    vaults::DEPLOYMENTS.to_vec()
+}
+
+// Stuck funds across the current and legacy vaults, and the transactions
+// that recover them
+#[tauri::command]
+async fn diagnose_vault(app: tauri::AppHandle, user: String) -> AppResult<RecoveryReport> {
+    recovery::diagnose(&app, &user).await
+}
+
+#[tauri::command]
+async fn recovery_execute(app: tauri::AppHandle, step: RecoveryStep) -> AppResult<TxRecord> {
+    recovery::execute(&app, &step).await
+}
+
+#[tauri::command]
+fn get_indexer_settings(app: tauri::AppHandle) -> IndexerSettings {
+    IndexerSettings::load(&app)
+}
+
+#[tauri::command]
+fn set_indexer_settings(app: tauri::AppHandle, settings: IndexerSettings) -> AppResult<()> {
+    settings.save(&app)
+}
+
+// Vault positions marked against GMX by the background monitor
+#[tauri::command]
+fn gmx_positions(app: tauri::AppHandle) -> Vec<PositionHealth> {
+    gmx_monitor::positions(&app)
+}
+
+#[tauri::command]
+fn get_gmx_monitor_settings(app: tauri::AppHandle) -> GmxMonitorSettings {
+    GmxMonitorSettings::load(&app)
+}
+
+#[tauri::command]
+fn set_gmx_monitor_settings(app: tauri::AppHandle, settings: GmxMonitorSettings) -> AppResult<()> {
+    settings.save(&app)
+}
+
+// Alert rules, evaluated in the background and delivered as native
+// notifications
+#[tauri::command]
+fn alert_rules(app: tauri::AppHandle) -> Vec<AlertRule> {
+    alerts::rules(&app)
+}
+
+#[tauri::command]
+fn save_alert_rule(app: tauri::AppHandle, rule: AlertRule) -> AppResult<AlertRule> {
+    alerts::save_rule(&app, rule)
+}
+
+#[tauri::command]
+fn delete_alert_rule(app: tauri::AppHandle, id: String) -> AppResult<()> {
+    alerts::delete_rule(&app, &id)
+}
+
+#[tauri::command]
+fn alert_events(app: tauri::AppHandle, limit: Option<u32>) -> AppResult<Vec<AlertEvent>> {
+    journal::alert_events(&app, limit.unwrap_or(100))
+}
+
+// Close-to-tray behaviour of the main window
+#[tauri::command]
+fn get_tray_settings(app: tauri::AppHandle) -> TraySettings {
+    TraySettings::load(&app)
+}
+
+#[tauri::command]
+fn set_tray_settings(app: tauri::AppHandle, settings: TraySettings) -> AppResult<()> {
+    settings.save(&app)
+}
+
+// Tax and accounting CSV from the journal, written where the user chose in
+// the save dialog. None when the dialog was cancelled.
+#[tauri::command]
+async fn export_trades(
+    app: tauri::AppHandle,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+) -> AppResult<Option<ExportSummary>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(path) = export::choose_path(&app, format)? else {
+            return Ok(None);
+        };
+        export::export(&app, &path, format, &options.unwrap_or_default()).map(Some)
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -145,6 +683,19 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(SecretsState::default())
+        .manage(WalletState::default())
+        .manage(MarketDataCache::default())
+        .manage(PriceStreams::default())
+        .manage(PaperState::default())
+        .manage(Journal::default())
+        .manage(ChainState::default())
+        .manage(TxState::default())
+        .manage(IndexerState::default())
+        .manage(GmxMonitorState::default())
+        .manage(AlertState::default())
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -153,13 +704,110 @@ pub fn run() {
                         .build(),
                 )?;
             }
+
+            // A build without a usable backend profile refuses to start
+            BackendConfig::active()?;
+
+            // Shared HTTP client, configured from the persisted network settings
+            let http = match HttpClient::new(HttpSettings::load(app.handle())) {
+                Ok(http) => http,
+                Err(e) => {
+                    log::error!("Invalid network settings, using defaults: {}", e);
+                    HttpClient::new(HttpSettings::default())?
+                }
+            };
+            app.manage(http);
+
+            // Without a tray the window closes as usual
+            if let Err(e) = tray::create(app.handle()) {
+                log::error!("Failed to create the tray icon: {}", e);
+            }
+
+            tauri::async_runtime::spawn(license_monitor::run(app.handle().clone()));
+            tauri::async_runtime::spawn(paper::run(app.handle().clone()));
+            tauri::async_runtime::spawn(gmx_monitor::run(app.handle().clone()));
+            tauri::async_runtime::spawn(alerts::run(app.handle().clone()));
+            tauri::async_runtime::spawn(tray::run(app.handle().clone()));
+
             Ok(())
         })
+        .on_window_event(tray::on_window_event)
         .invoke_handler(tauri::generate_handler![
             get_machine_id,
             get_stored_license,
             validate_license,
-            clear_license
+            get_backend_config,
+            clear_license,
+            deactivate_license,
+            transfer_license,
+            get_license_monitor_config,
+            set_license_monitor_config,
+            secrets_status,
+            secrets_unlock,
+            secrets_lock,
+            secrets_change_passphrase,
+            secret_get,
+            secret_set,
+            secret_delete,
+            get_http_settings,
+            set_http_settings,
+            fetch_candles,
+            generate_signal,
+            price_subscribe,
+            price_unsubscribe,
+            price_unsubscribe_all,
+            price_subscriptions,
+            run_backtest,
+            paper_open_position,
+            paper_close_position,
+            paper_positions,
+            paper_account,
+            paper_get_settings,
+            paper_set_settings,
+            paper_reset,
+            journal_record_position,
+            journal_record_fill,
+            journal_record_fee,
+            journal_positions,
+            journal_fills,
+            journal_pnl,
+            journal_signals,
+            journal_license_events,
+            journal_sync,
+            export_trades,
+            vault_user_status,
+            vault_positions,
+            vault_health,
+            vault_estimate,
+            vault_send,
+            vault_transactions,
+            get_chain_settings,
+            set_chain_settings,
+            indexer_sync,
+            vault_ledger,
+            get_indexer_settings,
+            set_indexer_settings,
+            gmx_positions,
+            get_gmx_monitor_settings,
+            set_gmx_monitor_settings,
+            alert_rules,
+            save_alert_rule,
+            delete_alert_rule,
+            alert_events,
+            get_tray_settings,
+            set_tray_settings,
+            vault_registry,
+            diagnose_vault,
+            recovery_execute,
+            wallet_create,
+            wallet_import,
+            wallet_list,
+            wallet_unlock,
+            wallet_lock,
+            wallet_export,
+            sign_message,
+            sign_typed_data,
+            sign_transaction
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,509 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri_plugin_store::StoreExt;
+
+use crate::config::BackendConfig;
+use crate::error::{AppError, AppResult};
+use crate::http::HttpClient;
+use crate::machine_id::{self, MachineFingerprint};
+use crate::secrets;
+
+// Ed25519 public key matching the LICENSE_SIGNING_KEY secret of the
+// validate-desktop-license edge function
+const LICENSE_PUBLIC_KEY: &str = "8WVg8oXI3mOKbfSOLV2oDuFXzA81FbvAlV1OaEuaPMc=";
+
+// Domain separator so a license signature can never be replayed as
+// something else signed with the same key
+const TOKEN_CONTEXT: &str = "monadier-desktop-license-v1";
+const DEACTIVATE_CONTEXT: &str = "monadier-desktop-deactivate-v1";
+
+pub const LICENSE_STORE: &str = "license.json";
+const LICENSE_KEY: &str = "license";
+const LICENSE_SECRET: &str = "internal.license";
+const DEVICE_KEY_SECRET: &str = "internal.device_key";
+const SERVER_URL_KEY: &str = "server_url";
+
+// License validation response from Supabase
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LicenseValidation {
+    pub valid: bool,
+    pub plan_tier: Option<String>,
+    pub error: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub token: Option<LicenseToken>,
+}
+
+impl LicenseValidation {
+    pub fn rejected(error: String) -> Self {
+        Self {
+            valid: false,
+            plan_tier: None,
+            error: Some(error),
+            token: None,
+        }
+    }
+}
+
+// Signed license token issued by the server
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LicenseToken {
+    pub code: String,
+    pub plan_tier: String,
+    pub machine_id: String,
+    pub issued_at: String,
+    pub expires_at: String,
+    pub signature: String,
+}
+
+impl LicenseToken {
+    // Bytes covered by the signature, one field per line
+    fn signed_message(&self) -> Vec<u8> {
+        [
+            TOKEN_CONTEXT,
+            self.code.as_str(),
+            self.plan_tier.as_str(),
+            self.machine_id.as_str(),
+            self.issued_at.as_str(),
+            self.expires_at.as_str(),
+        ]
+        .join("\n")
+        .into_bytes()
+    }
+
+    // Check the signature against the embedded public key
+    pub fn verify_signature(&self) -> AppResult<()> {
+        let key_bytes: [u8; 32] = BASE64
+            .decode(LICENSE_PUBLIC_KEY)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| AppError::Internal("Invalid license public key".to_string()))?;
+        let public_key = VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|e| AppError::Internal(format!("Invalid license public key: {}", e)))?;
+
+        let signature = BASE64
+            .decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| AppError::LicenseInvalid("malformed signature".to_string()))?;
+
+        public_key
+            .verify(&self.signed_message(), &signature)
+            .map_err(|_| AppError::LicenseInvalid("signature verification failed".to_string()))
+    }
+
+    // Unparseable expiry counts as already expired
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&self.expires_at)
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+
+    // Last moment the token is honoured while the server can't be reached
+    pub fn grace_ends_at(&self, grace: Duration) -> DateTime<Utc> {
+        let expires_at = self.expires_at();
+        expires_at.checked_add_signed(grace).unwrap_or(expires_at)
+    }
+
+    // Full offline check: signature, machine binding and expiry plus grace
+    pub fn verify(&self, machine_id: &str, grace: Duration) -> AppResult<()> {
+        self.verify_signature()?;
+
+        if self.machine_id != machine_id {
+            return Err(AppError::MachineMismatch);
+        }
+
+        if self.grace_ends_at(grace) < Utc::now() {
+            return Err(AppError::LicenseInvalid("token has expired".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+// Stored license info
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoredLicense {
+    #[serde(flatten)]
+    pub token: LicenseToken,
+    pub activated_at: String,
+    // Hardware component hashes recorded at activation
+    #[serde(default)]
+    pub fingerprint: machine_id::Components,
+    // Last successful round trip to the server
+    #[serde(default)]
+    pub last_validated_at: Option<String>,
+}
+
+// Read the stored license without verifying it
+pub fn load(app: &tauri::AppHandle) -> AppResult<Option<StoredLicense>> {
+    migrate_plaintext(app)?;
+
+    // Licenses stored before signed tokens existed no longer parse
+    Ok(secrets::get(app, LICENSE_SECRET)?.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+pub fn save(app: &tauri::AppHandle, license: &StoredLicense) -> AppResult<()> {
+    let json = serde_json::to_string(license)?;
+    secrets::set(app, LICENSE_SECRET, &json)
+}
+
+pub fn clear(app: &tauri::AppHandle) -> AppResult<()> {
+    secrets::delete(app, LICENSE_SECRET)
+}
+
+// Move a license left in plaintext license.json into the secrets store
+fn migrate_plaintext(app: &tauri::AppHandle) -> AppResult<()> {
+    let store = app.store(LICENSE_STORE)?;
+
+    if let Some(value) = store.get(LICENSE_KEY) {
+        secrets::set(app, LICENSE_SECRET, &value.to_string())?;
+        let _ = store.delete(LICENSE_KEY);
+        let _ = store.save();
+    }
+
+    Ok(())
+}
+
+// Server the license was activated against, used for background re-validation.
+// Falls back to the profile default if the stored URL is no longer allowed.
+pub fn server_url(app: &tauri::AppHandle, config: &BackendConfig) -> AppResult<String> {
+    let stored = app
+        .store(LICENSE_STORE)
+        .ok()
+        .and_then(|store| store.get(SERVER_URL_KEY))
+        .and_then(|value| value.as_str().map(|s| s.to_string()));
+
+    match stored {
+        Some(url) if config.allows(&url) => config.resolve_url(Some(&url)),
+        _ => config.resolve_url(None),
+    }
+}
+
+pub fn set_server_url(app: &tauri::AppHandle, url: &str) -> AppResult<()> {
+    let store = app.store(LICENSE_STORE)?;
+
+    store.set(SERVER_URL_KEY, serde_json::Value::String(url.to_string()));
+    let _ = store.save();
+
+    Ok(())
+}
+
+// Machine ID this install is bound to. Keeps the ID the license was activated
+// with as long as the hardware fingerprint still fuzzy-matches.
+pub fn resolve_machine_id(app: &tauri::AppHandle) -> String {
+    let current = MachineFingerprint::current();
+
+    match load(app).ok().flatten() {
+        Some(license) if current.matches(&license.fingerprint) => license.token.machine_id,
+        _ => current.id,
+    }
+}
+
+// Per-install Ed25519 key, registered on activation so later requests can
+// prove they come from the bound machine
+pub fn device_key(app: &tauri::AppHandle) -> AppResult<SigningKey> {
+    if let Some(encoded) = secrets::get(app, DEVICE_KEY_SECRET)? {
+        let seed: [u8; 32] = BASE64
+            .decode(encoded)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| AppError::StoreCorrupted("device key".to_string()))?;
+        return Ok(SigningKey::from_bytes(&seed));
+    }
+
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    secrets::set(app, DEVICE_KEY_SECRET, &BASE64.encode(seed))?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+pub fn reset_device_key(app: &tauri::AppHandle) -> AppResult<()> {
+    secrets::delete(app, DEVICE_KEY_SECRET)
+}
+
+pub fn device_public_key(key: &SigningKey) -> String {
+    BASE64.encode(key.verifying_key().to_bytes())
+}
+
+// Ask the server to drop the machine binding, signed with the device key
+pub async fn release_on_server(
+    http: &HttpClient,
+    config: &BackendConfig,
+    server_url: &str,
+    license_code: &str,
+    machine_id: &str,
+    device_key: &SigningKey,
+) -> AppResult<()> {
+    let timestamp = Utc::now().to_rfc3339();
+    let message = [
+        DEACTIVATE_CONTEXT,
+        license_code,
+        machine_id,
+        timestamp.as_str(),
+    ]
+    .join("\n");
+    let signature = BASE64.encode(device_key.sign(message.as_bytes()).to_bytes());
+
+    let mut body = HashMap::new();
+    body.insert("licenseCode", license_code);
+    body.insert("machineId", machine_id);
+    body.insert("timestamp", timestamp.as_str());
+    body.insert("signature", signature.as_str());
+
+    // Not retried, a second attempt would fail once the binding is gone
+    let url = config.deactivate_url(server_url);
+    let response = http.backend().post(&url).json(&body).send().await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(if status.is_server_error() {
+            AppError::ServerUnavailable(error_text)
+        } else {
+            AppError::ServerRejected(error_text)
+        });
+    }
+
+    Ok(())
+}
+
+// What the server said about a license
+pub enum ServerVerdict {
+    // Signed token verified for this machine and code
+    Valid(Box<LicenseValidation>, LicenseToken),
+    // Server answered `valid: false`, or its token failed the signature
+    // check. The only verdict that revokes a stored license.
+    Rejected(String),
+    // No verdict on the license: unreachable, throttled, unauthorized or a
+    // malformed answer
+    Unavailable(AppError),
+}
+
+// Second half of a transfer, after the server released the binding: forget
+// the old identity and activate again. A failed activation puts `stored`
+// back so the code is not lost.
+pub async fn rebind<Fut>(
+    stored: &StoredLicense,
+    forget: impl FnOnce() -> AppResult<()>,
+    activate: impl FnOnce() -> Fut,
+    restore: impl FnOnce(&StoredLicense) -> AppResult<()>,
+) -> AppResult<LicenseValidation>
+where
+    Fut: std::future::Future<Output = AppResult<LicenseValidation>>,
+{
+    forget()?;
+    let error = match activate().await {
+        Ok(validation) if validation.valid => return Ok(validation),
+        Ok(validation) => AppError::ServerRejected(format!(
+            "license {} was released but not activated again: {}",
+            stored.token.code,
+            validation.error.unwrap_or_default()
+        )),
+        Err(e) => AppError::ServerUnavailable(format!(
+            "license {} was released but not activated again, retry the transfer: {}",
+            stored.token.code, e
+        )),
+    };
+
+    restore(stored)?;
+    Err(error)
+}
+
+// Call the Supabase edge function and verify the returned token
+pub async fn check_with_server(
+    http: &HttpClient,
+    config: &BackendConfig,
+    server_url: &str,
+    license_code: &str,
+    machine_id: &str,
+    device_public_key: &str,
+) -> ServerVerdict {
+    let client = http.backend();
+    let url = config.license_url(server_url);
+
+    let mut body = HashMap::new();
+    body.insert("licenseCode", license_code);
+    body.insert("machineId", machine_id);
+    body.insert("devicePublicKey", device_public_key);
+
+    // Validation is idempotent, re-checking the same machine binding
+    let response = match http.send_idempotent(|| client.post(&url).json(&body)).await {
+        Ok(response) => response,
+        Err(e) => return ServerVerdict::Unavailable(e),
+    };
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        // Rate limits, timeouts and a bad anon key say nothing about the license
+        let transient =
+            status.is_server_error() || matches!(status.as_u16(), 401 | 403 | 408 | 429);
+        return ServerVerdict::Unavailable(if transient {
+            AppError::ServerUnavailable(format!("{}: {}", status, error_text))
+        } else {
+            AppError::ServerRejected(format!("Validation failed: {}", error_text))
+        });
+    }
+
+    let mut validation: LicenseValidation = match response.json().await {
+        Ok(validation) => validation,
+        Err(e) => return ServerVerdict::Unavailable(e.into()),
+    };
+
+    if !validation.valid {
+        let error = validation
+            .error
+            .unwrap_or_else(|| "License rejected by server".to_string());
+        return ServerVerdict::Rejected(error);
+    }
+
+    // A bare `valid: true` is not enough, the server must hand out a signed token
+    let token = match validation.token.take() {
+        Some(token) => token,
+        None => {
+            return ServerVerdict::Unavailable(AppError::ServerRejected(
+                "Server did not return a signed license".to_string(),
+            ))
+        }
+    };
+
+    if let Err(e) = token.verify_signature() {
+        return ServerVerdict::Rejected(e.to_string());
+    }
+
+    // A freshly issued token gets no grace
+    if let Err(e) = token.verify(machine_id, Duration::zero()) {
+        return ServerVerdict::Unavailable(e);
+    }
+
+    if !token.code.eq_ignore_ascii_case(license_code.trim()) {
+        return ServerVerdict::Unavailable(AppError::LicenseInvalid(
+            "token was issued for a different code".to_string(),
+        ));
+    }
+
+    ServerVerdict::Valid(Box::new(validation), token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(expires_at: &str) -> LicenseToken {
+        LicenseToken {
+            code: "CODE".into(),
+            plan_tier: "pro".into(),
+            machine_id: "machine".into(),
+            issued_at: "2025-01-01T00:00:00Z".into(),
+            expires_at: expires_at.into(),
+            signature: String::new(),
+        }
+    }
+
+    fn stored() -> StoredLicense {
+        StoredLicense {
+            token: token("2025-02-01T00:00:00Z"),
+            activated_at: "2025-01-01T00:00:00Z".into(),
+            fingerprint: Default::default(),
+            last_validated_at: None,
+        }
+    }
+
+    // Runs rebind with `outcome` as the activation result, returns the
+    // result and the code of the restored license, if any
+    async fn transfer(
+        outcome: AppResult<LicenseValidation>,
+    ) -> (AppResult<LicenseValidation>, Option<String>) {
+        let stored = stored();
+        let mut restored = None;
+        let result = rebind(
+            &stored,
+            || Ok(()),
+            || async { outcome },
+            |license| {
+                restored = Some(license.token.code.clone());
+                Ok(())
+            },
+        )
+        .await;
+        (result, restored)
+    }
+
+    #[tokio::test]
+    async fn failed_reactivation_restores_the_license() {
+        let (result, restored) =
+            transfer(Err(AppError::Network("connection refused".into()))).await;
+        assert!(
+            matches!(&result, Err(AppError::ServerUnavailable(m)) if m.contains("CODE") && m.contains("connection refused")),
+            "{:?}",
+            result
+        );
+        assert_eq!(restored.as_deref(), Some("CODE"));
+
+        let (result, restored) =
+            transfer(Ok(LicenseValidation::rejected("limit reached".into()))).await;
+        assert!(
+            matches!(&result, Err(AppError::ServerRejected(m)) if m.contains("limit reached")),
+            "{:?}",
+            result
+        );
+        assert_eq!(restored.as_deref(), Some("CODE"));
+    }
+
+    #[tokio::test]
+    async fn successful_reactivation_keeps_the_new_license() {
+        let validation = LicenseValidation {
+            valid: true,
+            plan_tier: Some("pro".into()),
+            error: None,
+            token: None,
+        };
+        let (result, restored) = transfer(Ok(validation)).await;
+        assert!(result.unwrap().valid);
+        assert_eq!(restored, None);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_activated_when_forgetting_fails() {
+        let mut activated = false;
+        let result = rebind(
+            &stored(),
+            || Err(AppError::Storage("disk full".into())),
+            || async {
+                activated = true;
+                Ok(LicenseValidation::rejected(String::new()))
+            },
+            |_| Ok(()),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::Storage(_))));
+        assert!(!activated);
+    }
+
+    #[test]
+    fn grace_extends_the_expiry() {
+        assert_eq!(
+            token("2025-02-01T00:00:00Z")
+                .grace_ends_at(Duration::days(7))
+                .to_rfc3339(),
+            "2025-02-08T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn grace_overflow_falls_back_to_the_expiry() {
+        let unparseable = token("never");
+        assert_eq!(
+            unparseable.grace_ends_at(-Duration::days(1)),
+            DateTime::<Utc>::MIN_UTC
+        );
+        assert_eq!(
+            token("2025-02-01T00:00:00Z").grace_ends_at(Duration::MAX),
+            token("2025-02-01T00:00:00Z").expires_at()
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::config::BackendConfig;
+use crate::error::AppResult;
+use crate::http::HttpClient;
+use crate::journal;
+use crate::license::{self, ServerVerdict, LICENSE_STORE};
+
+const CONFIG_KEY: &str = "monitor";
+
+// Upper bound for the grace period and the warning lead. The store is
+// writable from the webview, so both are clamped on load as well as save.
+const MAX_GRACE_SECS: i64 = 30 * 24 * 60 * 60;
+
+// Background re-validation settings, persisted next to the license
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonitorConfig {
+    pub check_interval_secs: u64,
+    // How long an expired token is honoured while the server is unreachable
+    pub grace_period_secs: i64,
+    // Start warning this long before the grace period runs out
+    pub warn_before_secs: i64,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 6 * 60 * 60,
+            grace_period_secs: 7 * 24 * 60 * 60,
+            warn_before_secs: 3 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl MonitorConfig {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        app.store(LICENSE_STORE)
+            .ok()
+            .and_then(|store| store.get(CONFIG_KEY))
+            .and_then(|value| serde_json::from_value::<Self>(value.clone()).ok())
+            .unwrap_or_default()
+            .bounded()
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> AppResult<()> {
+        let store = app.store(LICENSE_STORE)?;
+
+        store.set(CONFIG_KEY, serde_json::to_value(self.clone().bounded())?);
+        store.save()?;
+
+        Ok(())
+    }
+
+    fn bounded(mut self) -> Self {
+        self.grace_period_secs = self.grace_period_secs.clamp(0, MAX_GRACE_SECS);
+        self.warn_before_secs = self.warn_before_secs.clamp(0, MAX_GRACE_SECS);
+        self
+    }
+
+    pub fn grace_period(&self) -> Duration {
+        Duration::try_seconds(self.grace_period_secs.clamp(0, MAX_GRACE_SECS)).unwrap_or_default()
+    }
+
+    pub fn warn_before(&self) -> Duration {
+        Duration::try_seconds(self.warn_before_secs.clamp(0, MAX_GRACE_SECS)).unwrap_or_default()
+    }
+}
+
+// Payload for license://expiring and license://revoked
+#[derive(Debug, Serialize, Clone)]
+pub struct LicenseEvent {
+    pub reason: String,
+    pub expires_at: Option<String>,
+    pub grace_ends_at: Option<String>,
+}
+
+// Re-validate the stored license forever, started from run()'s setup hook
+pub async fn run(app: tauri::AppHandle) {
+    loop {
+        let config = MonitorConfig::load(&app);
+        check_once(&app, &config).await;
+
+        let interval = config.check_interval_secs.max(60);
+        tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+    }
+}
+
+async fn check_once(app: &tauri::AppHandle, config: &MonitorConfig) {
+    let mut stored = match license::load(app) {
+        Ok(Some(stored)) => stored,
+        _ => return,
+    };
+
+    let device_key = match license::device_key(app) {
+        Ok(key) => key,
+        Err(e) => {
+            log::error!("Device key unavailable: {}", e);
+            return;
+        }
+    };
+
+    let target =
+        BackendConfig::active().and_then(|config| Ok((config, license::server_url(app, config)?)));
+
+    let verdict = match target {
+        Ok((config, url)) => {
+            let machine_id = license::resolve_machine_id(app);
+            let public_key = license::device_public_key(&device_key);
+            let http = app.state::<HttpClient>();
+            license::check_with_server(
+                &http,
+                config,
+                &url,
+                &stored.token.code,
+                &machine_id,
+                &public_key,
+            )
+            .await
+        }
+        Err(e) => ServerVerdict::Unavailable(e),
+    };
+
+    match verdict {
+        ServerVerdict::Valid(_, token) => {
+            stored.token = token;
+            stored.last_validated_at = Some(Utc::now().to_rfc3339());
+            if let Err(e) = license::save(app, &stored) {
+                log::error!("Failed to store refreshed license: {}", e);
+            }
+        }
+        ServerVerdict::Rejected(reason) => {
+            log::warn!("License revoked by server: {}", reason);
+            if let Err(e) = license::clear(app) {
+                log::error!("Failed to clear the revoked license: {}", e);
+            }
+            emit(app, "license://revoked", reason, None, None);
+        }
+        ServerVerdict::Unavailable(reason) => {
+            log::info!("License server unavailable: {}", reason);
+
+            let expires_at = stored.token.expires_at();
+            let grace_ends_at = stored.token.grace_ends_at(config.grace_period());
+            let now = Utc::now();
+
+            if grace_ends_at < now {
+                emit(
+                    app,
+                    "license://revoked",
+                    "Offline grace period has ended".to_string(),
+                    Some(expires_at.to_rfc3339()),
+                    Some(grace_ends_at.to_rfc3339()),
+                );
+            } else if now + config.warn_before() > grace_ends_at {
+                emit(
+                    app,
+                    "license://expiring",
+                    "License could not be re-validated".to_string(),
+                    Some(expires_at.to_rfc3339()),
+                    Some(grace_ends_at.to_rfc3339()),
+                );
+            }
+        }
+    }
+}
+
+fn emit(
+    app: &tauri::AppHandle,
+    event: &str,
+    reason: String,
+    expires_at: Option<String>,
+    grace_ends_at: Option<String>,
+) {
+    journal::record_license_event(app, event, Some(&reason));
+
+    let payload = LicenseEvent {
+        reason,
+        expires_at,
+        grace_ends_at,
+    };
+    if let Err(e) = app.emit(event, payload) {
+        log::error!("Failed to emit {}: {}", event, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(grace_period_secs: i64, warn_before_secs: i64) -> MonitorConfig {
+        MonitorConfig {
+            grace_period_secs,
+            warn_before_secs,
+            ..MonitorConfig::default()
+        }
+    }
+
+    #[test]
+    fn clamps_out_of_range_periods() {
+        let bounded = config(i64::MAX, i64::MIN).bounded();
+        assert_eq!(bounded.grace_period_secs, MAX_GRACE_SECS);
+        assert_eq!(bounded.warn_before_secs, 0);
+
+        let bounded = config(-1, MAX_GRACE_SECS + 1).bounded();
+        assert_eq!(bounded.grace_period_secs, 0);
+        assert_eq!(bounded.warn_before_secs, MAX_GRACE_SECS);
+
+        let defaults = MonitorConfig::default();
+        let bounded = defaults.clone().bounded();
+        assert_eq!(bounded.grace_period_secs, defaults.grace_period_secs);
+        assert_eq!(bounded.warn_before_secs, defaults.warn_before_secs);
+    }
+
+    #[test]
+    fn durations_never_exceed_the_bound() {
+        // Values written straight into the store skip bounded()
+        let unbounded = config(i64::MAX, i64::MIN);
+        assert_eq!(unbounded.grace_period(), Duration::days(30));
+        assert_eq!(unbounded.warn_before(), Duration::zero());
+    }
+}
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::error::{AppError, AppResult};
+
+// Hashed hardware/OS identifiers keyed by source name
+pub type Components = BTreeMap<String, String>;
+
+// Sources that survive renames and network changes, most stable first
+const STABLE_SOURCES: [&str; 2] = ["os_machine_id", "product_uuid"];
+
+// Machine fingerprint built from several stable sources
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MachineFingerprint {
+    pub id: String,
+    pub components: Components,
+}
+
+impl MachineFingerprint {
+    // Collected once per process, the sources spawn commands and read files
+    pub fn current() -> Self {
+        static CURRENT: OnceLock<MachineFingerprint> = OnceLock::new();
+        CURRENT.get_or_init(Self::collect).clone()
+    }
+
+    // Collect every source available on this platform
+    fn collect() -> Self {
+        let mut components = Components::new();
+
+        let sources = [
+            ("os_machine_id", os_machine_id()),
+            ("product_uuid", product_uuid()),
+            ("mac", primary_mac()),
+            ("host", host_user()),
+        ];
+        for (name, value) in sources {
+            if let Some(value) = value {
+                components.insert(name.to_string(), sha256_hex(&value));
+            }
+        }
+
+        let id = fingerprint_id(&components);
+        Self { id, components }
+    }
+
+    // Most stable single source, used as key material for the secrets store.
+    // Hostname and MAC change too easily to lock secrets behind them.
+    pub fn key_material(&self) -> AppResult<String> {
+        STABLE_SOURCES
+            .iter()
+            .find_map(|name| self.components.get(*name))
+            .cloned()
+            .ok_or_else(|| {
+                AppError::Crypto("no stable machine identifier is available".to_string())
+            })
+    }
+
+    // Fuzzy match against a previously recorded fingerprint, k-of-n sources must agree
+    pub fn matches(&self, recorded: &Components) -> bool {
+        if recorded.is_empty() {
+            return false;
+        }
+
+        let agreeing = recorded
+            .iter()
+            .filter(|(name, hash)| self.components.get(*name) == Some(*hash))
+            .count();
+
+        agreeing >= required_matches(recorded.len())
+    }
+}
+
+// With three or more sources one of them may change (hostname, NIC swap)
+fn required_matches(total: usize) -> usize {
+    if total >= 3 {
+        total - 1
+    } else {
+        total
+    }
+}
+
+// Stable ID derived from all component hashes
+fn fingerprint_id(components: &Components) -> String {
+    let joined = components
+        .iter()
+        .map(|(name, hash)| format!("{}={}", name, hash))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let hash = sha256_hex(&joined);
+    format!("DSK-{}", hash[..16].to_uppercase())
+}
+
+fn sha256_hex(input: &str) -> String {
+    Sha256::digest(input.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Trimmed, lowercased value or None when empty
+fn normalize(value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_file(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| normalize(&contents))
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// OS install identifier
+#[cfg(target_os = "linux")]
+fn os_machine_id() -> Option<String> {
+    read_file("/etc/machine-id").or_else(|| read_file("/var/lib/dbus/machine-id"))
+}
+
+#[cfg(target_os = "macos")]
+fn os_machine_id() -> Option<String> {
+    let output = command_output("ioreg", &["-rd1", "-c", "IOPlatformExpertDevice"])?;
+    output
+        .lines()
+        .find(|line| line.contains("IOPlatformUUID"))
+        .and_then(|line| line.split('"').nth(3))
+        .and_then(normalize)
+}
+
+#[cfg(target_os = "windows")]
+fn os_machine_id() -> Option<String> {
+    let output = command_output(
+        "reg",
+        &[
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+        ],
+    )?;
+    output
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .and_then(normalize)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn os_machine_id() -> Option<String> {
+    None
+}
+
+// Motherboard UUID from DMI/SMBIOS
+#[cfg(target_os = "linux")]
+fn product_uuid() -> Option<String> {
+    read_file("/sys/class/dmi/id/product_uuid")
+}
+
+// wmic is deprecated and missing from recent Windows builds
+#[cfg(target_os = "windows")]
+fn product_uuid() -> Option<String> {
+    let output = command_output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "(Get-CimInstance -ClassName Win32_ComputerSystemProduct).UUID",
+        ],
+    )?;
+    output.lines().find_map(normalize)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn product_uuid() -> Option<String> {
+    // macOS exposes the SMBIOS UUID as IOPlatformUUID, already covered above
+    None
+}
+
+// MAC address of the primary network interface
+fn primary_mac() -> Option<String> {
+    match mac_address::get_mac_address() {
+        Ok(Some(mac)) if mac.bytes() != [0; 6] => normalize(&mac.to_string()),
+        _ => None,
+    }
+}
+
+// Weakest source, kept so a single hardware change is still tolerated
+fn host_user() -> Option<String> {
+    let hostname = hostname::get().ok()?.to_string_lossy().to_string();
+    normalize(&format!("{}:{}", hostname, whoami::username()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(sources: &[(&str, &str)]) -> MachineFingerprint {
+        let components: Components = sources
+            .iter()
+            .map(|(name, value)| (name.to_string(), sha256_hex(value)))
+            .collect();
+        MachineFingerprint {
+            id: fingerprint_id(&components),
+            components,
+        }
+    }
+
+    #[test]
+    fn key_material_prefers_the_os_machine_id() {
+        let all = fingerprint(&[
+            ("os_machine_id", "a"),
+            ("product_uuid", "b"),
+            ("mac", "c"),
+            ("host", "d"),
+        ]);
+        assert_eq!(all.key_material().unwrap(), sha256_hex("a"));
+
+        let no_machine_id = fingerprint(&[("product_uuid", "b"), ("mac", "c")]);
+        assert_eq!(no_machine_id.key_material().unwrap(), sha256_hex("b"));
+    }
+
+    #[test]
+    fn key_material_never_uses_host_or_mac() {
+        let unstable = fingerprint(&[("mac", "c"), ("host", "d")]);
+        assert!(matches!(unstable.key_material(), Err(AppError::Crypto(_))));
+    }
+
+    #[test]
+    fn tolerates_one_changed_source() {
+        let recorded = fingerprint(&[
+            ("os_machine_id", "a"),
+            ("product_uuid", "b"),
+            ("mac", "c"),
+            ("host", "d"),
+        ]);
+        let renamed = fingerprint(&[
+            ("os_machine_id", "a"),
+            ("product_uuid", "b"),
+            ("mac", "c"),
+            ("host", "e"),
+        ]);
+        assert!(renamed.matches(&recorded.components));
+
+        let rebuilt = fingerprint(&[
+            ("os_machine_id", "x"),
+            ("product_uuid", "b"),
+            ("mac", "c"),
+            ("host", "e"),
+        ]);
+        assert!(!rebuilt.matches(&recorded.components));
+    }
+}
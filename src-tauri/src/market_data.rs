@@ -0,0 +1,572 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+use tokio::task::JoinSet;
+
+use crate::error::{AppError, AppResult};
+use crate::http::HttpClient;
+
+// Per-exchange budget, a slow exchange must not hold up the race
+const EXCHANGE_TIMEOUT: Duration = Duration::from_secs(3);
+
+const MAX_LIMIT: u32 = 1000;
+
+// OKX caps a single candles request at 300 rows, larger requests race
+// without it
+const OKX_MAX_LIMIT: u32 = 300;
+
+// Quote assets the exchanges that need a dash separator know about
+const QUOTE_ASSETS: [&str; 4] = ["USDT", "USDC", "BTC", "ETH"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Candle {
+    // Open time, unix milliseconds
+    pub time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timeframe {
+    #[serde(rename = "1m")]
+    M1,
+    #[serde(rename = "5m")]
+    M5,
+    #[serde(rename = "15m")]
+    M15,
+    #[serde(rename = "1h")]
+    H1,
+    #[serde(rename = "4h")]
+    H4,
+    #[serde(rename = "1d")]
+    D1,
+}
+
+impl Timeframe {
+    pub fn seconds(self) -> i64 {
+        match self {
+            Timeframe::M1 => 60,
+            Timeframe::M5 => 300,
+            Timeframe::M15 => 900,
+            Timeframe::H1 => 3_600,
+            Timeframe::H4 => 14_400,
+            Timeframe::D1 => 86_400,
+        }
+    }
+
+    // How long a fetched series stays fresh, shorter candles move faster
+    fn cache_ttl(self) -> Duration {
+        Duration::from_secs(match self {
+            Timeframe::M1 => 5,
+            Timeframe::M5 => 15,
+            Timeframe::M15 => 30,
+            Timeframe::H1 => 60,
+            Timeframe::H4 => 120,
+            Timeframe::D1 => 300,
+        })
+    }
+
+    fn binance(self) -> &'static str {
+        match self {
+            Timeframe::M1 => "1m",
+            Timeframe::M5 => "5m",
+            Timeframe::M15 => "15m",
+            Timeframe::H1 => "1h",
+            Timeframe::H4 => "4h",
+            Timeframe::D1 => "1d",
+        }
+    }
+
+    fn bybit(self) -> &'static str {
+        match self {
+            Timeframe::M1 => "1",
+            Timeframe::M5 => "5",
+            Timeframe::M15 => "15",
+            Timeframe::H1 => "60",
+            Timeframe::H4 => "240",
+            Timeframe::D1 => "D",
+        }
+    }
+
+    fn kucoin(self) -> &'static str {
+        match self {
+            Timeframe::M1 => "1min",
+            Timeframe::M5 => "5min",
+            Timeframe::M15 => "15min",
+            Timeframe::H1 => "1hour",
+            Timeframe::H4 => "4hour",
+            Timeframe::D1 => "1day",
+        }
+    }
+
+    fn okx(self) -> &'static str {
+        match self {
+            Timeframe::M1 => "1m",
+            Timeframe::M5 => "5m",
+            Timeframe::M15 => "15m",
+            Timeframe::H1 => "1H",
+            Timeframe::H4 => "4H",
+            Timeframe::D1 => "1Dutc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exchange {
+    Binance,
+    Bybit,
+    KuCoin,
+    Okx,
+}
+
+// Race order. The first exchange to answer wins, whatever its place here.
+const EXCHANGES: [Exchange; 4] = [
+    Exchange::Binance,
+    Exchange::Bybit,
+    Exchange::KuCoin,
+    Exchange::Okx,
+];
+
+// Exchanges that can serve `limit` candles in one request
+fn racing(limit: u32) -> impl Iterator<Item = Exchange> {
+    EXCHANGES
+        .into_iter()
+        .filter(move |e| limit <= e.max_limit())
+}
+
+impl Exchange {
+    pub fn name(self) -> &'static str {
+        match self {
+            Exchange::Binance => "Binance",
+            Exchange::Bybit => "Bybit",
+            Exchange::KuCoin => "KuCoin",
+            Exchange::Okx => "OKX",
+        }
+    }
+
+    // Most candles one request returns
+    fn max_limit(self) -> u32 {
+        match self {
+            Exchange::Okx => OKX_MAX_LIMIT,
+            _ => MAX_LIMIT,
+        }
+    }
+
+    fn url(self, symbol: &str, timeframe: Timeframe, limit: u32) -> String {
+        match self {
+            Exchange::Binance => format!(
+                "https://api.binance.com/api/v3/klines?symbol={}&interval={}&limit={}",
+                symbol,
+                timeframe.binance(),
+                limit
+            ),
+            Exchange::Bybit => format!(
+                "https://api.bybit.com/v5/market/kline?category=spot&symbol={}&interval={}&limit={}",
+                symbol,
+                timeframe.bybit(),
+                limit
+            ),
+            Exchange::KuCoin => {
+                // KuCoin takes a time range instead of a count
+                let end_at = chrono::Utc::now().timestamp();
+                let start_at = end_at - i64::from(limit) * timeframe.seconds();
+                format!(
+                    "https://api.kucoin.com/api/v1/market/candles?type={}&symbol={}&startAt={}&endAt={}",
+                    timeframe.kucoin(),
+                    dashed_symbol(symbol),
+                    start_at,
+                    end_at
+                )
+            }
+            Exchange::Okx => format!(
+                "https://www.okx.com/api/v5/market/candles?instId={}&bar={}&limit={}",
+                dashed_symbol(symbol),
+                timeframe.okx(),
+                limit
+            ),
+        }
+    }
+
+    pub fn parse(self, body: &Value) -> AppResult<Vec<Candle>> {
+        match self {
+            Exchange::Binance => parse_binance(body),
+            Exchange::Bybit => parse_bybit(body),
+            Exchange::KuCoin => parse_kucoin(body),
+            Exchange::Okx => parse_okx(body),
+        }
+    }
+}
+
+// BTCUSDT -> BTC-USDT, as KuCoin and OKX expect
+fn dashed_symbol(symbol: &str) -> String {
+    QUOTE_ASSETS
+        .iter()
+        .find_map(|quote| {
+            symbol
+                .strip_suffix(quote)
+                .filter(|base| !base.is_empty())
+                .map(|base| format!("{}-{}", base, quote))
+        })
+        .unwrap_or_else(|| symbol.to_string())
+}
+
+fn malformed(exchange: &str) -> AppError {
+    AppError::ServerUnavailable(format!("{}: unexpected candle format", exchange))
+}
+
+// Exchanges send numbers either as JSON numbers or as strings
+fn number(row: &[Value], index: usize) -> Option<f64> {
+    match row.get(index)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+// Map each row through `columns`, a (time, open, high, low, close, volume)
+// index list, and return the candles oldest first
+fn parse_rows(
+    exchange: &str,
+    rows: Option<&Value>,
+    columns: [usize; 6],
+    time_scale: i64,
+    newest_first: bool,
+) -> AppResult<Vec<Candle>> {
+    let rows = rows
+        .and_then(Value::as_array)
+        .ok_or_else(|| malformed(exchange))?;
+
+    let mut candles = rows
+        .iter()
+        .map(|row| {
+            let row = row.as_array().ok_or_else(|| malformed(exchange))?;
+            let field = |i: usize| number(row, columns[i]).ok_or_else(|| malformed(exchange));
+            Ok(Candle {
+                time: field(0)? as i64 * time_scale,
+                open: field(1)?,
+                high: field(2)?,
+                low: field(3)?,
+                close: field(4)?,
+                volume: field(5)?,
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    if newest_first {
+        candles.reverse();
+    }
+    Ok(candles)
+}
+
+// [[openTime, "open", "high", "low", "close", "volume", ...], ...]
+pub fn parse_binance(body: &Value) -> AppResult<Vec<Candle>> {
+    parse_rows("Binance", Some(body), [0, 1, 2, 3, 4, 5], 1, false)
+}
+
+// {"retCode": 0, "result": {"list": [["start", "open", "high", "low", "close", "volume", ...]]}}
+pub fn parse_bybit(body: &Value) -> AppResult<Vec<Candle>> {
+    if let Some(code) = body["retCode"].as_i64().filter(|code| *code != 0) {
+        return Err(AppError::ServerRejected(format!(
+            "Bybit: {} ({})",
+            body["retMsg"].as_str().unwrap_or("error"),
+            code
+        )));
+    }
+    parse_rows(
+        "Bybit",
+        body.pointer("/result/list"),
+        [0, 1, 2, 3, 4, 5],
+        1,
+        true,
+    )
+}
+
+// {"code": "200000", "data": [["time secs", "open", "close", "high", "low", "volume", "turnover"]]}
+pub fn parse_kucoin(body: &Value) -> AppResult<Vec<Candle>> {
+    if let Some(code) = body["code"].as_str().filter(|code| *code != "200000") {
+        return Err(AppError::ServerRejected(format!(
+            "KuCoin: {} ({})",
+            body["msg"].as_str().unwrap_or("error"),
+            code
+        )));
+    }
+    parse_rows("KuCoin", body.get("data"), [0, 1, 3, 4, 2, 5], 1_000, true)
+}
+
+// {"code": "0", "data": [["ts", "o", "h", "l", "c", "vol", ...]]}
+pub fn parse_okx(body: &Value) -> AppResult<Vec<Candle>> {
+    if let Some(code) = body["code"].as_str().filter(|code| *code != "0") {
+        return Err(AppError::ServerRejected(format!(
+            "OKX: {} ({})",
+            body["msg"].as_str().unwrap_or("error"),
+            code
+        )));
+    }
+    parse_rows("OKX", body.get("data"), [0, 1, 2, 3, 4, 5], 1, true)
+}
+
+struct CacheEntry {
+    fetched_at: Instant,
+    source: Exchange,
+    candles: Vec<Candle>,
+}
+
+// Recently fetched series, keyed by symbol, timeframe and limit
+#[derive(Default)]
+pub struct MarketDataCache(Mutex<HashMap<(String, Timeframe, u32), CacheEntry>>);
+
+impl MarketDataCache {
+    fn get(&self, key: &(String, Timeframe, u32)) -> Option<Vec<Candle>> {
+        let cache = self.0.lock().unwrap();
+        let entry = cache.get(key)?;
+        if entry.fetched_at.elapsed() >= key.1.cache_ttl() {
+            return None;
+        }
+        log::debug!(
+            "Candle cache hit for {:?} from {}",
+            key,
+            entry.source.name()
+        );
+        Some(entry.candles.clone())
+    }
+
+    fn insert(&self, key: (String, Timeframe, u32), source: Exchange, candles: Vec<Candle>) {
+        let mut cache = self.0.lock().unwrap();
+        cache.retain(|key, entry| entry.fetched_at.elapsed() < key.1.cache_ttl());
+        cache.insert(
+            key,
+            CacheEntry {
+                fetched_at: Instant::now(),
+                source,
+                candles,
+            },
+        );
+    }
+}
+
+async fn fetch_from(
+    client: reqwest::Client,
+    exchange: Exchange,
+    symbol: String,
+    timeframe: Timeframe,
+    limit: u32,
+) -> AppResult<Vec<Candle>> {
+    let request = async {
+        let response = client
+            .get(exchange.url(&symbol, timeframe, limit))
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(if status.is_server_error() {
+                AppError::ServerUnavailable(format!("{}: {}", exchange.name(), status))
+            } else {
+                AppError::ServerRejected(format!("{}: {}", exchange.name(), status))
+            });
+        }
+
+        let body: Value = response.json().await?;
+        exchange.parse(&body)
+    };
+
+    let mut candles = tokio::time::timeout(EXCHANGE_TIMEOUT, request)
+        .await
+        .map_err(|_| AppError::Network(format!("{}: timed out", exchange.name())))??;
+
+    if candles.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "{}: no candles for {}",
+            exchange.name(),
+            symbol
+        )));
+    }
+
+    // KuCoin answers with the whole range, keep the most recent `limit`
+    let excess = candles.len().saturating_sub(limit as usize);
+    candles.drain(..excess);
+    Ok(candles)
+}
+
+// Race all exchanges and return the first non-empty series
+pub async fn fetch_candles(
+    app: &tauri::AppHandle,
+    symbol: &str,
+    timeframe: Timeframe,
+    limit: u32,
+) -> AppResult<Vec<Candle>> {
+    let symbol = symbol.trim().to_uppercase();
+    if symbol.is_empty() || !symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid symbol: {}",
+            symbol
+        )));
+    }
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(AppError::InvalidInput(format!(
+            "Limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+
+    let key = (symbol.clone(), timeframe, limit);
+    let cache = app.state::<MarketDataCache>();
+    if let Some(candles) = cache.get(&key) {
+        return Ok(candles);
+    }
+
+    let client = app.state::<HttpClient>().general();
+    let mut race = JoinSet::new();
+    for exchange in racing(limit) {
+        let (client, symbol) = (client.clone(), symbol.clone());
+        race.spawn(async move {
+            let result = fetch_from(client, exchange, symbol, timeframe, limit).await;
+            (exchange, result)
+        });
+    }
+
+    let mut failures = Vec::new();
+    while let Some(joined) = race.join_next().await {
+        let (exchange, result) = match joined {
+            Ok(joined) => joined,
+            Err(e) => {
+                failures.push(e.to_string());
+                continue;
+            }
+        };
+
+        match result {
+            Ok(candles) => {
+                race.abort_all();
+                log::debug!(
+                    "Fetched {} {:?} candles for {} from {}",
+                    candles.len(),
+                    timeframe,
+                    symbol,
+                    exchange.name()
+                );
+                cache.insert(key, exchange, candles.clone());
+                return Ok(candles);
+            }
+            Err(e) => {
+                log::debug!("{} candles failed for {}: {}", exchange.name(), symbol, e);
+                failures.push(e.to_string());
+            }
+        }
+    }
+
+    log::error!("All exchanges failed for {}: {:?}", symbol, failures);
+    Err(AppError::ServerUnavailable(format!(
+        "No exchange returned candles for {}: {}",
+        symbol,
+        failures.join("; ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(exchange: &str) -> Value {
+        let path = format!(
+            "{}/tests/fixtures/candles/{}.json",
+            env!("CARGO_MANIFEST_DIR"),
+            exchange
+        );
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    // The three ETHUSDT 1h candles every fixture records, oldest first
+    fn expected() -> Vec<Candle> {
+        let candle = |time, open, high, low, close, volume| Candle {
+            time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+        };
+        vec![
+            candle(1_700_000_000_000, 2000.5, 2012.25, 1995.1, 2010.0, 150.5),
+            candle(1_700_003_600_000, 2010.0, 2020.0, 2001.75, 2003.4, 98.25),
+            candle(1_700_007_200_000, 2003.4, 2008.9, 1990.0, 1992.6, 120.0),
+        ]
+    }
+
+    #[test]
+    fn parses_binance() {
+        assert_eq!(parse_binance(&fixture("binance")).unwrap(), expected());
+    }
+
+    #[test]
+    fn parses_bybit_newest_first() {
+        assert_eq!(parse_bybit(&fixture("bybit")).unwrap(), expected());
+    }
+
+    #[test]
+    fn parses_kucoin_columns_and_seconds() {
+        // KuCoin sends open, close, high, low in seconds, newest first
+        assert_eq!(parse_kucoin(&fixture("kucoin")).unwrap(), expected());
+    }
+
+    #[test]
+    fn parses_okx_newest_first() {
+        assert_eq!(parse_okx(&fixture("okx")).unwrap(), expected());
+    }
+
+    #[test]
+    fn surfaces_exchange_errors() {
+        let bybit = serde_json::json!({"retCode": 10001, "retMsg": "Not supported symbols"});
+        assert!(matches!(
+            parse_bybit(&bybit),
+            Err(AppError::ServerRejected(_))
+        ));
+
+        let kucoin = serde_json::json!({"code": "400100", "msg": "This pair is not provided"});
+        assert!(matches!(
+            parse_kucoin(&kucoin),
+            Err(AppError::ServerRejected(_))
+        ));
+
+        let okx = serde_json::json!({"code": "51001", "msg": "Instrument ID does not exist"});
+        assert!(matches!(parse_okx(&okx), Err(AppError::ServerRejected(_))));
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        let short_row = serde_json::json!([[1_700_000_000_000_i64, "1", "2", "0.5"]]);
+        assert!(matches!(
+            parse_binance(&short_row),
+            Err(AppError::ServerUnavailable(_))
+        ));
+
+        let not_a_number =
+            serde_json::json!({"code": "0", "data": [["ts", "1", "2", "0.5", "1.5", "10"]]});
+        assert!(matches!(
+            parse_okx(&not_a_number),
+            Err(AppError::ServerUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn dashes_known_quote_assets() {
+        assert_eq!(dashed_symbol("BTCUSDT"), "BTC-USDT");
+        assert_eq!(dashed_symbol("ETHBTC"), "ETH-BTC");
+        assert_eq!(dashed_symbol("USDT"), "USDT");
+        assert_eq!(dashed_symbol("FOOBAR"), "FOOBAR");
+    }
+
+    #[test]
+    fn leaves_okx_out_above_its_limit() {
+        assert!(racing(OKX_MAX_LIMIT).eq(EXCHANGES));
+        assert!(!racing(OKX_MAX_LIMIT + 1).any(|e| e == Exchange::Okx));
+        assert_eq!(racing(MAX_LIMIT).count(), 3);
+    }
+}
@@ -0,0 +1,708 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Listener, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::backtest::TRADE_FEE_PERCENT;
+use crate::error::{AppError, AppResult};
+use crate::journal::{self, FillSide, JournalFill, JournalPosition};
+use crate::market_data::{self, Timeframe};
+use crate::price_stream::{PriceTick, TICK_EVENT};
+use crate::risk::exits::{self, Direction, ExitReason, ExitRules, Position};
+
+const PAPER_STORE: &str = "paper.json";
+const ACCOUNT_KEY: &str = "account";
+
+pub const POSITION_EVENT: &str = "paper://position";
+
+const DEFAULT_STARTING_BALANCE: f64 = 10_000.0;
+
+// Same cadence as the bot-service demo simulator
+const MONITOR_INTERVAL: Duration = Duration::from_secs(30);
+
+// Streamed prices older than this are refreshed from the REST candles
+const PRICE_MAX_AGE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionStatus {
+    Open,
+    Closed,
+}
+
+// Field names follow the `positions` table the dashboard already renders
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaperPosition {
+    pub id: String,
+    pub token_symbol: String,
+    #[serde(flatten)]
+    pub exits: Position,
+    // USDC margin committed, fees excluded
+    pub entry_amount: f64,
+    pub token_amount: f64,
+    pub fees: f64,
+    pub exit_price: Option<f64>,
+    pub profit_loss: Option<f64>,
+    pub profit_loss_percent: Option<f64>,
+    pub status: PositionStatus,
+    pub close_reason: Option<ExitReason>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub closed_at: Option<String>,
+}
+
+impl PaperPosition {
+    fn journal_entry(&self) -> JournalPosition {
+        JournalPosition {
+            id: self.id.clone(),
+            wallet_address: None,
+            chain_id: None,
+            token_symbol: self.token_symbol.clone(),
+            direction: self.exits.direction,
+            leverage: self.exits.leverage_multiplier,
+            entry_price: self.exits.entry_price,
+            entry_amount: self.entry_amount,
+            token_amount: Some(self.token_amount),
+            entry_tx_hash: None,
+            exit_price: self.exit_price,
+            exit_amount: self.profit_loss.map(|pnl| self.entry_amount + pnl),
+            exit_tx_hash: None,
+            profit_loss: self.profit_loss,
+            profit_loss_percent: self.profit_loss_percent,
+            status: match self.status {
+                PositionStatus::Open => "open",
+                PositionStatus::Closed => "closed",
+            }
+            .to_string(),
+            close_reason: self.close_reason.map(|reason| reason.as_str().to_string()),
+            paper: true,
+            origin: String::new(),
+            created_at: self.created_at.clone(),
+            updated_at: Some(self.updated_at.clone()),
+            closed_at: self.closed_at.clone(),
+            synced_at: None,
+        }
+    }
+
+    pub fn unrealized(&self, price: f64) -> f64 {
+        self.exits.pnl(price, self.token_amount)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PaperSettings {
+    pub slippage_percent: f64,
+    pub fee_percent: f64,
+    #[serde(flatten)]
+    pub exits: ExitRules,
+}
+
+impl Default for PaperSettings {
+    fn default() -> Self {
+        Self {
+            slippage_percent: 0.1,
+            fee_percent: TRADE_FEE_PERCENT,
+            exits: ExitRules::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaperAccount {
+    // Virtual USDC not committed to open positions
+    pub balance: f64,
+    pub starting_balance: f64,
+    pub settings: PaperSettings,
+    pub positions: Vec<PaperPosition>,
+}
+
+impl Default for PaperAccount {
+    fn default() -> Self {
+        Self {
+            balance: DEFAULT_STARTING_BALANCE,
+            starting_balance: DEFAULT_STARTING_BALANCE,
+            settings: PaperSettings::default(),
+            positions: Vec::new(),
+        }
+    }
+}
+
+// Order as sent from the webview. Unset rule fields use the chain's rules
+// when a chain is given, the account settings otherwise.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PaperOrder {
+    pub symbol: String,
+    pub direction: Direction,
+    // USDC margin
+    pub amount: f64,
+    #[serde(default)]
+    pub leverage: Option<f64>,
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    #[serde(default)]
+    pub take_profit_percent: Option<f64>,
+    #[serde(default)]
+    pub trailing_stop_percent: Option<f64>,
+    #[serde(default)]
+    pub profit_lock_percent: Option<f64>,
+    #[serde(default)]
+    pub trailing_increment: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PaperSummary {
+    pub balance: f64,
+    pub starting_balance: f64,
+    // Balance plus margin and unrealized pnl of open positions
+    pub equity: f64,
+    pub open_positions: usize,
+    pub realized_pnl: f64,
+}
+
+// Serializes account updates and caches the account and the latest
+// streamed prices
+#[derive(Default)]
+pub struct PaperState {
+    // Loaded from paper.json on first use, written back on every change
+    account: Mutex<Option<PaperAccount>>,
+    prices: Mutex<HashMap<String, (Instant, f64)>>,
+}
+
+fn load(app: &tauri::AppHandle) -> AppResult<PaperAccount> {
+    let store = app.store(PAPER_STORE)?;
+    match store.get(ACCOUNT_KEY) {
+        Some(value) => Ok(serde_json::from_value(value.clone())?),
+        None => Ok(PaperAccount::default()),
+    }
+}
+
+fn save(app: &tauri::AppHandle, account: &PaperAccount) -> AppResult<()> {
+    let store = app.store(PAPER_STORE)?;
+    store.set(ACCOUNT_KEY, serde_json::to_value(account)?);
+    store.save()?;
+    Ok(())
+}
+
+// Run `f` on the cached account while holding the account lock
+fn read<T>(app: &tauri::AppHandle, f: impl FnOnce(&PaperAccount) -> T) -> AppResult<T> {
+    let state = app.state::<PaperState>();
+    let mut cached = state.account.lock().unwrap();
+    let account = match &mut *cached {
+        Some(account) => account,
+        None => cached.insert(load(app)?),
+    };
+    Ok(f(account))
+}
+
+fn snapshot(app: &tauri::AppHandle) -> AppResult<PaperAccount> {
+    read(app, PaperAccount::clone)
+}
+
+// Modify a copy of the account while holding the account lock. `f` reports
+// whether anything changed, only then is it saved.
+fn modify<T>(
+    app: &tauri::AppHandle,
+    f: impl FnOnce(&mut PaperAccount) -> AppResult<(T, bool)>,
+) -> AppResult<T> {
+    let state = app.state::<PaperState>();
+    let mut cached = state.account.lock().unwrap();
+    let mut account = match &*cached {
+        Some(account) => account.clone(),
+        None => load(app)?,
+    };
+    let (result, changed) = f(&mut account)?;
+    if changed {
+        save(app, &account)?;
+    }
+    *cached = Some(account);
+    Ok(result)
+}
+
+fn update<T>(
+    app: &tauri::AppHandle,
+    f: impl FnOnce(&mut PaperAccount) -> AppResult<T>,
+) -> AppResult<T> {
+    modify(app, |account| Ok((f(account)?, true)))
+}
+
+fn emit_position(app: &tauri::AppHandle, position: &PaperPosition) {
+    if let Err(e) = app.emit(POSITION_EVENT, position) {
+        log::warn!("Failed to emit paper position: {}", e);
+    }
+}
+
+// Mirror an open or close into the trade journal, best effort
+fn journal_fill(app: &tauri::AppHandle, position: &PaperPosition, fee: f64) {
+    let (side, price) = match position.exit_price {
+        Some(exit_price) => (FillSide::Close, exit_price),
+        None => (FillSide::Open, position.exits.entry_price),
+    };
+    let fill = JournalFill {
+        id: 0,
+        position_id: position.id.clone(),
+        side,
+        price,
+        amount: position.token_amount * price,
+        token_amount: Some(position.token_amount),
+        fee,
+        tx_hash: None,
+        created_at: Some(position.updated_at.clone()),
+    };
+    if let Err(e) = journal::record_trade(app, position.journal_entry(), Some(fill)) {
+        log::warn!("Failed to journal paper position {}: {}", position.id, e);
+    }
+}
+
+fn record_price(app: &tauri::AppHandle, symbol: &str, price: f64) {
+    app.state::<PaperState>()
+        .prices
+        .lock()
+        .unwrap()
+        .insert(symbol.to_string(), (Instant::now(), price));
+}
+
+// Latest streamed price, falling back to the last 1m candle close
+pub async fn current_price(app: &tauri::AppHandle, symbol: &str) -> AppResult<f64> {
+    let cached = app
+        .state::<PaperState>()
+        .prices
+        .lock()
+        .unwrap()
+        .get(symbol)
+        .filter(|(at, _)| at.elapsed() < PRICE_MAX_AGE)
+        .map(|(_, price)| *price);
+    if let Some(price) = cached {
+        return Ok(price);
+    }
+
+    let candles = market_data::fetch_candles(app, symbol, Timeframe::M1, 1).await?;
+    let price = candles
+        .last()
+        .map(|candle| candle.close)
+        .ok_or_else(|| AppError::NotFound(format!("price for {}", symbol)))?;
+    record_price(app, symbol, price);
+    Ok(price)
+}
+
+// Settle a position at `price` and credit margin plus pnl back. Returns the
+// closed position and the exit fee.
+fn settle(
+    account: &mut PaperAccount,
+    index: usize,
+    price: f64,
+    reason: ExitReason,
+) -> (PaperPosition, f64) {
+    let settings = account.settings.clone();
+    let position = &mut account.positions[index];
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let (exit_price, gross, exit_fee) = if reason == ExitReason::Liquidation {
+        (
+            position.exits.liquidation_price(),
+            -position.entry_amount,
+            0.0,
+        )
+    } else {
+        let exit_price = exits::slip(price, settings.slippage_percent, !position.exits.is_long());
+        let exit_fee = position.token_amount * exit_price * settings.fee_percent / 100.0;
+        (exit_price, position.unrealized(exit_price), exit_fee)
+    };
+
+    // A gap past liquidation cannot take more than the margin. The entry fee
+    // already left the balance when the position opened.
+    let returned = (position.entry_amount + gross - exit_fee).max(0.0);
+    let net = returned - position.entry_amount - position.fees;
+    position.fees += exit_fee;
+    position.exit_price = Some(exit_price);
+    position.profit_loss = Some(net);
+    position.profit_loss_percent = Some(net / position.entry_amount * 100.0);
+    position.status = PositionStatus::Closed;
+    position.close_reason = Some(reason);
+    position.updated_at = now.clone();
+    position.closed_at = Some(now);
+
+    account.balance += returned;
+    (position.clone(), exit_fee)
+}
+
+// Exit rules a position can be opened with
+fn check_rules(rules: &ExitRules) -> AppResult<()> {
+    let percents = [
+        rules.trailing_stop_percent,
+        rules.take_profit_percent,
+        rules.profit_lock_percent,
+        rules.trailing_increment,
+    ];
+    if percents.iter().any(|p| !p.is_finite()) {
+        return Err(AppError::InvalidInput(
+            "Exit rule percents must be numbers".to_string(),
+        ));
+    }
+    if !(rules.trailing_stop_percent > 0.0 && rules.trailing_stop_percent < 100.0) {
+        return Err(AppError::InvalidInput(
+            "Trailing stop must be between 0 and 100%".to_string(),
+        ));
+    }
+    if rules.take_profit_percent <= 0.0 {
+        return Err(AppError::InvalidInput(
+            "Take profit must be positive".to_string(),
+        ));
+    }
+    if rules.profit_lock_percent < 0.0 || rules.trailing_increment < 0.0 {
+        return Err(AppError::InvalidInput(
+            "Profit lock and trailing increment cannot be negative".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn open_position(app: &tauri::AppHandle, order: PaperOrder) -> AppResult<PaperPosition> {
+    let symbol = order.symbol.trim().to_uppercase();
+    let leverage = order.leverage.unwrap_or(1.0);
+    if order.amount <= 0.0 || !order.amount.is_finite() {
+        return Err(AppError::InvalidInput(
+            "Amount must be positive".to_string(),
+        ));
+    }
+    if !(1.0..=100.0).contains(&leverage) {
+        return Err(AppError::InvalidInput(
+            "Leverage must be between 1 and 100".to_string(),
+        ));
+    }
+
+    let market_price = current_price(app, &symbol).await?;
+
+    let position = update(app, |account| {
+        let settings = account.settings.clone();
+        let notional = order.amount * leverage;
+        let fee = notional * settings.fee_percent / 100.0;
+        if order.amount + fee > account.balance {
+            return Err(AppError::InvalidInput(format!(
+                "Insufficient paper balance: {:.2} USDC available",
+                account.balance
+            )));
+        }
+
+        let entry_price = exits::slip(
+            market_price,
+            settings.slippage_percent,
+            order.direction == Direction::Long,
+        );
+        let base = order.chain_id.map_or(settings.exits, ExitRules::for_chain);
+        let rules = ExitRules {
+            trailing_stop_percent: order
+                .trailing_stop_percent
+                .unwrap_or(base.trailing_stop_percent),
+            take_profit_percent: order
+                .take_profit_percent
+                .unwrap_or(base.take_profit_percent),
+            profit_lock_percent: order
+                .profit_lock_percent
+                .unwrap_or(base.profit_lock_percent),
+            trailing_increment: order.trailing_increment.unwrap_or(base.trailing_increment),
+        };
+        check_rules(&rules)?;
+        let now = chrono::Utc::now();
+
+        let position = PaperPosition {
+            id: format!(
+                "paper-{}-{:08x}",
+                now.timestamp_millis(),
+                rand::random::<u32>()
+            ),
+            token_symbol: symbol.clone(),
+            exits: Position::open(order.direction, entry_price, leverage, &rules),
+            entry_amount: order.amount,
+            token_amount: notional / entry_price,
+            fees: fee,
+            exit_price: None,
+            profit_loss: None,
+            profit_loss_percent: None,
+            status: PositionStatus::Open,
+            close_reason: None,
+            created_at: now.to_rfc3339(),
+            updated_at: now.to_rfc3339(),
+            closed_at: None,
+        };
+
+        account.balance -= order.amount + fee;
+        account.positions.push(position.clone());
+        Ok(position)
+    })?;
+
+    log::info!(
+        "Paper {:?} {} opened at {} ({} USDC x{})",
+        position.exits.direction,
+        position.token_symbol,
+        position.exits.entry_price,
+        position.entry_amount,
+        position.exits.leverage_multiplier
+    );
+    journal_fill(app, &position, position.fees);
+    emit_position(app, &position);
+    Ok(position)
+}
+
+pub async fn close_position(app: &tauri::AppHandle, id: &str) -> AppResult<PaperPosition> {
+    let symbol = snapshot(app)?
+        .positions
+        .iter()
+        .find(|p| p.id == id && p.status == PositionStatus::Open)
+        .map(|p| p.token_symbol.clone())
+        .ok_or_else(|| AppError::NotFound(format!("open paper position {}", id)))?;
+
+    let price = current_price(app, &symbol).await?;
+    let (position, exit_fee) = update(app, |account| {
+        let index = account
+            .positions
+            .iter()
+            .position(|p| p.id == id && p.status == PositionStatus::Open)
+            .ok_or_else(|| AppError::NotFound(format!("open paper position {}", id)))?;
+        Ok(settle(account, index, price, ExitReason::Manual))
+    })?;
+
+    journal_fill(app, &position, exit_fee);
+    emit_position(app, &position);
+    Ok(position)
+}
+
+// Run the exit rules for every open position on `symbol`
+pub fn on_price(app: &tauri::AppHandle, symbol: &str, price: f64) -> AppResult<()> {
+    record_price(app, symbol, price);
+
+    let watched = read(app, |account| {
+        account
+            .positions
+            .iter()
+            .any(|p| p.status == PositionStatus::Open && p.token_symbol == symbol)
+    })?;
+    if !watched {
+        return Ok(());
+    }
+
+    let (moved, closed) = modify(app, |account| {
+        let mut moved_stops = Vec::new();
+        let mut closed = Vec::new();
+        for index in 0..account.positions.len() {
+            let position = &mut account.positions[index];
+            if position.status != PositionStatus::Open || position.token_symbol != symbol {
+                continue;
+            }
+
+            let moved = position.exits.update_trailing_stop(price);
+            if moved {
+                position.updated_at = chrono::Utc::now().to_rfc3339();
+            }
+            match position.exits.should_close(price) {
+                Some(decision) => closed.push(settle(account, index, price, decision.reason)),
+                None if moved => moved_stops.push(position.clone()),
+                None => {}
+            }
+        }
+        let changed = !moved_stops.is_empty() || !closed.is_empty();
+        Ok(((moved_stops, closed), changed))
+    })?;
+
+    for position in &moved {
+        emit_position(app, position);
+    }
+    for (position, exit_fee) in &closed {
+        log::info!(
+            "Paper {} closed: {:?} ({:.2} USDC)",
+            position.token_symbol,
+            position.close_reason,
+            position.profit_loss.unwrap_or_default()
+        );
+        journal_fill(app, position, *exit_fee);
+        emit_position(app, position);
+    }
+    Ok(())
+}
+
+pub fn positions(app: &tauri::AppHandle, include_closed: bool) -> AppResult<Vec<PaperPosition>> {
+    Ok(snapshot(app)?
+        .positions
+        .into_iter()
+        .filter(|p| include_closed || p.status == PositionStatus::Open)
+        .collect())
+}
+
+pub async fn summary(app: &tauri::AppHandle) -> AppResult<PaperSummary> {
+    let account = snapshot(app)?;
+    let mut equity = account.balance;
+    for position in account
+        .positions
+        .iter()
+        .filter(|p| p.status == PositionStatus::Open)
+    {
+        let unrealized = match current_price(app, &position.token_symbol).await {
+            Ok(price) => position.unrealized(price),
+            Err(_) => 0.0,
+        };
+        equity += position.entry_amount + unrealized;
+    }
+
+    Ok(PaperSummary {
+        balance: account.balance,
+        starting_balance: account.starting_balance,
+        equity,
+        open_positions: account
+            .positions
+            .iter()
+            .filter(|p| p.status == PositionStatus::Open)
+            .count(),
+        realized_pnl: account.positions.iter().filter_map(|p| p.profit_loss).sum(),
+    })
+}
+
+pub fn settings(app: &tauri::AppHandle) -> AppResult<PaperSettings> {
+    Ok(snapshot(app)?.settings)
+}
+
+pub fn set_settings(app: &tauri::AppHandle, settings: PaperSettings) -> AppResult<()> {
+    if settings.slippage_percent < 0.0 || settings.fee_percent < 0.0 {
+        return Err(AppError::InvalidInput(
+            "Slippage and fees cannot be negative".to_string(),
+        ));
+    }
+    check_rules(&settings.exits)?;
+    update(app, |account| {
+        account.settings = settings;
+        Ok(())
+    })
+}
+
+// Start over with a fresh virtual balance, keeping the settings
+pub fn reset(app: &tauri::AppHandle, starting_balance: Option<f64>) -> AppResult<()> {
+    let starting_balance = starting_balance.unwrap_or(DEFAULT_STARTING_BALANCE);
+    if starting_balance <= 0.0 {
+        return Err(AppError::InvalidInput(
+            "Starting balance must be positive".to_string(),
+        ));
+    }
+    update(app, |account| {
+        *account = PaperAccount {
+            balance: starting_balance,
+            starting_balance,
+            settings: account.settings.clone(),
+            positions: Vec::new(),
+        };
+        Ok(())
+    })
+}
+
+// Follow streamed ticks, and poll prices for symbols nobody streams
+pub async fn run(app: tauri::AppHandle) {
+    let listener = app.clone();
+    app.listen_any(TICK_EVENT, move |event| {
+        if let Ok(tick) = serde_json::from_str::<PriceTick>(event.payload()) {
+            if let Err(e) = on_price(&listener, &tick.symbol, tick.price) {
+                log::warn!("Paper tick failed: {}", e);
+            }
+        }
+    });
+
+    loop {
+        tokio::time::sleep(MONITOR_INTERVAL).await;
+
+        let symbols: Vec<String> = match positions(&app, false) {
+            Ok(open) => open.into_iter().map(|p| p.token_symbol).collect(),
+            Err(e) => {
+                log::warn!("Paper monitor could not load positions: {}", e);
+                continue;
+            }
+        };
+
+        for symbol in symbols {
+            match current_price(&app, &symbol).await {
+                Ok(price) => {
+                    if let Err(e) = on_price(&app, &symbol, price) {
+                        log::warn!("Paper monitor failed for {}: {}", symbol, e);
+                    }
+                }
+                Err(e) => log::debug!("No price for {}: {}", symbol, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10x long of 100 USDC margin at 100, entry fee already paid
+    fn account() -> PaperAccount {
+        let settings = PaperSettings {
+            slippage_percent: 0.0,
+            fee_percent: 0.1,
+            exits: ExitRules::default(),
+        };
+        let position = PaperPosition {
+            id: "paper-1".to_string(),
+            token_symbol: "ETH".to_string(),
+            exits: Position::open(Direction::Long, 100.0, 10.0, &settings.exits),
+            entry_amount: 100.0,
+            token_amount: 10.0,
+            fees: 1.0,
+            exit_price: None,
+            profit_loss: None,
+            profit_loss_percent: None,
+            status: PositionStatus::Open,
+            close_reason: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+            closed_at: None,
+        };
+        PaperAccount {
+            balance: 899.0,
+            starting_balance: 1000.0,
+            settings,
+            positions: vec![position],
+        }
+    }
+
+    #[test]
+    fn settle_credits_margin_plus_pnl_less_fees() {
+        let mut account = account();
+        let (closed, exit_fee) = settle(&mut account, 0, 105.0, ExitReason::TakeProfit);
+        assert!((exit_fee - 1.05).abs() < 1e-9);
+        // 50 gain, 1.05 exit fee back with the margin
+        assert!((account.balance - (899.0 + 148.95)).abs() < 1e-9);
+        // Both fees count against the trade
+        assert!((closed.profit_loss.unwrap() - 47.95).abs() < 1e-9);
+        assert_eq!(closed.status, PositionStatus::Closed);
+    }
+
+    #[test]
+    fn settle_past_liquidation_loses_only_the_margin() {
+        let mut account = account();
+        // A 20% gap at 10x is twice the margin
+        let (closed, _) = settle(&mut account, 0, 80.0, ExitReason::TrailingStop);
+        assert_eq!(account.balance, 899.0);
+        // The books match the balance: margin plus entry fee, not 200
+        assert_eq!(closed.profit_loss, Some(-101.0));
+        assert_eq!(
+            account.balance - account.starting_balance,
+            closed.profit_loss.unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_unusable_exit_rules() {
+        let rules = |f: fn(&mut ExitRules)| {
+            let mut rules = ExitRules::default();
+            f(&mut rules);
+            check_rules(&rules)
+        };
+        assert!(rules(|_| {}).is_ok());
+        assert!(rules(|r| r.trailing_stop_percent = 0.0).is_err());
+        assert!(rules(|r| r.trailing_stop_percent = 100.0).is_err());
+        assert!(rules(|r| r.take_profit_percent = -5.0).is_err());
+        assert!(rules(|r| r.profit_lock_percent = -1.0).is_err());
+        assert!(rules(|r| r.trailing_increment = f64::NAN).is_err());
+        assert!(check_rules(&ExitRules::for_chain(42161)).is_ok());
+    }
+}
@@ -0,0 +1,799 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::error::{AppError, AppResult};
+use crate::http;
+use crate::market_data::{Candle, Timeframe};
+
+pub const TICK_EVENT: &str = "price://tick";
+pub const CANDLE_EVENT: &str = "price://candle";
+
+// Client-side keepalive, both exchanges drop idle sockets after a minute or so
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(20);
+
+// No frame at all for this long means the socket is dead
+const STALE_AFTER: Duration = Duration::from_secs(60);
+
+const RECONNECT_BASE_DELAY_MS: u64 = 1_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Feed {
+    Binance,
+    Bybit,
+}
+
+impl Feed {
+    fn url(self, symbol: &str, timeframe: Timeframe) -> String {
+        match self {
+            Feed::Binance => {
+                let symbol = symbol.to_lowercase();
+                format!(
+                    "wss://stream.binance.com:9443/stream?streams={}@kline_{}/{}@miniTicker",
+                    symbol,
+                    binance_interval(timeframe),
+                    symbol
+                )
+            }
+            Feed::Bybit => "wss://stream.bybit.com/v5/public/spot".to_string(),
+        }
+    }
+
+    // Sent once after connecting, Binance subscribes through the URL
+    fn subscribe_message(self, symbol: &str, timeframe: Timeframe) -> Option<String> {
+        match self {
+            Feed::Binance => None,
+            Feed::Bybit => Some(
+                json!({
+                    "op": "subscribe",
+                    "args": [
+                        format!("kline.{}.{}", bybit_interval(timeframe), symbol),
+                        format!("tickers.{}", symbol),
+                    ],
+                })
+                .to_string(),
+            ),
+        }
+    }
+
+    fn heartbeat(self) -> Message {
+        match self {
+            Feed::Binance => Message::Ping(Vec::new().into()),
+            Feed::Bybit => Message::text(json!({ "op": "ping" }).to_string()),
+        }
+    }
+}
+
+fn binance_interval(timeframe: Timeframe) -> &'static str {
+    match timeframe {
+        Timeframe::M1 => "1m",
+        Timeframe::M5 => "5m",
+        Timeframe::M15 => "15m",
+        Timeframe::H1 => "1h",
+        Timeframe::H4 => "4h",
+        Timeframe::D1 => "1d",
+    }
+}
+
+fn bybit_interval(timeframe: Timeframe) -> &'static str {
+    match timeframe {
+        Timeframe::M1 => "1",
+        Timeframe::M5 => "5",
+        Timeframe::M15 => "15",
+        Timeframe::H1 => "60",
+        Timeframe::H4 => "240",
+        Timeframe::D1 => "D",
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PriceTick {
+    pub feed: Feed,
+    pub symbol: String,
+    pub price: f64,
+    // Exchange event time, unix milliseconds
+    pub time: i64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CandleUpdate {
+    pub feed: Feed,
+    pub symbol: String,
+    pub timeframe: Timeframe,
+    pub candle: Candle,
+    // False while the candle is still forming
+    pub closed: bool,
+}
+
+// What a single feed message carries
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedEvent {
+    Tick { time: i64, price: f64 },
+    Kline { candle: Candle, closed: bool },
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn binance_kline(k: &Value) -> Option<FeedEvent> {
+    Some(FeedEvent::Kline {
+        candle: Candle {
+            time: integer(&k["t"])?,
+            open: number(&k["o"])?,
+            high: number(&k["h"])?,
+            low: number(&k["l"])?,
+            close: number(&k["c"])?,
+            volume: number(&k["v"])?,
+        },
+        closed: k["x"].as_bool().unwrap_or(false),
+    })
+}
+
+fn binance_tick(data: &Value) -> Option<FeedEvent> {
+    Some(FeedEvent::Tick {
+        time: integer(&data["E"])?,
+        price: number(&data["c"])?,
+    })
+}
+
+// {"stream": "...", "data": {"e": "kline" | "24hrMiniTicker", ...}}
+fn parse_binance(message: &Value) -> Vec<FeedEvent> {
+    let data = message.get("data").unwrap_or(message);
+
+    let event = match data["e"].as_str() {
+        Some("kline") => binance_kline(&data["k"]),
+        Some("24hrMiniTicker") | Some("24hrTicker") => binance_tick(data),
+        _ => None,
+    };
+    event.into_iter().collect()
+}
+
+// {"topic": "kline.1.BTCUSDT" | "tickers.BTCUSDT", "ts": ..., "data": ...}
+fn parse_bybit(message: &Value) -> Vec<FeedEvent> {
+    let topic = message["topic"].as_str().unwrap_or_default();
+
+    if topic.starts_with("kline.") {
+        let rows = message["data"].as_array().cloned().unwrap_or_default();
+        return rows
+            .iter()
+            .filter_map(|k| {
+                Some(FeedEvent::Kline {
+                    candle: Candle {
+                        time: integer(&k["start"])?,
+                        open: number(&k["open"])?,
+                        high: number(&k["high"])?,
+                        low: number(&k["low"])?,
+                        close: number(&k["close"])?,
+                        volume: number(&k["volume"])?,
+                    },
+                    closed: k["confirm"].as_bool().unwrap_or(false),
+                })
+            })
+            .collect();
+    }
+
+    if topic.starts_with("tickers.") {
+        let price = number(&message["data"]["lastPrice"]);
+        let time = integer(&message["ts"]).unwrap_or_default();
+        return price
+            .map(|price| FeedEvent::Tick { time, price })
+            .into_iter()
+            .collect();
+    }
+
+    // Subscription acks and pongs
+    Vec::new()
+}
+
+pub fn parse_message(feed: Feed, text: &str) -> Vec<FeedEvent> {
+    let Ok(message) = serde_json::from_str::<Value>(text) else {
+        return Vec::new();
+    };
+    match feed {
+        Feed::Binance => parse_binance(&message),
+        Feed::Bybit => parse_bybit(&message),
+    }
+}
+
+// Folds ticks and exchange klines into the forming candle. Klines are
+// authoritative, ticks only move close/high/low until the next one arrives.
+#[derive(Debug, Clone)]
+pub struct CandleAggregator {
+    timeframe: Timeframe,
+    current: Option<Candle>,
+    // The exchange already reported `current` as closed
+    finished: bool,
+    // Open time of the last candle handed out as closed
+    last_closed: Option<i64>,
+}
+
+impl CandleAggregator {
+    pub fn new(timeframe: Timeframe) -> Self {
+        Self {
+            timeframe,
+            current: None,
+            finished: false,
+            last_closed: None,
+        }
+    }
+
+    fn bucket(&self, time: i64) -> i64 {
+        let width = self.timeframe.seconds() * 1_000;
+        time - time.rem_euclid(width)
+    }
+
+    // The candle still open, if any
+    pub fn forming(&self) -> Option<Candle> {
+        self.current.filter(|_| !self.finished)
+    }
+
+    // Returns the previous candle when the tick opens a new bucket and the
+    // exchange has not closed it already
+    pub fn on_tick(&mut self, time: i64, price: f64) -> Option<Candle> {
+        let bucket = self.bucket(time);
+        match self.current.as_mut() {
+            // Late tick for a bucket that is already closed or behind us
+            Some(candle) if candle.time > bucket || (candle.time == bucket && self.finished) => {
+                None
+            }
+            Some(candle) if candle.time == bucket => {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                None
+            }
+            _ => {
+                let closed = self.current.filter(|_| !self.finished);
+                self.current = Some(Candle {
+                    time: bucket,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: 0.0,
+                });
+                self.finished = false;
+                if let Some(closed) = closed {
+                    self.last_closed = Some(closed.time);
+                }
+                closed
+            }
+        }
+    }
+
+    // Returns whether the kline is news: a stale kline, a repeat of the
+    // current state or a second close for an open time is not
+    pub fn on_kline(&mut self, candle: Candle, closed: bool) -> bool {
+        if self
+            .current
+            .is_some_and(|current| current.time > candle.time)
+        {
+            return false;
+        }
+        if self.last_closed.is_some_and(|time| time >= candle.time) {
+            return false;
+        }
+        if self.current == Some(candle) && self.finished == closed {
+            return false;
+        }
+        self.current = Some(candle);
+        self.finished = closed;
+        if closed {
+            self.last_closed = Some(candle.time);
+        }
+        true
+    }
+
+    // Candle updates for `event`, each open time is closed at most once
+    pub fn updates(&mut self, event: &FeedEvent) -> Vec<(Candle, bool)> {
+        let mut updates = Vec::new();
+        match *event {
+            FeedEvent::Tick { time, price } => {
+                if let Some(closed) = self.on_tick(time, price) {
+                    updates.push((closed, true));
+                }
+                // Keep the forming candle moving between exchange klines
+                if let Some(forming) = self.forming() {
+                    updates.push((forming, false));
+                }
+            }
+            FeedEvent::Kline { candle, closed } => {
+                if self.on_kline(candle, closed) {
+                    updates.push((candle, closed));
+                }
+            }
+        }
+        updates
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+pub struct StreamInfo {
+    pub feed: Feed,
+    pub symbol: String,
+    pub timeframe: Timeframe,
+}
+
+// Running streams and their tasks
+#[derive(Default)]
+pub struct PriceStreams(Mutex<HashMap<StreamInfo, tauri::async_runtime::JoinHandle<()>>>);
+
+// Socket settings of one subscription, the timings are fields so tests can
+// shrink them
+#[derive(Debug, Clone)]
+struct Session {
+    feed: Feed,
+    symbol: String,
+    timeframe: Timeframe,
+    url: String,
+    heartbeat: Duration,
+    stale_after: Duration,
+    reconnect_base_ms: u64,
+}
+
+impl Session {
+    fn new(feed: Feed, symbol: String, timeframe: Timeframe, url: String) -> Self {
+        Self {
+            feed,
+            symbol,
+            timeframe,
+            url,
+            heartbeat: HEARTBEAT_INTERVAL,
+            stale_after: STALE_AFTER,
+            reconnect_base_ms: RECONNECT_BASE_DELAY_MS,
+        }
+    }
+
+    // One connection, until it fails or goes stale. Ok(true) means data flowed.
+    async fn connect_once(&self, on_event: &mut impl FnMut(FeedEvent)) -> AppResult<bool> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .map_err(|e| AppError::Network(e.to_string()))?;
+        let (mut sink, mut source) = socket.split();
+        let send_error =
+            |e: tokio_tungstenite::tungstenite::Error| AppError::Network(e.to_string());
+
+        if let Some(subscribe) = self.feed.subscribe_message(&self.symbol, self.timeframe) {
+            sink.send(Message::text(subscribe))
+                .await
+                .map_err(send_error)?;
+        }
+        log::info!("Price stream connected: {}", self.url);
+
+        let mut received = false;
+        // Any frame counts, heartbeat replies included
+        let mut last_frame = Instant::now();
+        let mut heartbeat = tokio::time::interval(self.heartbeat);
+        heartbeat.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = heartbeat.tick() => {
+                    // A half-open socket never errors, it just goes quiet
+                    if last_frame.elapsed() > self.stale_after {
+                        return Err(AppError::Network("price stream went stale".to_string()));
+                    }
+                    sink.send(self.feed.heartbeat()).await.map_err(send_error)?;
+                }
+                frame = source.next() => {
+                    let message = match frame {
+                        None => return Ok(received),
+                        Some(message) => message.map_err(send_error)?,
+                    };
+                    last_frame = Instant::now();
+
+                    match message {
+                        Message::Text(text) => {
+                            for event in parse_message(self.feed, text.as_str()) {
+                                received = true;
+                                on_event(event);
+                            }
+                        }
+                        Message::Ping(payload) => {
+                            sink.send(Message::Pong(payload)).await.map_err(send_error)?;
+                        }
+                        Message::Close(_) => return Ok(received),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    // Reconnect forever with backoff, reset once a connection delivered data
+    async fn run(&self, mut on_event: impl FnMut(FeedEvent)) {
+        let mut attempt = 0;
+        loop {
+            match self.connect_once(&mut on_event).await {
+                Ok(true) => attempt = 0,
+                Ok(false) => attempt += 1,
+                Err(e) => {
+                    log::warn!("Price stream {} failed: {}", self.url, e);
+                    attempt += 1;
+                }
+            }
+
+            let delay = http::backoff_delay(self.reconnect_base_ms, attempt);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+struct Stream {
+    app: tauri::AppHandle,
+    session: Session,
+    aggregator: CandleAggregator,
+}
+
+impl Stream {
+    fn emit_candle(&self, candle: Candle, closed: bool) {
+        let update = CandleUpdate {
+            feed: self.session.feed,
+            symbol: self.session.symbol.clone(),
+            timeframe: self.session.timeframe,
+            candle,
+            closed,
+        };
+        if let Err(e) = self.app.emit(CANDLE_EVENT, update) {
+            log::warn!("Failed to emit candle: {}", e);
+        }
+    }
+
+    fn handle(&mut self, event: FeedEvent) {
+        if let FeedEvent::Tick { time, price } = event {
+            let tick = PriceTick {
+                feed: self.session.feed,
+                symbol: self.session.symbol.clone(),
+                price,
+                time,
+            };
+            if let Err(e) = self.app.emit(TICK_EVENT, tick) {
+                log::warn!("Failed to emit tick: {}", e);
+            }
+        }
+        for (candle, closed) in self.aggregator.updates(&event) {
+            self.emit_candle(candle, closed);
+        }
+    }
+
+    async fn run(mut self) {
+        let session = self.session.clone();
+        session.run(|event| self.handle(event)).await
+    }
+}
+
+// Start streaming `symbol`, replacing an existing stream for the same key.
+// Debug builds may point `url` at a local mock server.
+pub fn subscribe(
+    app: &tauri::AppHandle,
+    feed: Feed,
+    symbol: &str,
+    timeframe: Timeframe,
+    url: Option<String>,
+) -> AppResult<StreamInfo> {
+    let symbol = symbol.trim().to_uppercase();
+    if symbol.is_empty() || !symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid symbol: {}",
+            symbol
+        )));
+    }
+
+    let url = url
+        .filter(|_| cfg!(debug_assertions))
+        .unwrap_or_else(|| feed.url(&symbol, timeframe));
+    let stream = Stream {
+        app: app.clone(),
+        session: Session::new(feed, symbol.clone(), timeframe, url),
+        aggregator: CandleAggregator::new(timeframe),
+    };
+
+    let info = StreamInfo {
+        feed,
+        symbol,
+        timeframe,
+    };
+    let handle = tauri::async_runtime::spawn(stream.run());
+    let state = app.state::<PriceStreams>();
+    if let Some(previous) = state.0.lock().unwrap().insert(info.clone(), handle) {
+        previous.abort();
+    }
+
+    Ok(info)
+}
+
+pub fn unsubscribe(app: &tauri::AppHandle, feed: Feed, symbol: &str, timeframe: Timeframe) {
+    let info = StreamInfo {
+        feed,
+        symbol: symbol.trim().to_uppercase(),
+        timeframe,
+    };
+    if let Some(handle) = app.state::<PriceStreams>().0.lock().unwrap().remove(&info) {
+        handle.abort();
+    }
+}
+
+pub fn list(app: &tauri::AppHandle) -> Vec<StreamInfo> {
+    app.state::<PriceStreams>()
+        .0
+        .lock()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect()
+}
+
+pub fn unsubscribe_all(app: &tauri::AppHandle) {
+    for (_, handle) in app.state::<PriceStreams>().0.lock().unwrap().drain() {
+        handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    const MINI_TICKER: &str = r#"{"stream":"ethusdt@miniTicker","data":{"e":"24hrMiniTicker","E":1700000000123,"s":"ETHUSDT","c":"2001.50","o":"1990.00","h":"2010.00","l":"1985.00","v":"1000","q":"2000000"}}"#;
+
+    fn session(url: String) -> Session {
+        Session {
+            heartbeat: Duration::from_millis(50),
+            stale_after: Duration::from_millis(200),
+            reconnect_base_ms: 10,
+            ..Session::new(Feed::Binance, "ETHUSDT".into(), Timeframe::M1, url)
+        }
+    }
+
+    // Local WebSocket server; `serve` gets every accepted socket and its
+    // 1-based connection number
+    async fn mock_server<F, Fut>(serve: F) -> (String, Arc<AtomicUsize>)
+    where
+        F: Fn(tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>, usize) -> Fut
+            + Send
+            + Sync
+            + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                let socket = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                tokio::spawn(serve(socket, n));
+            }
+        });
+        (url, accepted)
+    }
+
+    // Runs the session until `count` events arrived
+    async fn collect(session: Session, count: usize) -> Vec<FeedEvent> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            session
+                .run(move |event| {
+                    let _ = tx.send(event);
+                })
+                .await
+        });
+        let mut events = Vec::new();
+        while events.len() < count {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .expect("no event from the mock server")
+                .unwrap();
+            events.push(event);
+        }
+        task.abort();
+        events
+    }
+
+    #[test]
+    fn parses_binance_messages() {
+        assert_eq!(
+            parse_message(Feed::Binance, MINI_TICKER),
+            vec![FeedEvent::Tick {
+                time: 1700000000123,
+                price: 2001.5
+            }]
+        );
+
+        let kline = r#"{"stream":"ethusdt@kline_1m","data":{"e":"kline","E":1700000000500,"s":"ETHUSDT","k":{"t":1699999980000,"T":1700000039999,"i":"1m","o":"2000.0","c":"2001.5","h":"2002.0","l":"1999.0","v":"12.5","x":true}}}"#;
+        assert_eq!(
+            parse_message(Feed::Binance, kline),
+            vec![FeedEvent::Kline {
+                candle: Candle {
+                    time: 1699999980000,
+                    open: 2000.0,
+                    high: 2002.0,
+                    low: 1999.0,
+                    close: 2001.5,
+                    volume: 12.5,
+                },
+                closed: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_bybit_messages() {
+        let kline = r#"{"topic":"kline.1.ETHUSDT","type":"snapshot","ts":1700000000500,"data":[{"start":1699999980000,"end":1700000039999,"interval":"1","open":"2000","close":"2001.5","high":"2002","low":"1999","volume":"12.5","turnover":"25000","confirm":false,"timestamp":1700000000500}]}"#;
+        assert_eq!(
+            parse_message(Feed::Bybit, kline),
+            vec![FeedEvent::Kline {
+                candle: Candle {
+                    time: 1699999980000,
+                    open: 2000.0,
+                    high: 2002.0,
+                    low: 1999.0,
+                    close: 2001.5,
+                    volume: 12.5,
+                },
+                closed: false,
+            }]
+        );
+
+        let ticker = r#"{"topic":"tickers.ETHUSDT","ts":1700000000123,"type":"snapshot","data":{"symbol":"ETHUSDT","lastPrice":"2001.5"}}"#;
+        assert_eq!(
+            parse_message(Feed::Bybit, ticker),
+            vec![FeedEvent::Tick {
+                time: 1700000000123,
+                price: 2001.5
+            }]
+        );
+
+        let ack = r#"{"success":true,"ret_msg":"subscribe","op":"subscribe"}"#;
+        assert!(parse_message(Feed::Bybit, ack).is_empty());
+        assert!(parse_message(Feed::Bybit, "not json").is_empty());
+    }
+
+    #[tokio::test]
+    async fn delivers_events_from_the_socket() {
+        let (url, _) = mock_server(|mut socket, _| async move {
+            socket.send(Message::text(MINI_TICKER)).await.unwrap();
+            // Keep the socket open and answer heartbeats
+            while socket.next().await.is_some() {}
+        })
+        .await;
+
+        let events = collect(session(url), 1).await;
+        assert_eq!(
+            events,
+            vec![FeedEvent::Tick {
+                time: 1700000000123,
+                price: 2001.5
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_the_server_closes() {
+        let (url, accepted) = mock_server(|mut socket, _| async move {
+            socket.send(Message::text(MINI_TICKER)).await.unwrap();
+            socket.close(None).await.unwrap();
+        })
+        .await;
+
+        let events = collect(session(url), 2).await;
+        assert_eq!(events.len(), 2);
+        assert!(accepted.load(Ordering::SeqCst) >= 2);
+    }
+
+    #[tokio::test]
+    async fn replaces_a_silent_socket() {
+        // First connection accepts and then never reads or writes, like a
+        // half-open socket. The second one delivers.
+        let (url, accepted) = mock_server(|mut socket, n| async move {
+            if n == 1 {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                drop(socket);
+                return;
+            }
+            socket.send(Message::text(MINI_TICKER)).await.unwrap();
+            while socket.next().await.is_some() {}
+        })
+        .await;
+
+        let events = collect(session(url), 1).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    }
+
+    fn binance_kline(open_time: i64, close: &str, closed: bool) -> String {
+        format!(
+            r#"{{"stream":"ethusdt@kline_1m","data":{{"e":"kline","E":{},"s":"ETHUSDT","k":{{"t":{},"T":{},"i":"1m","o":"2000.0","c":"{}","h":"2002.0","l":"1999.0","v":"12.5","x":{}}}}}}}"#,
+            open_time + 30_000,
+            open_time,
+            open_time + 59_999,
+            close,
+            closed
+        )
+    }
+
+    #[tokio::test]
+    async fn closes_a_candle_once_when_a_tick_rolls_over_first() {
+        let (url, _) = mock_server(|mut socket, _| async move {
+            let tick = r#"{"stream":"ethusdt@miniTicker","data":{"e":"24hrMiniTicker","E":1700000040100,"s":"ETHUSDT","c":"2003.00","o":"1990.00","h":"2010.00","l":"1985.00","v":"1000","q":"2000000"}}"#;
+            for message in [
+                binance_kline(1699999980000, "2001.5", false),
+                tick.to_string(),
+                // The exchange's own close, with a different last price
+                binance_kline(1699999980000, "2002.0", true),
+                binance_kline(1699999980000, "2002.0", true),
+            ] {
+                socket.send(Message::text(message)).await.unwrap();
+            }
+            while socket.next().await.is_some() {}
+        })
+        .await;
+
+        let mut aggregator = CandleAggregator::new(Timeframe::M1);
+        let updates: Vec<(Candle, bool)> = collect(session(url), 4)
+            .await
+            .iter()
+            .flat_map(|event| aggregator.updates(event))
+            .collect();
+
+        let forming = Candle {
+            time: 1699999980000,
+            open: 2000.0,
+            high: 2002.0,
+            low: 1999.0,
+            close: 2001.5,
+            volume: 12.5,
+        };
+        let next = Candle {
+            time: 1700000040000,
+            open: 2003.0,
+            high: 2003.0,
+            low: 2003.0,
+            close: 2003.0,
+            volume: 0.0,
+        };
+        assert_eq!(
+            updates,
+            vec![(forming, false), (forming, true), (next, false)]
+        );
+    }
+
+    #[tokio::test]
+    async fn heartbeat_replies_keep_the_socket() {
+        let (url, accepted) = mock_server(|mut socket, _| async move {
+            // Reading answers the client pings with pongs
+            while socket.next().await.is_some() {}
+        })
+        .await;
+
+        let session = session(url);
+        let stale_after = session.stale_after;
+        let task = tokio::spawn(async move { session.run(|_| {}).await });
+        tokio::time::sleep(stale_after * 4).await;
+        task.abort();
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,482 @@
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::chain::abi::{IGmxVault, IVault, IERC20};
+use crate::chain::{self, ChainSettings, LegacyVault, RpcClient, USDC_DECIMALS};
+use crate::error::{AppError, AppResult};
+use crate::tx::{self, TxRecord};
+use crate::vaults;
+use crate::wallet;
+
+// What the recovery scripts did by hand, each one a transaction the user
+// can sign
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryKind {
+    // Credits the collateral of a position GMX no longer holds. Anyone may
+    // send it.
+    Reconcile,
+    // Pro-rata share of an underfunded vault
+    EmergencyWithdraw,
+    // Full balance out of a legacy vault
+    Withdraw,
+    // Refunds a legacy position GMX never executed, V8 and later
+    CancelStuckPosition,
+    // Sells a V3 or V5 spot position back to USDC
+    EmergencyClose,
+}
+
+impl RecoveryKind {
+    // Whether the USDC leaves the vault, the other steps only credit the
+    // vault balance for a withdraw to pay out
+    pub fn pays_out(self) -> bool {
+        matches!(
+            self,
+            RecoveryKind::EmergencyWithdraw | RecoveryKind::Withdraw
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecoveryStep {
+    pub kind: RecoveryKind,
+    pub vault: Address,
+    pub vault_label: String,
+    // Position token, reconcile only
+    pub token: Option<Address>,
+    pub user: Address,
+    // Raw USDC units, sent as-is for legacy withdraws
+    pub amount: U256,
+    // Expected to come back, in USDC. Paid out or credited to the vault
+    // balance, see RecoveryKind::pays_out.
+    pub amount_usdc: f64,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PositionCheck {
+    pub token_symbol: String,
+    pub token: Address,
+    pub is_long: bool,
+    pub collateral: f64,
+    // Size of the vault's GMX position, in USD
+    pub gmx_size: f64,
+    // Active in the vault but gone on GMX
+    pub orphaned: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultDiagnosis {
+    pub label: String,
+    pub vault: Address,
+    pub current: bool,
+    pub balance: f64,
+    // None on vaults without getWithdrawable
+    pub withdrawable: Option<f64>,
+    // USDC the contract actually holds
+    pub contract_usdc: f64,
+    pub total_value_locked: Option<f64>,
+    pub solvent: bool,
+    pub positions: Vec<PositionCheck>,
+    pub issues: Vec<String>,
+}
+
+// A known vault diagnose could not look at
+#[derive(Debug, Serialize)]
+pub struct UncheckedVault {
+    pub label: String,
+    pub vault: Address,
+    pub chain_id: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryReport {
+    pub user: Address,
+    pub vaults: Vec<VaultDiagnosis>,
+    pub not_checked: Vec<UncheckedVault>,
+    pub steps: Vec<RecoveryStep>,
+    // USDC the steps pay out to the wallet
+    pub total_recoverable: f64,
+}
+
+fn usdc(amount: U256) -> f64 {
+    chain::units(amount, USDC_DECIMALS)
+}
+
+// check_user_stuck.ts for the current vault: balance against what the
+// contract holds, and every active position against GMX
+async fn diagnose_current(
+    rpc: &RpcClient<'_>,
+    user: Address,
+    steps: &mut Vec<RecoveryStep>,
+) -> AppResult<VaultDiagnosis> {
+    let settings = rpc.settings();
+    let vault = settings.vault_address;
+    let label = "Current".to_string();
+
+    let balance = rpc.call(vault, IVault::balancesCall { user }).await?;
+    let withdrawable = rpc
+        .call(vault, IVault::getWithdrawableCall { user })
+        .await?;
+    let contract_usdc = rpc
+        .call(
+            settings.usdc_address,
+            IERC20::balanceOfCall { owner: vault },
+        )
+        .await?;
+    let status = rpc.call(vault, IVault::getHealthStatusCall {}).await?;
+
+    let mut issues = Vec::new();
+    if !status.isSolvent {
+        issues.push(format!(
+            "Vault holds {:.2} USDC against {:.2} USDC owed",
+            usdc(contract_usdc),
+            usdc(status.totalValueLocked)
+        ));
+    }
+
+    let mut positions = Vec::new();
+    for token in &settings.tokens {
+        let position = rpc
+            .call(
+                vault,
+                IVault::getPositionCall {
+                    user,
+                    token: token.address,
+                },
+            )
+            .await?;
+        if !position.isActive {
+            continue;
+        }
+
+        // Same lookup reconcile() makes, the vault trades with USDC collateral
+        let gmx = rpc
+            .call(
+                settings.gmx_vault_address,
+                IGmxVault::getPositionCall {
+                    account: vault,
+                    collateralToken: settings.usdc_address,
+                    indexToken: token.address,
+                    isLong: position.isLong,
+                },
+            )
+            .await?;
+        let orphaned = gmx.size.is_zero();
+        if orphaned {
+            issues.push(format!(
+                "{} position is closed on GMX but still holds {:.2} USDC collateral",
+                token.symbol,
+                usdc(position.collateral)
+            ));
+            steps.push(RecoveryStep {
+                kind: RecoveryKind::Reconcile,
+                vault,
+                vault_label: label.clone(),
+                token: Some(token.address),
+                user,
+                amount: position.collateral,
+                amount_usdc: usdc(position.collateral),
+                description: format!(
+                    "Reconcile the {} position to credit its collateral back",
+                    token.symbol
+                ),
+            });
+        }
+        positions.push(PositionCheck {
+            token_symbol: token.symbol.clone(),
+            token: token.address,
+            is_long: position.isLong,
+            collateral: usdc(position.collateral),
+            gmx_size: chain::units(gmx.size, chain::PRICE_DECIMALS),
+            orphaned,
+        });
+    }
+
+    // A plain withdraw reverts once the vault is short, emergencyWithdraw
+    // pays the pro-rata share and leaves the rest credited
+    if withdrawable < balance {
+        issues.push(format!(
+            "Only {:.2} of {:.2} USDC can be withdrawn",
+            usdc(withdrawable),
+            usdc(balance)
+        ));
+        if !withdrawable.is_zero() {
+            steps.push(RecoveryStep {
+                kind: RecoveryKind::EmergencyWithdraw,
+                vault,
+                vault_label: label.clone(),
+                token: None,
+                user,
+                amount: withdrawable,
+                amount_usdc: usdc(withdrawable),
+                description: "Emergency withdraw the pro-rata share, the remainder stays credited"
+                    .into(),
+            });
+        }
+    }
+
+    Ok(VaultDiagnosis {
+        label,
+        vault,
+        current: true,
+        balance: usdc(balance),
+        withdrawable: Some(usdc(withdrawable)),
+        contract_usdc: usdc(contract_usdc),
+        total_value_locked: Some(usdc(status.totalValueLocked)),
+        solvent: status.isSolvent,
+        positions,
+        issues,
+    })
+}
+
+// recover_v8_funds.ts and LegacyVaultWithdraw.tsx: any balance or open
+// position left in an older vault
+async fn diagnose_legacy(
+    rpc: &RpcClient<'_>,
+    user: Address,
+    legacy: &LegacyVault,
+    steps: &mut Vec<RecoveryStep>,
+) -> AppResult<Option<VaultDiagnosis>> {
+    let vault = legacy.address;
+    let client = vaults::client(legacy.version, vault);
+    let tokens: Vec<Address> = rpc.settings().tokens.iter().map(|t| t.address).collect();
+
+    let balance = client.balance(rpc, user).await?;
+    let open = client.open_positions(rpc, user, &tokens).await?;
+    if balance.is_zero() && open.is_empty() {
+        return Ok(None);
+    }
+    let withdrawable = client.withdrawable(rpc, user).await?;
+    let contract_usdc = rpc
+        .call(
+            rpc.settings().usdc_address,
+            IERC20::balanceOfCall { owner: vault },
+        )
+        .await?;
+
+    let mut issues = Vec::new();
+    if !balance.is_zero() {
+        issues.push(format!("{:.2} USDC left in a retired vault", usdc(balance)));
+    }
+    for token in open {
+        let symbol = token_symbol(rpc, token);
+        issues.push(format!("{} position still open", symbol));
+        let (kind, description) = if client.cancel_stuck_position(user, token).is_some() {
+            (
+                RecoveryKind::CancelStuckPosition,
+                format!(
+                    "Cancel the stuck {} position, its collateral goes back to the {} balance",
+                    symbol, legacy.label
+                ),
+            )
+        } else if client.emergency_close(token).is_some() {
+            (
+                RecoveryKind::EmergencyClose,
+                format!(
+                    "Sell the {} held in the {} vault back to USDC",
+                    symbol, legacy.label
+                ),
+            )
+        } else {
+            continue;
+        };
+        // The amount lands in the vault balance, a withdraw follows
+        steps.push(RecoveryStep {
+            kind,
+            vault,
+            vault_label: legacy.label.clone(),
+            token: Some(token),
+            user,
+            amount: U256::ZERO,
+            amount_usdc: 0.0,
+            description,
+        });
+    }
+
+    // withdraw() takes an exact amount, ask for what is there
+    let amount = withdrawable.min(contract_usdc);
+    if !balance.is_zero() && amount.is_zero() {
+        issues.push("The vault holds no USDC, nothing can be withdrawn".into());
+    } else if !amount.is_zero() {
+        if amount < balance {
+            issues.push(format!(
+                "Only {:.2} of {:.2} USDC can be withdrawn",
+                usdc(amount),
+                usdc(balance)
+            ));
+        }
+        steps.push(RecoveryStep {
+            kind: RecoveryKind::Withdraw,
+            vault,
+            vault_label: legacy.label.clone(),
+            token: None,
+            user,
+            amount,
+            amount_usdc: usdc(amount),
+            description: format!(
+                "Withdraw {:.2} USDC from the {} vault",
+                usdc(amount),
+                legacy.label
+            ),
+        });
+    }
+
+    Ok(Some(VaultDiagnosis {
+        label: legacy.label.clone(),
+        vault,
+        current: false,
+        balance: usdc(balance),
+        withdrawable: Some(usdc(withdrawable)),
+        contract_usdc: usdc(contract_usdc),
+        total_value_locked: None,
+        solvent: contract_usdc >= balance,
+        positions: Vec::new(),
+        issues,
+    }))
+}
+
+fn token_symbol(rpc: &RpcClient<'_>, token: Address) -> String {
+    rpc.settings()
+        .tokens
+        .iter()
+        .find(|t| t.address == token)
+        .map_or_else(|| token.to_string(), |t| t.symbol.clone())
+}
+
+// Everything that can leave a user's USDC stuck, with the transactions
+// that get it back. Legacy vaults without a balance are left out.
+pub async fn diagnose(app: &tauri::AppHandle, user: &str) -> AppResult<RecoveryReport> {
+    let user = chain::parse_address(user)?;
+    let rpc = RpcClient::new(app);
+    let mut steps = Vec::new();
+
+    let settings = rpc.settings();
+    let mut vaults = vec![diagnose_current(&rpc, user, &mut steps).await?];
+    let mut not_checked = Vec::new();
+    for legacy in &settings.legacy_vaults {
+        if legacy.address == settings.vault_address {
+            continue;
+        }
+        // One unreachable or odd vault should not hide the others
+        match diagnose_legacy(&rpc, user, legacy, &mut steps).await {
+            Ok(Some(diagnosis)) => vaults.push(diagnosis),
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!(
+                    "Cannot check {} vault {}: {}",
+                    legacy.label,
+                    legacy.address,
+                    e
+                );
+                not_checked.push(UncheckedVault {
+                    label: legacy.label.clone(),
+                    vault: legacy.address,
+                    chain_id: settings.chain_id,
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    // The RPC endpoints serve one chain, the Base vaults need their own
+    not_checked.extend(
+        vaults::DEPLOYMENTS
+            .iter()
+            .filter(|d| d.chain_id != settings.chain_id)
+            .map(|d| UncheckedVault {
+                label: d.label.to_string(),
+                vault: d.address,
+                chain_id: d.chain_id,
+                reason: format!(
+                    "On chain {}, the RPC endpoints serve chain {}",
+                    d.chain_id, settings.chain_id
+                ),
+            }),
+    );
+
+    Ok(RecoveryReport {
+        user,
+        vaults,
+        not_checked,
+        total_recoverable: total_recoverable(&steps),
+        steps,
+    })
+}
+
+// Reconcile and the legacy refunds only move USDC into the vault balance,
+// counting them next to the withdraw that pays it out would count it twice
+fn total_recoverable(steps: &[RecoveryStep]) -> f64 {
+    steps
+        .iter()
+        .filter(|step| step.kind.pays_out())
+        .map(|step| step.amount_usdc)
+        .sum()
+}
+
+// Sign and send one step of the report with the unlocked wallet
+pub async fn execute(app: &tauri::AppHandle, step: &RecoveryStep) -> AppResult<TxRecord> {
+    // Withdraws pay msg.sender, only reconcile works on someone else's behalf
+    if step.kind != RecoveryKind::Reconcile && wallet::unlocked_address(app) != Some(step.user) {
+        return Err(AppError::InvalidInput(format!(
+            "Unlock {} to recover its funds",
+            step.user
+        )));
+    }
+
+    let version = vaults::version_of(&ChainSettings::load(app), step.vault)?;
+    let client = vaults::client(version, step.vault);
+    let token = || {
+        step.token
+            .ok_or_else(|| AppError::InvalidInput("The step needs a token".into()))
+    };
+    let (action, data) = match step.kind {
+        RecoveryKind::Reconcile => ("reconcile", client.reconcile(step.user, token()?)),
+        RecoveryKind::EmergencyWithdraw => ("emergency_withdraw", client.emergency_withdraw()),
+        RecoveryKind::Withdraw => ("legacy_withdraw", Some(client.withdraw(step.amount))),
+        RecoveryKind::CancelStuckPosition => (
+            "cancel_stuck_position",
+            client.cancel_stuck_position(step.user, token()?),
+        ),
+        RecoveryKind::EmergencyClose => ("emergency_close", client.emergency_close(token()?)),
+    };
+    let data = data.ok_or_else(|| {
+        AppError::InvalidInput(format!("{:?} vaults have no {}", version, action))
+    })?;
+    tx::send_data(app, action, step.vault, data).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(kind: RecoveryKind, amount_usdc: f64) -> RecoveryStep {
+        RecoveryStep {
+            kind,
+            vault: chain::VAULT_ADDRESS,
+            vault_label: "Current".to_string(),
+            token: None,
+            user: Address::ZERO,
+            amount: U256::from((amount_usdc * 1e6) as u64),
+            amount_usdc,
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn counts_only_what_leaves_the_vaults() {
+        let steps = [
+            // Credits 40 to the balance the emergency withdraw then pays
+            step(RecoveryKind::Reconcile, 40.0),
+            step(RecoveryKind::EmergencyWithdraw, 75.0),
+            step(RecoveryKind::CancelStuckPosition, 0.0),
+            step(RecoveryKind::Withdraw, 12.5),
+        ];
+        assert_eq!(total_recoverable(&steps), 87.5);
+        assert_eq!(
+            total_recoverable(&[step(RecoveryKind::Reconcile, 40.0)]),
+            0.0
+        );
+    }
+}
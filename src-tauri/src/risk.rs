@@ -0,0 +1,2 @@
+// Position risk rules shared by the backtester and the paper simulator
+pub mod exits;
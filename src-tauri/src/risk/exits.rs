@@ -0,0 +1,385 @@
+use serde::{Deserialize, Serialize};
+
+// positions.ts defaults
+const DEFAULT_TRAILING_STOP_PERCENT: f64 = 1.0;
+const DEFAULT_TAKE_PROFIT_PERCENT: f64 = 5.0;
+const DEFAULT_PROFIT_THRESHOLD: f64 = 0.5;
+
+// V5 settings for Arbitrum, tighter trailing because fees are lower
+const V5_CHAIN_ID: u64 = 42161;
+const V5_PROFIT_THRESHOLD: f64 = 0.4;
+const V5_TRAILING_INCREMENT: f64 = 0.15;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Direction {
+    Long,
+    Short,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    TakeProfit,
+    TrailingStop,
+    Liquidation,
+    Manual,
+    EndOfData,
+}
+
+impl ExitReason {
+    // Same strings as `close_reason` in the positions table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExitReason::TakeProfit => "take_profit",
+            ExitReason::TrailingStop => "trailing_stop",
+            ExitReason::Liquidation => "liquidation",
+            ExitReason::Manual => "manual",
+            ExitReason::EndOfData => "end_of_data",
+        }
+    }
+}
+
+// Per-position rule parameters, fixed when the position opens
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct ExitRules {
+    pub trailing_stop_percent: f64,
+    pub take_profit_percent: f64,
+    // Profit needed before the stop exists at all
+    pub profit_lock_percent: f64,
+    // V5 increment trailing when above zero, standard trailing otherwise
+    pub trailing_increment: f64,
+}
+
+impl Default for ExitRules {
+    fn default() -> Self {
+        Self {
+            trailing_stop_percent: DEFAULT_TRAILING_STOP_PERCENT,
+            take_profit_percent: DEFAULT_TAKE_PROFIT_PERCENT,
+            profit_lock_percent: DEFAULT_PROFIT_THRESHOLD,
+            trailing_increment: 0.0,
+        }
+    }
+}
+
+impl ExitRules {
+    // PositionService.createPosition picks V5 rules for Arbitrum
+    pub fn for_chain(chain_id: u64) -> Self {
+        if chain_id == V5_CHAIN_ID {
+            Self {
+                profit_lock_percent: V5_PROFIT_THRESHOLD,
+                trailing_increment: V5_TRAILING_INCREMENT,
+                ..Self::default()
+            }
+        } else {
+            Self::default()
+        }
+    }
+}
+
+// Fill price after slippage, always against the trader
+pub fn slip(price: f64, slippage_percent: f64, buying: bool) -> f64 {
+    let slippage = slippage_percent / 100.0;
+    if buying {
+        price * (1.0 + slippage)
+    } else {
+        price * (1.0 - slippage)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub struct CloseDecision {
+    pub reason: ExitReason,
+    // Price level that triggered the exit
+    pub level: f64,
+}
+
+// The exit-rule columns of a `positions` row, same names as positions.ts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Position {
+    pub direction: Direction,
+    pub entry_price: f64,
+    pub highest_price: f64,
+    pub lowest_price: f64,
+    pub trailing_stop_price: Option<f64>,
+    pub trailing_stop_percent: f64,
+    pub trailing_increment: f64,
+    pub take_profit_price: Option<f64>,
+    pub take_profit_percent: f64,
+    pub profit_lock_percent: f64,
+    // Only true once the position has been in profit
+    pub stop_activated: bool,
+    pub leverage_multiplier: f64,
+}
+
+impl Position {
+    pub fn open(direction: Direction, entry_price: f64, leverage: f64, rules: &ExitRules) -> Self {
+        let take_profit = rules.take_profit_percent / 100.0;
+        Self {
+            direction,
+            entry_price,
+            highest_price: entry_price,
+            lowest_price: entry_price,
+            trailing_stop_price: None,
+            trailing_stop_percent: rules.trailing_stop_percent,
+            trailing_increment: rules.trailing_increment,
+            take_profit_price: Some(match direction {
+                Direction::Long => entry_price * (1.0 + take_profit),
+                Direction::Short => entry_price * (1.0 - take_profit),
+            }),
+            take_profit_percent: rules.take_profit_percent,
+            profit_lock_percent: rules.profit_lock_percent,
+            stop_activated: false,
+            leverage_multiplier: leverage,
+        }
+    }
+
+    pub fn is_long(&self) -> bool {
+        self.direction == Direction::Long
+    }
+
+    pub fn profit_percent(&self, price: f64) -> f64 {
+        let change = (price - self.entry_price) / self.entry_price * 100.0;
+        if self.is_long() {
+            change
+        } else {
+            -change
+        }
+    }
+
+    // USDC gain on `quantity` tokens closed at `price`, fees excluded
+    pub fn pnl(&self, price: f64, quantity: f64) -> f64 {
+        let change = (price - self.entry_price) * quantity;
+        if self.is_long() {
+            change
+        } else {
+            -change
+        }
+    }
+
+    // Where the margin is gone. Unleveraged longs end up at zero, and so
+    // does a zero or negative leverage.
+    pub fn liquidation_price(&self) -> f64 {
+        let leverage = if self.leverage_multiplier > 0.0 {
+            self.leverage_multiplier
+        } else {
+            1.0
+        };
+        let buffer = 1.0 / leverage;
+        if self.is_long() {
+            self.entry_price * (1.0 - buffer)
+        } else {
+            self.entry_price * (1.0 + buffer)
+        }
+    }
+
+    // PositionService.updateTrailingStop: the stop only exists once the
+    // position is in profit by the lock threshold, never sits past
+    // break-even and only ever moves in the position's favour. Returns
+    // whether anything changed.
+    pub fn update_trailing_stop(&mut self, price: f64) -> bool {
+        let profit = self.profit_percent(price);
+        let in_profit = profit >= self.profit_lock_percent;
+        if !in_profit && !self.stop_activated {
+            return false;
+        }
+
+        let new_stop = if self.trailing_increment > 0.0 {
+            let earned = (profit / self.trailing_increment).floor();
+            let locked = (earned - 1.0).max(0.0) * self.trailing_increment;
+            if self.is_long() {
+                self.entry_price * (1.0 + locked / 100.0)
+            } else {
+                self.entry_price * (1.0 - locked / 100.0)
+            }
+        } else if self.is_long() {
+            (price * (1.0 - self.trailing_stop_percent / 100.0)).max(self.entry_price)
+        } else {
+            (price * (1.0 + self.trailing_stop_percent / 100.0)).min(self.entry_price)
+        };
+
+        if !self.stop_activated {
+            self.stop_activated = true;
+            self.trailing_stop_price = Some(new_stop);
+            if self.is_long() {
+                self.highest_price = price;
+            } else {
+                self.lowest_price = price;
+            }
+            return true;
+        }
+
+        let new_extreme = if self.is_long() {
+            price > self.highest_price
+        } else {
+            price < self.lowest_price
+        };
+        if !new_extreme {
+            return false;
+        }
+        if self.is_long() {
+            self.highest_price = price;
+        } else {
+            self.lowest_price = price;
+        }
+        self.trailing_stop_price = Some(match (self.direction, self.trailing_stop_price) {
+            (Direction::Long, Some(stop)) => new_stop.max(stop),
+            (Direction::Short, Some(stop)) => new_stop.min(stop),
+            (_, None) => new_stop,
+        });
+        true
+    }
+
+    // PositionService.shouldClose, with liquidation checked first
+    pub fn should_close(&self, price: f64) -> Option<CloseDecision> {
+        let decision = |reason, level| Some(CloseDecision { reason, level });
+
+        let liquidation = self.liquidation_price();
+        if (self.is_long() && price <= liquidation) || (!self.is_long() && price >= liquidation) {
+            return decision(ExitReason::Liquidation, liquidation);
+        }
+
+        if let Some(take_profit) = self.take_profit_price {
+            if (self.is_long() && price >= take_profit) || (!self.is_long() && price <= take_profit)
+            {
+                return decision(ExitReason::TakeProfit, take_profit);
+            }
+        }
+
+        // Not activated means hold through dips and spikes. A triggered stop
+        // past break-even is ignored, same as the TS service.
+        let stop = self.trailing_stop_price.filter(|_| self.stop_activated)?;
+        let (hit, at_profit) = if self.is_long() {
+            (price <= stop, stop >= self.entry_price)
+        } else {
+            (price >= stop, stop <= self.entry_price)
+        };
+        if hit && at_profit {
+            return decision(ExitReason::TrailingStop, stop);
+        }
+        None
+    }
+
+    // Feed prices in order until one closes the position. Returns the index
+    // of that price and the decision.
+    pub fn evaluate(
+        &mut self,
+        prices: impl IntoIterator<Item = f64>,
+    ) -> Option<(usize, CloseDecision)> {
+        prices.into_iter().enumerate().find_map(|(index, price)| {
+            self.update_trailing_stop(price);
+            self.should_close(price).map(|decision| (index, decision))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn direction() -> impl Strategy<Value = Direction> {
+        prop_oneof![Just(Direction::Long), Just(Direction::Short)]
+    }
+
+    // Standard trailing or the V5 increment rules
+    fn rules() -> impl Strategy<Value = ExitRules> {
+        prop_oneof![
+            Just(ExitRules::default()),
+            Just(ExitRules::for_chain(V5_CHAIN_ID))
+        ]
+    }
+
+    // Entry price and a random walk of up to 3% per step
+    fn path() -> impl Strategy<Value = (f64, Vec<f64>)> {
+        (
+            1.0..10_000.0f64,
+            prop::collection::vec(-3.0..3.0f64, 1..300),
+        )
+            .prop_map(|(entry, steps)| {
+                let mut price = entry;
+                let prices = steps
+                    .into_iter()
+                    .map(|step| {
+                        price *= 1.0 + step / 100.0;
+                        price
+                    })
+                    .collect();
+                (entry, prices)
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn stop_never_moves_against_the_position(
+            direction in direction(),
+            rules in rules(),
+            (entry, prices) in path(),
+        ) {
+            let mut position = Position::open(direction, entry, 1.0, &rules);
+            let mut previous: Option<f64> = None;
+            for price in prices {
+                position.update_trailing_stop(price);
+                let stop = position.trailing_stop_price;
+                if let (Some(before), Some(after)) = (previous, stop) {
+                    match direction {
+                        Direction::Long => prop_assert!(after >= before),
+                        Direction::Short => prop_assert!(after <= before),
+                    }
+                }
+                // Once a stop exists it never goes away
+                prop_assert!(previous.is_none() || stop.is_some());
+                previous = stop;
+            }
+        }
+
+        #[test]
+        fn active_stop_never_falls_behind_entry(
+            direction in direction(),
+            rules in rules(),
+            (entry, prices) in path(),
+        ) {
+            let mut position = Position::open(direction, entry, 1.0, &rules);
+            for price in prices {
+                position.update_trailing_stop(price);
+                if !position.stop_activated {
+                    prop_assert!(position.trailing_stop_price.is_none());
+                    continue;
+                }
+                let stop = position.trailing_stop_price.unwrap();
+                match direction {
+                    Direction::Long => prop_assert!(stop >= entry),
+                    Direction::Short => prop_assert!(stop <= entry),
+                }
+            }
+        }
+
+        #[test]
+        fn trailing_exits_lock_in_profit(
+            direction in direction(),
+            rules in rules(),
+            (entry, prices) in path(),
+        ) {
+            let mut position = Position::open(direction, entry, 2.0, &rules);
+            if let Some((_, decision)) = position.evaluate(prices) {
+                if decision.reason == ExitReason::TrailingStop {
+                    prop_assert!(position.profit_percent(decision.level) >= 0.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn liquidation_price_survives_bad_leverage() {
+        for leverage in [0.0, -3.0, f64::NAN] {
+            let long = Position::open(Direction::Long, 100.0, leverage, &ExitRules::default());
+            assert_eq!(long.liquidation_price(), 0.0);
+            let short = Position::open(Direction::Short, 100.0, leverage, &ExitRules::default());
+            assert_eq!(short.liquidation_price(), 200.0);
+        }
+
+        let long = Position::open(Direction::Long, 100.0, 10.0, &ExitRules::default());
+        assert!((long.liquidation_price() - 90.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,266 @@
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::Manager;
+use tauri_plugin_store::StoreExt;
+
+use crate::error::{AppError, AppResult};
+use crate::machine_id::MachineFingerprint;
+
+const SECRETS_STORE: &str = "secrets.json";
+const HEADER_KEY: &str = "header";
+const ENTRY_PREFIX: &str = "entry:";
+
+// Entries under this prefix belong to the backend and are not reachable
+// through the secret_* commands
+pub const INTERNAL_PREFIX: &str = "internal.";
+
+// AAD for the wrapped data key, entries use their own name
+const DATA_KEY_AAD: &str = "monadier-secrets-data-key-v1";
+
+// Key wrapping parameters, stored in plaintext next to the entries
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Header {
+    salt: String,
+    passphrase_protected: bool,
+    wrapped_key: Sealed,
+}
+
+// Nonce and ciphertext, both base64
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+// Unwrapped data key, held in memory while unlocked
+#[derive(Default)]
+pub struct SecretsState(Mutex<Option<[u8; 32]>>);
+
+// Whether the store exists, needs a passphrase, and is unlocked
+#[derive(Debug, Serialize)]
+pub struct SecretsStatus {
+    pub initialized: bool,
+    pub passphrase_protected: bool,
+    pub unlocked: bool,
+}
+
+fn corrupt() -> AppError {
+    AppError::StoreCorrupted("secrets store".to_string())
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+// Key-encryption key from the machine identity plus an optional passphrase
+fn derive_kek(salt: &[u8], passphrase: Option<&str>) -> AppResult<[u8; 32]> {
+    let mut material = MachineFingerprint::current().key_material()?;
+    if let Some(passphrase) = passphrase {
+        material.push('\n');
+        material.push_str(passphrase);
+    }
+
+    let mut kek = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(material.as_bytes(), salt, &mut kek)
+        .map_err(|e| AppError::Crypto(format!("key derivation failed: {}", e)))?;
+    Ok(kek)
+}
+
+fn seal(key: &[u8; 32], plaintext: &[u8], aad: &str) -> AppResult<Sealed> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = random_bytes::<12>();
+
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: aad.as_bytes(),
+            },
+        )
+        .map_err(|_| AppError::Crypto("encryption failed".to_string()))?;
+
+    Ok(Sealed {
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn open(key: &[u8; 32], sealed: &Sealed, aad: &str) -> AppResult<Vec<u8>> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = BASE64.decode(&sealed.nonce).map_err(|_| corrupt())?;
+    let ciphertext = BASE64.decode(&sealed.ciphertext).map_err(|_| corrupt())?;
+    if nonce.len() != 12 {
+        return Err(corrupt());
+    }
+
+    cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: aad.as_bytes(),
+            },
+        )
+        .map_err(|_| AppError::Crypto("secret could not be decrypted".to_string()))
+}
+
+fn load_header(app: &tauri::AppHandle) -> AppResult<Option<Header>> {
+    let store = app.store(SECRETS_STORE)?;
+
+    match store.get(HEADER_KEY) {
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(AppError::from),
+        None => Ok(None),
+    }
+}
+
+// Wrap the data key under a freshly salted KEK and persist the header
+fn write_header(
+    app: &tauri::AppHandle,
+    data_key: &[u8; 32],
+    passphrase: Option<&str>,
+) -> AppResult<()> {
+    let salt = random_bytes::<16>();
+    let kek = derive_kek(&salt, passphrase)?;
+    let header = Header {
+        salt: BASE64.encode(salt),
+        passphrase_protected: passphrase.is_some(),
+        wrapped_key: seal(&kek, data_key, DATA_KEY_AAD)?,
+    };
+
+    let store = app.store(SECRETS_STORE)?;
+    store.set(HEADER_KEY, serde_json::to_value(&header)?);
+    store.save().map_err(AppError::from)
+}
+
+fn unwrap_data_key(header: &Header, passphrase: Option<&str>) -> AppResult<[u8; 32]> {
+    let salt = BASE64.decode(&header.salt).map_err(|_| corrupt())?;
+    let kek = derive_kek(&salt, passphrase)?;
+
+    open(&kek, &header.wrapped_key, DATA_KEY_AAD)
+        .map_err(|_| AppError::WrongPassphrase)?
+        .try_into()
+        .map_err(|_| corrupt())
+}
+
+// Unlock with the given passphrase, creating the store on first use
+pub fn unlock(app: &tauri::AppHandle, passphrase: Option<&str>) -> AppResult<()> {
+    let data_key = match load_header(app)? {
+        Some(header) => unwrap_data_key(&header, passphrase)?,
+        None => {
+            let data_key = random_bytes::<32>();
+            write_header(app, &data_key, passphrase)?;
+            data_key
+        }
+    };
+
+    let state = app.state::<SecretsState>();
+    *state.0.lock().unwrap() = Some(data_key);
+    Ok(())
+}
+
+// Only a passphrase keeps the store locked, without one the next read
+// unlocks it again with the machine key
+pub fn lock(app: &tauri::AppHandle) -> AppResult<()> {
+    if !load_header(app)?.is_some_and(|h| h.passphrase_protected) {
+        return Err(AppError::InvalidInput(
+            "Set a passphrase before locking the secrets store".to_string(),
+        ));
+    }
+    let state = app.state::<SecretsState>();
+    *state.0.lock().unwrap() = None;
+    Ok(())
+}
+
+// Data key, unlocking with the machine key alone when no passphrase is set
+fn data_key(app: &tauri::AppHandle) -> AppResult<[u8; 32]> {
+    if let Some(key) = *app.state::<SecretsState>().0.lock().unwrap() {
+        return Ok(key);
+    }
+
+    if let Some(header) = load_header(app)? {
+        if header.passphrase_protected {
+            return Err(AppError::SecretsLocked);
+        }
+    }
+
+    unlock(app, None)?;
+    app.state::<SecretsState>()
+        .0
+        .lock()
+        .unwrap()
+        .ok_or(AppError::SecretsLocked)
+}
+
+pub fn status(app: &tauri::AppHandle) -> AppResult<SecretsStatus> {
+    let header = load_header(app)?;
+    Ok(SecretsStatus {
+        initialized: header.is_some(),
+        passphrase_protected: header.map(|h| h.passphrase_protected).unwrap_or(false),
+        unlocked: app.state::<SecretsState>().0.lock().unwrap().is_some(),
+    })
+}
+
+// Re-wrap the data key, entries stay as they are
+pub fn change_passphrase(app: &tauri::AppHandle, passphrase: Option<&str>) -> AppResult<()> {
+    let data_key = data_key(app)?;
+    write_header(app, &data_key, passphrase)
+}
+
+pub fn get(app: &tauri::AppHandle, name: &str) -> AppResult<Option<String>> {
+    let store = app.store(SECRETS_STORE)?;
+
+    let sealed: Sealed = match store.get(format!("{}{}", ENTRY_PREFIX, name)) {
+        Some(value) => serde_json::from_value(value.clone())?,
+        None => return Ok(None),
+    };
+
+    let plaintext = open(&data_key(app)?, &sealed, name)?;
+    String::from_utf8(plaintext)
+        .map(Some)
+        .map_err(|_| corrupt())
+}
+
+pub fn set(app: &tauri::AppHandle, name: &str, value: &str) -> AppResult<()> {
+    let sealed = seal(&data_key(app)?, value.as_bytes(), name)?;
+
+    let store = app.store(SECRETS_STORE)?;
+    store.set(
+        format!("{}{}", ENTRY_PREFIX, name),
+        serde_json::to_value(&sealed)?,
+    );
+    store.save().map_err(AppError::from)
+}
+
+pub fn delete(app: &tauri::AppHandle, name: &str) -> AppResult<()> {
+    let store = app.store(SECRETS_STORE)?;
+
+    let _ = store.delete(format!("{}{}", ENTRY_PREFIX, name));
+    store.save().map_err(AppError::from)
+}
+
+// Reject names the webview may not touch
+pub fn check_public_name(name: &str) -> AppResult<()> {
+    if name.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Secret name is required".to_string(),
+        ));
+    }
+    if name.starts_with(INTERNAL_PREFIX) {
+        return Err(AppError::InvalidInput(format!(
+            "Secret '{}' is reserved",
+            name
+        )));
+    }
+    Ok(())
+}
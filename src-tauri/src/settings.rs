@@ -0,0 +1,24 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri_plugin_store::StoreExt;
+
+use crate::error::AppResult;
+
+// One store for every module's preferences, each under its own key
+const SETTINGS_STORE: &str = "settings.json";
+
+// The stored value, or the default when it is missing or unreadable
+pub fn load<T: DeserializeOwned + Default>(app: &tauri::AppHandle, key: &str) -> T {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(key))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+pub fn save<T: Serialize + ?Sized>(app: &tauri::AppHandle, key: &str, value: &T) -> AppResult<()> {
+    let store = app.store(SETTINGS_STORE)?;
+    store.set(key, serde_json::to_value(value)?);
+    store.save()?;
+    Ok(())
+}
@@ -0,0 +1,834 @@
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+use crate::error::{AppError, AppResult};
+use crate::market_data::{self, Candle, Timeframe};
+
+// Candles fetched per timeframe
+const CANDLE_LIMIT: u32 = 100;
+
+// Fewer candles than this and a timeframe is reported as HOLD
+const MIN_CANDLES: usize = 30;
+
+pub const DEFAULT_TIMEFRAMES: [Timeframe; 4] =
+    [Timeframe::M1, Timeframe::M5, Timeframe::M15, Timeframe::H1];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SignalDirection {
+    Long,
+    Short,
+    Hold,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Trend {
+    Up,
+    Down,
+    Sideways,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Bias {
+    Bullish,
+    Bearish,
+    Neutral,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternType {
+    BullishEngulfing,
+    BearishEngulfing,
+    Hammer,
+    InvertedHammer,
+    Doji,
+    DragonflyDoji,
+    GravestoneDoji,
+    MorningStar,
+    EveningStar,
+    ThreeWhiteSoldiers,
+    ThreeBlackCrows,
+    BullishHarami,
+    BearishHarami,
+}
+
+impl PatternType {
+    // PATTERN_WEIGHTS
+    pub fn weight(self) -> f64 {
+        match self {
+            PatternType::BullishEngulfing | PatternType::BearishEngulfing => 0.8,
+            PatternType::Hammer => 0.7,
+            PatternType::InvertedHammer => 0.6,
+            PatternType::Doji => 0.3,
+            PatternType::DragonflyDoji | PatternType::GravestoneDoji => 0.5,
+            PatternType::MorningStar | PatternType::EveningStar => 0.9,
+            PatternType::ThreeWhiteSoldiers | PatternType::ThreeBlackCrows => 1.0,
+            PatternType::BullishHarami | PatternType::BearishHarami => 0.6,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PatternType::BullishEngulfing => "bullish_engulfing",
+            PatternType::BearishEngulfing => "bearish_engulfing",
+            PatternType::Hammer => "hammer",
+            PatternType::InvertedHammer => "inverted_hammer",
+            PatternType::Doji => "doji",
+            PatternType::DragonflyDoji => "dragonfly_doji",
+            PatternType::GravestoneDoji => "gravestone_doji",
+            PatternType::MorningStar => "morning_star",
+            PatternType::EveningStar => "evening_star",
+            PatternType::ThreeWhiteSoldiers => "three_white_soldiers",
+            PatternType::ThreeBlackCrows => "three_black_crows",
+            PatternType::BullishHarami => "bullish_harami",
+            PatternType::BearishHarami => "bearish_harami",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Pattern {
+    #[serde(rename = "type")]
+    pub kind: PatternType,
+    pub direction: Bias,
+    // 1 = weak, 2 = medium, 3 = strong
+    pub strength: u8,
+    pub candle_index: usize,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeframeAnalysis {
+    pub timeframe: Timeframe,
+    pub direction: SignalDirection,
+    pub confidence: f64,
+    pub trend: Trend,
+    pub rsi: f64,
+    pub macd_signal: Bias,
+    pub patterns: Vec<Pattern>,
+    pub support: f64,
+    pub resistance: f64,
+    pub current_price: f64,
+}
+
+// Same shape as UnifiedSignal in bot-service signalEngine.ts, which this
+// module ports. Keep the two engines in sync.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnifiedSignal {
+    pub symbol: String,
+    pub direction: SignalDirection,
+    pub confidence: f64,
+    pub timeframes: Vec<TimeframeAnalysis>,
+    // How many timeframes agree on the trend, 0-100
+    pub trend_alignment: f64,
+    pub pattern_strength: f64,
+    pub patterns: Vec<Pattern>,
+    pub suggested_entry: f64,
+    #[serde(rename = "suggestedTP")]
+    pub suggested_tp: f64,
+    #[serde(rename = "suggestedSL")]
+    pub suggested_sl: f64,
+    pub reasons: Vec<String>,
+    pub warnings: Vec<String>,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Macd {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+// TIMEFRAME_WEIGHTS as (trend, entry), higher timeframes carry the trend
+fn timeframe_weights(timeframe: Timeframe) -> (f64, f64) {
+    match timeframe {
+        Timeframe::M1 => (0.05, 0.30),
+        Timeframe::M5 => (0.10, 0.30),
+        Timeframe::M15 => (0.20, 0.25),
+        Timeframe::H1 => (0.35, 0.10),
+        Timeframe::H4 => (0.30, 0.05),
+        // Not in the TS engine, generate_signal rejects 1d. Backtests on daily
+        // candles combine a single timeframe, where the weight cancels out
+        Timeframe::D1 => (0.30, 0.05),
+    }
+}
+
+fn body(c: &Candle) -> f64 {
+    (c.close - c.open).abs()
+}
+
+fn range(c: &Candle) -> f64 {
+    c.high - c.low
+}
+
+fn lower_wick(c: &Candle) -> f64 {
+    c.open.min(c.close) - c.low
+}
+
+fn upper_wick(c: &Candle) -> f64 {
+    c.high - c.open.max(c.close)
+}
+
+fn is_bullish(c: &Candle) -> bool {
+    c.close > c.open
+}
+
+fn is_bearish(c: &Candle) -> bool {
+    c.close < c.open
+}
+
+fn is_bullish_engulfing(prev: &Candle, curr: &Candle) -> bool {
+    is_bearish(prev) && is_bullish(curr) && curr.open < prev.close && curr.close > prev.open
+}
+
+fn is_bearish_engulfing(prev: &Candle, curr: &Candle) -> bool {
+    is_bullish(prev) && is_bearish(curr) && curr.open > prev.close && curr.close < prev.open
+}
+
+fn is_hammer(c: &Candle) -> bool {
+    body(c) < range(c) * 0.3 && lower_wick(c) > body(c) * 2.0 && upper_wick(c) < body(c) * 0.5
+}
+
+fn is_inverted_hammer(c: &Candle) -> bool {
+    body(c) < range(c) * 0.3 && upper_wick(c) > body(c) * 2.0 && lower_wick(c) < body(c) * 0.5
+}
+
+fn is_doji(c: &Candle) -> bool {
+    body(c) < range(c) * 0.1
+}
+
+fn doji_type(c: &Candle) -> PatternType {
+    if lower_wick(c) > upper_wick(c) * 3.0 {
+        PatternType::DragonflyDoji
+    } else if upper_wick(c) > lower_wick(c) * 3.0 {
+        PatternType::GravestoneDoji
+    } else {
+        PatternType::Doji
+    }
+}
+
+fn is_morning_star(first: &Candle, second: &Candle, third: &Candle) -> bool {
+    is_bearish(first)
+        && body(second) < body(first) * 0.3
+        && is_bullish(third)
+        && third.close > (first.open + first.close) / 2.0
+}
+
+fn is_evening_star(first: &Candle, second: &Candle, third: &Candle) -> bool {
+    is_bullish(first)
+        && body(second) < body(first) * 0.3
+        && is_bearish(third)
+        && third.close < (first.open + first.close) / 2.0
+}
+
+fn is_bullish_harami(prev: &Candle, curr: &Candle) -> bool {
+    is_bearish(prev) && is_bullish(curr) && curr.open > prev.close && curr.close < prev.open
+}
+
+fn is_bearish_harami(prev: &Candle, curr: &Candle) -> bool {
+    is_bullish(prev) && is_bearish(curr) && curr.open < prev.close && curr.close > prev.open
+}
+
+fn is_three_white_soldiers(c: &[Candle]) -> bool {
+    c.len() == 3
+        && is_bullish(&c[0])
+        && c.windows(2)
+            .all(|w| is_bullish(&w[1]) && w[1].open > w[0].open && w[1].close > w[0].close)
+}
+
+fn is_three_black_crows(c: &[Candle]) -> bool {
+    c.len() == 3
+        && is_bearish(&c[0])
+        && c.windows(2)
+            .all(|w| is_bearish(&w[1]) && w[1].open < w[0].open && w[1].close < w[0].close)
+}
+
+// Engulfing strength grows with how much larger the second body is
+fn engulfing_strength(prev: &Candle, curr: &Candle) -> u8 {
+    if body(curr) > body(prev) * 1.5 {
+        3
+    } else if body(curr) > body(prev) * 1.2 {
+        2
+    } else {
+        1
+    }
+}
+
+pub fn detect_patterns(candles: &[Candle]) -> Vec<Pattern> {
+    let mut patterns = Vec::new();
+    if candles.len() < 3 {
+        return patterns;
+    }
+
+    let mut push = |kind, direction, strength, candle_index| {
+        patterns.push(Pattern {
+            kind,
+            direction,
+            strength,
+            candle_index,
+        })
+    };
+
+    for i in 2..candles.len() {
+        let (prev2, prev, curr) = (&candles[i - 2], &candles[i - 1], &candles[i]);
+
+        if is_bullish_engulfing(prev, curr) {
+            let strength = engulfing_strength(prev, curr);
+            push(PatternType::BullishEngulfing, Bias::Bullish, strength, i);
+        }
+        if is_bearish_engulfing(prev, curr) {
+            let strength = engulfing_strength(prev, curr);
+            push(PatternType::BearishEngulfing, Bias::Bearish, strength, i);
+        }
+        if is_hammer(curr) {
+            push(PatternType::Hammer, Bias::Bullish, 2, i);
+        }
+        if is_inverted_hammer(curr) {
+            push(PatternType::InvertedHammer, Bias::Bullish, 1, i);
+        }
+        if is_doji(curr) {
+            let kind = doji_type(curr);
+            let direction = if kind == PatternType::GravestoneDoji {
+                Bias::Bearish
+            } else {
+                Bias::Bullish
+            };
+            push(kind, direction, 1, i);
+        }
+        if is_morning_star(prev2, prev, curr) {
+            push(PatternType::MorningStar, Bias::Bullish, 3, i);
+        }
+        if is_evening_star(prev2, prev, curr) {
+            push(PatternType::EveningStar, Bias::Bearish, 3, i);
+        }
+        if is_bullish_harami(prev, curr) {
+            push(PatternType::BullishHarami, Bias::Bullish, 2, i);
+        }
+        if is_bearish_harami(prev, curr) {
+            push(PatternType::BearishHarami, Bias::Bearish, 2, i);
+        }
+    }
+
+    let last3 = &candles[candles.len() - 3..];
+    if is_three_white_soldiers(last3) {
+        push(
+            PatternType::ThreeWhiteSoldiers,
+            Bias::Bullish,
+            3,
+            candles.len() - 1,
+        );
+    }
+    if is_three_black_crows(last3) {
+        push(
+            PatternType::ThreeBlackCrows,
+            Bias::Bearish,
+            3,
+            candles.len() - 1,
+        );
+    }
+
+    patterns
+}
+
+// Simple-average RSI over the last `period` closes
+pub fn rsi(candles: &[Candle], period: usize) -> f64 {
+    if candles.len() < period + 1 {
+        return 50.0;
+    }
+
+    let (mut gains, mut losses) = (0.0, 0.0);
+    for i in candles.len() - period..candles.len() {
+        let change = candles[i].close - candles[i - 1].close;
+        if change > 0.0 {
+            gains += change;
+        } else {
+            losses += change.abs();
+        }
+    }
+
+    let avg_gain = gains / period as f64;
+    let avg_loss = losses / period as f64;
+    if avg_loss == 0.0 {
+        return 100.0;
+    }
+    100.0 - 100.0 / (1.0 + avg_gain / avg_loss)
+}
+
+// EMA seeded with the SMA of the first `period` values
+pub fn ema(values: &[f64], period: usize) -> f64 {
+    if values.len() < period {
+        return values.last().copied().unwrap_or(0.0);
+    }
+
+    let multiplier = 2.0 / (period as f64 + 1.0);
+    let seed = values[..period].iter().sum::<f64>() / period as f64;
+    values[period..]
+        .iter()
+        .fold(seed, |ema, value| (value - ema) * multiplier + ema)
+}
+
+// MACD(12, 26) with a 9-period signal line
+pub fn macd(candles: &[Candle]) -> Macd {
+    let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    let macd = ema(&closes, 12) - ema(&closes, 26);
+
+    let history: Vec<f64> = (25..closes.len())
+        .map(|i| ema(&closes[..=i], 12) - ema(&closes[..=i], 26))
+        .collect();
+    let signal = if history.len() >= 9 {
+        ema(&history, 9)
+    } else {
+        macd
+    };
+
+    Macd {
+        macd,
+        signal,
+        histogram: macd - signal,
+    }
+}
+
+fn average_close(candles: &[Candle]) -> f64 {
+    candles.iter().map(|c| c.close).sum::<f64>() / candles.len() as f64
+}
+
+// Price against SMA20 and SMA20 against SMA50
+pub fn trend(candles: &[Candle]) -> Trend {
+    if candles.len() < 20 {
+        return Trend::Sideways;
+    }
+
+    let sma20 = average_close(&candles[candles.len() - 20..]);
+    let sma50 = if candles.len() >= 50 {
+        average_close(&candles[candles.len() - 50..])
+    } else {
+        sma20
+    };
+    let price = candles[candles.len() - 1].close;
+
+    if price > sma20 && sma20 > sma50 {
+        Trend::Up
+    } else if price < sma20 && sma20 < sma50 {
+        Trend::Down
+    } else {
+        Trend::Sideways
+    }
+}
+
+// 10th percentile low and high of the last 20 candles, as (support, resistance)
+pub fn support_resistance(candles: &[Candle]) -> (f64, f64) {
+    let Some(last) = candles.last() else {
+        return (0.0, 0.0);
+    };
+    if candles.len() < 10 {
+        return (last.low, last.high);
+    }
+
+    let recent = &candles[candles.len().saturating_sub(20)..];
+    let mut lows: Vec<f64> = recent.iter().map(|c| c.low).collect();
+    let mut highs: Vec<f64> = recent.iter().map(|c| c.high).collect();
+    lows.sort_by(|a, b| a.total_cmp(b));
+    highs.sort_by(|a, b| b.total_cmp(a));
+
+    let index = lows.len() / 10;
+    (lows[index], highs[index])
+}
+
+pub fn analyze_timeframe(timeframe: Timeframe, candles: &[Candle]) -> TimeframeAnalysis {
+    let current_price = candles.last().map(|c| c.close).unwrap_or(0.0);
+
+    if candles.len() < MIN_CANDLES {
+        return TimeframeAnalysis {
+            timeframe,
+            direction: SignalDirection::Hold,
+            confidence: 0.0,
+            trend: Trend::Sideways,
+            rsi: 50.0,
+            macd_signal: Bias::Neutral,
+            patterns: Vec::new(),
+            support: 0.0,
+            resistance: 0.0,
+            current_price,
+        };
+    }
+
+    let rsi = rsi(candles, 14);
+    let macd = macd(candles);
+    let trend = trend(candles);
+    let (support, resistance) = support_resistance(candles);
+    let patterns = detect_patterns(&candles[candles.len() - 10..]);
+
+    let (mut bullish, mut bearish) = (0.0, 0.0);
+
+    if rsi < 30.0 {
+        bullish += 2.0;
+    } else if rsi < 40.0 {
+        bullish += 1.0;
+    } else if rsi > 70.0 {
+        bearish += 2.0;
+    } else if rsi > 60.0 {
+        bearish += 1.0;
+    }
+
+    if macd.histogram > 0.0 && macd.macd > macd.signal {
+        bullish += 2.0;
+    } else if macd.histogram < 0.0 && macd.macd < macd.signal {
+        bearish += 2.0;
+    }
+
+    match trend {
+        Trend::Up => bullish += 2.0,
+        Trend::Down => bearish += 2.0,
+        Trend::Sideways => {}
+    }
+
+    // Pattern indices are relative to the 10-candle window while the cutoff
+    // uses the full series, exactly as in the TS engine. Changing one side
+    // alone would make the two engines disagree.
+    let recent_patterns: Vec<Pattern> = patterns
+        .into_iter()
+        .filter(|p| p.candle_index + 3 >= candles.len())
+        .collect();
+    for pattern in &recent_patterns {
+        let weight = pattern.kind.weight() * f64::from(pattern.strength) * 3.0;
+        match pattern.direction {
+            Bias::Bullish => bullish += weight,
+            _ => bearish += weight,
+        }
+    }
+
+    let position = (current_price - support) / (resistance - support);
+    if position < 0.3 {
+        bullish += 1.0;
+    }
+    if position > 0.7 {
+        bearish += 1.0;
+    }
+
+    let total = bullish + bearish;
+    let (direction, confidence) = if total <= 0.0 {
+        (SignalDirection::Hold, 0.0)
+    } else if bullish / total > 0.6 {
+        (SignalDirection::Long, (bullish / total * 100.0).min(100.0))
+    } else if bearish / total > 0.6 {
+        (SignalDirection::Short, (bearish / total * 100.0).min(100.0))
+    } else {
+        (SignalDirection::Hold, 50.0)
+    };
+
+    let macd_signal = if macd.histogram > 0.0 {
+        Bias::Bullish
+    } else if macd.histogram < 0.0 {
+        Bias::Bearish
+    } else {
+        Bias::Neutral
+    };
+
+    TimeframeAnalysis {
+        timeframe,
+        direction,
+        confidence,
+        trend,
+        rsi,
+        macd_signal,
+        patterns: recent_patterns,
+        support,
+        resistance,
+        current_price,
+    }
+}
+
+fn direction_label(direction: SignalDirection) -> &'static str {
+    match direction {
+        SignalDirection::Long => "LONG",
+        SignalDirection::Short => "SHORT",
+        SignalDirection::Hold => "HOLD",
+    }
+}
+
+fn timeframe_label(timeframe: Timeframe) -> &'static str {
+    match timeframe {
+        Timeframe::M1 => "1m",
+        Timeframe::M5 => "5m",
+        Timeframe::M15 => "15m",
+        Timeframe::H1 => "1h",
+        Timeframe::H4 => "4h",
+        Timeframe::D1 => "1d",
+    }
+}
+
+fn pattern_list(patterns: &[Pattern], direction: Bias) -> Option<String> {
+    let names: Vec<String> = patterns
+        .iter()
+        .filter(|p| p.direction == direction)
+        // The TS engine only replaces the first underscore
+        .map(|p| p.kind.as_str().replacen('_', " ", 1))
+        .collect();
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+// Combine per-timeframe analyses into one weighted signal
+pub fn combine(symbol: &str, analyses: Vec<TimeframeAnalysis>, timestamp: i64) -> UnifiedSignal {
+    let count = analyses.len().max(1) as f64;
+    let current_price = analyses.first().map(|a| a.current_price).unwrap_or(0.0);
+    let mut reasons = Vec::new();
+    let mut warnings = Vec::new();
+
+    let patterns: Vec<Pattern> = analyses
+        .iter()
+        .flat_map(|a| a.patterns.iter().copied())
+        .collect();
+
+    let trend_count = |trend| analyses.iter().filter(|a| a.trend == trend).count();
+    let max_trend_count = [Trend::Up, Trend::Down, Trend::Sideways]
+        .into_iter()
+        .map(trend_count)
+        .max()
+        .unwrap_or(0);
+    let trend_alignment = max_trend_count as f64 / count * 100.0;
+
+    let (mut weighted_bullish, mut weighted_bearish, mut total_weight) = (0.0, 0.0, 0.0);
+    for analysis in &analyses {
+        let (trend_weight, entry_weight) = timeframe_weights(analysis.timeframe);
+        let weight = (trend_weight + entry_weight) / 2.0;
+        total_weight += weight;
+
+        match analysis.direction {
+            SignalDirection::Long => weighted_bullish += weight * analysis.confidence / 100.0,
+            SignalDirection::Short => weighted_bearish += weight * analysis.confidence / 100.0,
+            SignalDirection::Hold => {}
+        }
+
+        if analysis.confidence > 60.0 {
+            reasons.push(format!(
+                "{}: {} ({:.0}% conf, RSI: {:.0})",
+                timeframe_label(analysis.timeframe),
+                direction_label(analysis.direction),
+                analysis.confidence,
+                analysis.rsi
+            ));
+        }
+    }
+
+    let (bullish_score, bearish_score) = if total_weight > 0.0 {
+        (
+            weighted_bullish / total_weight * 100.0,
+            weighted_bearish / total_weight * 100.0,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+
+    let pattern_strength = patterns
+        .iter()
+        .map(|p| p.kind.weight() * f64::from(p.strength) * 10.0)
+        .sum::<f64>()
+        .min(100.0);
+
+    let bonus = |score: f64| {
+        let alignment_bonus = if trend_alignment > 75.0 { 10.0 } else { 0.0 };
+        let pattern_bonus = if pattern_strength > 50.0 { 10.0 } else { 0.0 };
+        (score + alignment_bonus + pattern_bonus).min(100.0)
+    };
+
+    let (direction, mut confidence) = if bullish_score > bearish_score && bullish_score > 40.0 {
+        if let Some(list) = pattern_list(&patterns, Bias::Bullish) {
+            reasons.push(format!("Bullish patterns: {}", list));
+        }
+        (SignalDirection::Long, bonus(bullish_score))
+    } else if bearish_score > bullish_score && bearish_score > 40.0 {
+        if let Some(list) = pattern_list(&patterns, Bias::Bearish) {
+            reasons.push(format!("Bearish patterns: {}", list));
+        }
+        (SignalDirection::Short, bonus(bearish_score))
+    } else {
+        warnings.push("Mixed signals across timeframes".to_string());
+        (SignalDirection::Hold, 50.0)
+    };
+
+    let has = |direction| analyses.iter().any(|a| a.direction == direction);
+    if has(SignalDirection::Long) && has(SignalDirection::Short) {
+        warnings.push("Conflicting signals: Some timeframes show LONG, others SHORT".to_string());
+        confidence = (confidence - 20.0).max(25.0);
+    }
+
+    let avg_support = analyses.iter().map(|a| a.support).sum::<f64>() / count;
+    let avg_resistance = analyses.iter().map(|a| a.resistance).sum::<f64>() / count;
+    let (suggested_tp, suggested_sl) = match direction {
+        SignalDirection::Short => (avg_support, avg_resistance),
+        _ => (avg_resistance, avg_support),
+    };
+
+    UnifiedSignal {
+        symbol: symbol.to_string(),
+        direction,
+        confidence,
+        timeframes: analyses,
+        trend_alignment,
+        pattern_strength,
+        patterns,
+        suggested_entry: current_price,
+        suggested_tp,
+        suggested_sl,
+        reasons,
+        warnings,
+        timestamp,
+    }
+}
+
+// Fetch every timeframe in parallel and combine them
+pub async fn generate_signal(
+    app: &tauri::AppHandle,
+    symbol: &str,
+    timeframes: &[Timeframe],
+) -> AppResult<UnifiedSignal> {
+    if timeframes.is_empty() {
+        return Err(AppError::InvalidInput(
+            "At least one timeframe is required".to_string(),
+        ));
+    }
+    if timeframes.contains(&Timeframe::D1) {
+        return Err(AppError::InvalidInput(
+            "The 1d timeframe is not supported for signals".to_string(),
+        ));
+    }
+
+    let symbol = symbol.trim().to_uppercase();
+
+    let mut fetches = JoinSet::new();
+    for (index, &timeframe) in timeframes.iter().enumerate() {
+        let (app, symbol) = (app.clone(), symbol.clone());
+        fetches.spawn(async move {
+            let result = market_data::fetch_candles(&app, &symbol, timeframe, CANDLE_LIMIT).await;
+            (index, result)
+        });
+    }
+
+    let mut results: Vec<AppResult<Vec<Candle>>> = timeframes
+        .iter()
+        .map(|_| {
+            Err(AppError::Internal(
+                "candle fetch did not finish".to_string(),
+            ))
+        })
+        .collect();
+    while let Some(joined) = fetches.join_next().await {
+        let (index, result) = joined.map_err(|e| AppError::Internal(e.to_string()))?;
+        results[index] = result;
+    }
+
+    // A timeframe without data is analysed as HOLD, like the TS engine does,
+    // but a symbol no exchange knows is an error
+    let mut last_error = None;
+    let mut analyses = Vec::with_capacity(timeframes.len());
+    for (&timeframe, result) in timeframes.iter().zip(results) {
+        let candles = match result {
+            Ok(candles) => candles,
+            Err(AppError::InvalidInput(message)) => return Err(AppError::InvalidInput(message)),
+            Err(e) => {
+                log::warn!("No {:?} candles for {}: {}", timeframe, symbol, e);
+                last_error = Some(e);
+                Vec::new()
+            }
+        };
+        analyses.push(analyze_timeframe(timeframe, &candles));
+    }
+    if let Some(e) = last_error.filter(|_| analyses.iter().all(|a| a.current_price == 0.0)) {
+        return Err(e);
+    }
+
+    let signal = combine(&symbol, analyses, chrono::Utc::now().timestamp_millis());
+    log::info!(
+        "Signal for {}: {:?} {:.0}% (alignment {:.0}%, patterns {:.0}%)",
+        symbol,
+        signal.direction,
+        signal.confidence,
+        signal.trend_alignment,
+        signal.pattern_strength
+    );
+    Ok(signal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    // Recorded by bot-service/generate_signal_goldens.ts from signalEngine.ts
+    const GOLDENS: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/signal_goldens.json"
+    ));
+
+    #[derive(Deserialize)]
+    struct Golden {
+        name: String,
+        symbol: String,
+        timeframes: Vec<Timeframe>,
+        candles: HashMap<Timeframe, Vec<Candle>>,
+        signal: Value,
+    }
+
+    fn assert_matches(path: &str, actual: &Value, expected: &Value) {
+        match (actual, expected) {
+            (Value::Number(a), Value::Number(e)) => {
+                let (a, e) = (a.as_f64().unwrap(), e.as_f64().unwrap());
+                assert!(
+                    (a - e).abs() <= 1e-9 * e.abs().max(1.0),
+                    "{}: {} != {}",
+                    path,
+                    a,
+                    e
+                );
+            }
+            (Value::Array(a), Value::Array(e)) => {
+                assert_eq!(a.len(), e.len(), "{}: length", path);
+                for (i, (a, e)) in a.iter().zip(e).enumerate() {
+                    assert_matches(&format!("{}[{}]", path, i), a, e);
+                }
+            }
+            (Value::Object(a), Value::Object(e)) => {
+                let mut keys: Vec<_> = a.keys().chain(e.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                for key in keys {
+                    let (a, e) = (&a.get(key), &e.get(key));
+                    match (a, e) {
+                        (Some(a), Some(e)) => assert_matches(&format!("{}.{}", path, key), a, e),
+                        _ => panic!("{}.{}: {:?} != {:?}", path, key, a, e),
+                    }
+                }
+            }
+            _ => assert_eq!(actual, expected, "{}", path),
+        }
+    }
+
+    #[test]
+    fn matches_the_ts_engine() {
+        let goldens: Vec<Golden> = serde_json::from_str(GOLDENS).unwrap();
+        assert!(!goldens.is_empty());
+        for golden in goldens {
+            let analyses = golden
+                .timeframes
+                .iter()
+                .map(|&timeframe| {
+                    let candles = golden.candles.get(&timeframe).cloned().unwrap_or_default();
+                    analyze_timeframe(timeframe, &candles)
+                })
+                .collect();
+            let signal = combine(&golden.symbol, analyses, 0);
+            assert_matches(
+                &golden.name,
+                &serde_json::to_value(&signal).unwrap(),
+                &golden.signal,
+            );
+        }
+    }
+}
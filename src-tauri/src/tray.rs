@@ -0,0 +1,276 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{Manager, Window, WindowEvent};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::alerts;
+use crate::chain;
+use crate::error::AppResult;
+use crate::gmx_monitor;
+use crate::license;
+use crate::paper;
+use crate::settings;
+use crate::tx::{self, VaultAction};
+use crate::wallet;
+
+const TRAY_SETTINGS_KEY: &str = "tray";
+
+const TRAY_ID: &str = "main";
+const MAIN_WINDOW: &str = "main";
+
+const PAUSE_ID: &str = "pause_auto_trade";
+const OPEN_ID: &str = "open_dashboard";
+const QUIT_ID: &str = "quit";
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TraySettings {
+    // Hide to the tray instead of quitting when the window closes
+    pub close_to_tray: bool,
+}
+
+impl Default for TraySettings {
+    fn default() -> Self {
+        Self {
+            close_to_tray: true,
+        }
+    }
+}
+
+impl TraySettings {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        settings::load(app, TRAY_SETTINGS_KEY)
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> AppResult<()> {
+        settings::save(app, TRAY_SETTINGS_KEY, self)?;
+        Ok(())
+    }
+}
+
+// Menu lines updated by run(), registered as Tauri state once the tray exists
+pub struct TrayMenu {
+    positions: MenuItem<tauri::Wry>,
+    pnl: MenuItem<tauri::Wry>,
+    license: MenuItem<tauri::Wry>,
+    pause: MenuItem<tauri::Wry>,
+    // The first hide explains where the window went
+    hinted: AtomicBool,
+}
+
+// What the status lines show
+struct TrayStatus {
+    vault_positions: usize,
+    vault_pnl: f64,
+    paper_positions: usize,
+    paper_pnl: f64,
+    license: String,
+    // None without an unlocked wallet or when the vault is unreachable
+    auto_trade: Option<bool>,
+}
+
+// Build the tray icon and its menu, called from run()'s setup hook
+pub fn create(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let positions = MenuItem::with_id(app, "positions", "Open positions: -", false, None::<&str>)?;
+    let pnl = MenuItem::with_id(app, "pnl", "Total PnL: -", false, None::<&str>)?;
+    let license = MenuItem::with_id(app, "license", "License: -", false, None::<&str>)?;
+    let pause = MenuItem::with_id(app, PAUSE_ID, "Pause auto-trade", true, None::<&str>)?;
+    let open = MenuItem::with_id(app, OPEN_ID, "Open dashboard", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, QUIT_ID, "Quit Monadier", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &positions,
+            &pnl,
+            &license,
+            &PredefinedMenuItem::separator(app)?,
+            &pause,
+            &open,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Monadier")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_dashboard(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    app.manage(TrayMenu {
+        positions,
+        pnl,
+        license,
+        pause,
+        hinted: AtomicBool::new(false),
+    });
+    Ok(())
+}
+
+fn on_menu_event(app: &tauri::AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        PAUSE_ID => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { pause_auto_trade(&app).await });
+        }
+        OPEN_ID => show_dashboard(app),
+        QUIT_ID => app.exit(0),
+        _ => {}
+    }
+}
+
+pub fn show_dashboard(app: &tauri::AppHandle) {
+    let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+        return;
+    };
+    if let Err(e) = window
+        .show()
+        .and_then(|_| window.unminimize())
+        .and_then(|_| window.set_focus())
+    {
+        log::warn!("Failed to show the dashboard: {}", e);
+    }
+}
+
+// Closing the main window hides it, the monitors keep running and only
+// the tray menu quits
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    let WindowEvent::CloseRequested { api, .. } = event else {
+        return;
+    };
+    let app = window.app_handle();
+    if window.label() != MAIN_WINDOW
+        || !TraySettings::load(app).close_to_tray
+        || app.tray_by_id(TRAY_ID).is_none()
+    {
+        return;
+    }
+
+    api.prevent_close();
+    if let Err(e) = window.hide() {
+        log::warn!("Failed to hide the window: {}", e);
+        return;
+    }
+    if let Some(menu) = app.try_state::<TrayMenu>() {
+        if !menu.hinted.swap(true, Ordering::Relaxed) {
+            let shown = app
+                .notification()
+                .builder()
+                .title("Monadier is still running")
+                .body("Monitoring continues in the background. Quit from the tray icon.")
+                .show();
+            if let Err(e) = shown {
+                log::warn!("Failed to show the tray hint: {}", e);
+            }
+        }
+    }
+}
+
+// Turn auto-trade off on the vault with the unlocked wallet
+async fn pause_auto_trade(app: &tauri::AppHandle) {
+    let message = match wallet::unlocked_address(app) {
+        None => {
+            show_dashboard(app);
+            "Unlock the wallet to pause auto-trade".to_string()
+        }
+        Some(_) => match tx::send(app, &VaultAction::SetAutoTrade { enabled: false }).await {
+            Ok(record) => format!("Auto-trade pause sent in {}", record.hash),
+            Err(e) => format!("Could not pause auto-trade: {}", e),
+        },
+    };
+    alerts::notify(app, None, "Auto-trade", &message);
+}
+
+async fn status(app: &tauri::AppHandle) -> TrayStatus {
+    let vault = gmx_monitor::positions(app);
+    let mut status = TrayStatus {
+        vault_positions: vault.len(),
+        vault_pnl: vault.iter().map(|p| p.pnl).sum(),
+        paper_positions: 0,
+        paper_pnl: 0.0,
+        license: "not activated".to_string(),
+        auto_trade: None,
+    };
+
+    for position in paper::positions(app, false).unwrap_or_default() {
+        status.paper_positions += 1;
+        if let Ok(price) = paper::current_price(app, &position.token_symbol).await {
+            status.paper_pnl += position.unrealized(price);
+        }
+    }
+
+    if let Ok(Some(stored)) = license::load(app) {
+        status.license = if stored.token.expires_at() < Utc::now() {
+            format!("{} (expired)", stored.token.plan_tier)
+        } else {
+            stored.token.plan_tier
+        };
+    }
+
+    if let Some(user) = wallet::unlocked_address(app) {
+        status.auto_trade = chain::user_status(app, &user.to_string())
+            .await
+            .ok()
+            .map(|s| s.auto_trade_enabled);
+    }
+    status
+}
+
+fn render(menu: &TrayMenu, status: &TrayStatus) -> tauri::Result<()> {
+    menu.positions.set_text(format!(
+        "Open positions: {} vault, {} paper",
+        status.vault_positions, status.paper_positions
+    ))?;
+    let pnl = if status.paper_positions > 0 {
+        format!(
+            "Total PnL: {:+.2} USDC (paper {:+.2})",
+            status.vault_pnl, status.paper_pnl
+        )
+    } else {
+        format!("Total PnL: {:+.2} USDC", status.vault_pnl)
+    };
+    menu.pnl.set_text(pnl)?;
+    menu.license
+        .set_text(format!("License: {}", status.license))?;
+
+    let (text, enabled) = match status.auto_trade {
+        Some(false) => ("Auto-trade is off", false),
+        _ => ("Pause auto-trade", true),
+    };
+    menu.pause.set_text(text)?;
+    menu.pause.set_enabled(enabled)?;
+    Ok(())
+}
+
+// Keep the tray status current forever, started from run()'s setup hook
+pub async fn run(app: tauri::AppHandle) {
+    loop {
+        let status = status(&app).await;
+        if let Some(menu) = app.try_state::<TrayMenu>() {
+            if let Err(e) = render(&menu, &status) {
+                log::warn!("Failed to update the tray menu: {}", e);
+            }
+        }
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+    }
+}
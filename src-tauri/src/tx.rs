@@ -0,0 +1,892 @@
+use alloy::primitives::utils::parse_units;
+use alloy::primitives::{keccak256, Address, Bytes, B256, U128, U256, U64};
+use alloy::sol_types::SolCall;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::chain::abi::{IVault, IERC20};
+use crate::chain::{self, RpcClient, USDC_DECIMALS};
+use crate::error::{AppError, AppResult};
+use crate::journal::{self, JournalFee};
+use crate::wallet::{self, UnsignedTransaction};
+
+pub const TX_EVENT: &str = "tx://status";
+
+// Added on top of eth_estimateGas, GMX keeper paths vary between blocks
+const GAS_MARGIN_PERCENT: u64 = 20;
+// Room for the base fee to double before the transaction lands
+const BASE_FEE_MULTIPLIER: u128 = 2;
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+const ETH_DECIMALS: u8 = 18;
+
+// Vault writes from vault.ts. Amounts are USDC, percents as in the UI.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum VaultAction {
+    // Approves the vault for USDC first when the allowance is short
+    Deposit {
+        amount: String,
+    },
+    Withdraw {
+        amount: String,
+    },
+    // Sends the GMX execution fee along
+    UserInstantClose {
+        token: Address,
+    },
+    EmergencyWithdraw,
+    SetAutoTrade {
+        enabled: bool,
+    },
+    SetSettings {
+        risk_percent: f64,
+        max_leverage: u64,
+        stop_loss_percent: f64,
+        take_profit_percent: f64,
+    },
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    Pending,
+    Confirmed,
+    Failed,
+}
+
+// One contract call, ready for gas estimation
+struct Call {
+    action: &'static str,
+    to: Address,
+    data: Bytes,
+    value: U256,
+}
+
+impl Call {
+    fn new(action: &'static str, to: Address, call: impl SolCall) -> Self {
+        Self {
+            action,
+            to,
+            data: call.abi_encode().into(),
+            value: U256::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GasQuote {
+    // "approve" while a deposit still waits for its USDC allowance
+    pub action: String,
+    pub gas_estimate: u64,
+    pub gas_limit: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    // Wei sent along, the GMX execution fee for closes
+    pub value: U256,
+    // Worst case gas plus value, in ETH
+    pub max_cost: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TxRecord {
+    pub hash: B256,
+    pub action: String,
+    pub from: Address,
+    pub to: Address,
+    pub nonce: u64,
+    pub quote: GasQuote,
+    pub status: TxStatus,
+    pub block_number: Option<u64>,
+    pub gas_used: Option<u64>,
+    // Gas actually paid, in ETH
+    pub fee: Option<f64>,
+    pub error: Option<String>,
+    pub submitted_at: String,
+    pub updated_at: String,
+}
+
+// Next nonce per sender and this session's transactions
+#[derive(Default)]
+pub struct TxState {
+    // Held from nonce pick to broadcast so two sends never share a nonce
+    nonces: tokio::sync::Mutex<HashMap<Address, u64>>,
+    transactions: Mutex<Vec<TxRecord>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockHeader {
+    base_fee_per_gas: U128,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Receipt {
+    status: U64,
+    block_number: U64,
+    gas_used: U64,
+    effective_gas_price: U128,
+}
+
+impl Receipt {
+    // Gas actually paid, in ETH
+    fn fee(&self) -> f64 {
+        chain::units(
+            U256::from(self.gas_used.to::<u64>())
+                * U256::from(self.effective_gas_price.to::<u128>()),
+            ETH_DECIMALS,
+        )
+    }
+}
+
+fn usdc_amount(amount: &str) -> AppResult<U256> {
+    let parsed = parse_units(amount, USDC_DECIMALS)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid amount {}: {}", amount, e)))?;
+    if parsed.is_negative() || parsed.get_absolute().is_zero() {
+        return Err(AppError::InvalidInput("Amount must be positive".into()));
+    }
+    Ok(parsed.get_absolute())
+}
+
+fn bps(percent: f64) -> AppResult<U256> {
+    if !percent.is_finite() || percent < 0.0 {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid percent: {}",
+            percent
+        )));
+    }
+    Ok(U256::from((percent * 100.0).round() as u64))
+}
+
+fn signer_address(app: &tauri::AppHandle) -> AppResult<Address> {
+    wallet::unlocked_address(app).ok_or(AppError::WalletLocked)
+}
+
+async fn build(rpc: &RpcClient<'_>, action: &VaultAction) -> AppResult<Call> {
+    let vault = rpc.settings().vault_address;
+    Ok(match action {
+        VaultAction::Deposit { amount } => Call::new(
+            "deposit",
+            vault,
+            IVault::depositCall {
+                amount: usdc_amount(amount)?,
+            },
+        ),
+        VaultAction::Withdraw { amount } => Call::new(
+            "withdraw",
+            vault,
+            IVault::withdrawCall {
+                amount: usdc_amount(amount)?,
+            },
+        ),
+        VaultAction::UserInstantClose { token } => Call {
+            value: rpc.call(vault, IVault::getExecutionFeeCall {}).await?,
+            ..Call::new(
+                "user_instant_close",
+                vault,
+                IVault::userInstantCloseCall { token: *token },
+            )
+        },
+        VaultAction::EmergencyWithdraw => Call::new(
+            "emergency_withdraw",
+            vault,
+            IVault::emergencyWithdrawCall {},
+        ),
+        VaultAction::SetAutoTrade { enabled } => Call::new(
+            "set_auto_trade",
+            vault,
+            IVault::setAutoTradeCall { enabled: *enabled },
+        ),
+        VaultAction::SetSettings {
+            risk_percent,
+            max_leverage,
+            stop_loss_percent,
+            take_profit_percent,
+        } => Call::new(
+            "set_settings",
+            vault,
+            IVault::setSettingsCall {
+                riskBps: bps(*risk_percent)?,
+                maxLeverage: U256::from(*max_leverage),
+                stopLossBps: bps(*stop_loss_percent)?,
+                takeProfitBps: bps(*take_profit_percent)?,
+            },
+        ),
+    })
+}
+
+// eth_estimateGas plus margin, fees from the latest base fee
+async fn quote(rpc: &RpcClient<'_>, from: Address, call: &Call) -> AppResult<GasQuote> {
+    let estimate: U64 = rpc
+        .request(
+            "eth_estimateGas",
+            json!([{ "from": from, "to": call.to, "data": call.data, "value": call.value }]),
+        )
+        .await?;
+    let gas_estimate = estimate.to::<u64>();
+    let gas_limit = gas_estimate * (100 + GAS_MARGIN_PERCENT) / 100;
+
+    let block: BlockHeader = rpc
+        .request("eth_getBlockByNumber", json!(["latest", false]))
+        .await?;
+    let priority: U128 = rpc.request("eth_maxPriorityFeePerGas", json!([])).await?;
+    let max_priority_fee_per_gas = priority.to::<u128>();
+    let max_fee_per_gas =
+        block.base_fee_per_gas.to::<u128>() * BASE_FEE_MULTIPLIER + max_priority_fee_per_gas;
+
+    let max_gas_cost = U256::from(gas_limit) * U256::from(max_fee_per_gas);
+    Ok(GasQuote {
+        action: call.action.to_string(),
+        gas_estimate,
+        gas_limit,
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+        value: call.value,
+        max_cost: chain::units(max_gas_cost + call.value, ETH_DECIMALS),
+    })
+}
+
+fn emit(app: &tauri::AppHandle, record: &TxRecord) {
+    if let Err(e) = app.emit(TX_EVENT, record) {
+        log::warn!("Failed to emit transaction status: {}", e);
+    }
+}
+
+fn store(app: &tauri::AppHandle, record: &TxRecord) {
+    let state = app.state::<TxState>();
+    let mut transactions = state.transactions.lock().unwrap();
+    match transactions.iter_mut().find(|tx| tx.hash == record.hash) {
+        Some(existing) => *existing = record.clone(),
+        None => transactions.push(record.clone()),
+    }
+    drop(transactions);
+    emit(app, record);
+}
+
+// Outcome of TxState::broadcast
+struct Broadcast {
+    nonce: u64,
+    hash: B256,
+    // Set when the node could not be reached to confirm it got the tx
+    unconfirmed: Option<AppError>,
+}
+
+impl TxState {
+    // Pick the nonce, sign with it and broadcast. Errors only when the node
+    // refused the transaction.
+    async fn broadcast(
+        &self,
+        rpc: &RpcClient<'_>,
+        from: Address,
+        sign: impl FnOnce(u64) -> AppResult<String>,
+    ) -> AppResult<Broadcast> {
+        let mut nonces = self.nonces.lock().await;
+        // Our own count wins while the node has not seen the last broadcast yet
+        let nonce = nonces
+            .get(&from)
+            .copied()
+            .unwrap_or(0)
+            .max(pending_count(rpc, from).await?);
+
+        let raw = sign(nonce)?;
+        let hash = keccak256(
+            alloy::hex::decode(&raw)
+                .map_err(|e| AppError::Internal(format!("Signed tx: {}", e)))?,
+        );
+
+        let unconfirmed = match rpc
+            .request::<B256>("eth_sendRawTransaction", json!([raw]))
+            .await
+        {
+            Ok(_) => None,
+            // A failover node already had it from the first attempt
+            Err(e) if e.to_string().contains("already known") => None,
+            // The node refused it, so the nonce is still free
+            Err(e @ AppError::ServerRejected(_)) => {
+                if e.to_string().contains("nonce too low") {
+                    nonces.remove(&from);
+                }
+                return Err(e);
+            }
+            // It may have gone out before the connection failed
+            Err(e) => Some(e),
+        };
+
+        match &unconfirmed {
+            None => {
+                nonces.insert(from, nonce + 1);
+            }
+            // Keep the nonce only if the node counts the tx, otherwise later
+            // sends would queue behind a gap
+            Some(e) => {
+                log::warn!("Broadcast of {} unconfirmed: {}", hash, e);
+                match pending_count(rpc, from).await {
+                    Ok(pending) if pending <= nonce => {
+                        nonces.remove(&from);
+                    }
+                    _ => {
+                        nonces.insert(from, nonce + 1);
+                    }
+                }
+            }
+        }
+
+        Ok(Broadcast {
+            nonce,
+            hash,
+            unconfirmed,
+        })
+    }
+
+    // Back to the node's pending count once a tx was dropped
+    async fn reset_nonce(&self, from: Address) {
+        self.nonces.lock().await.remove(&from);
+    }
+}
+
+async fn pending_count(rpc: &RpcClient<'_>, from: Address) -> AppResult<u64> {
+    let pending: U64 = rpc
+        .request("eth_getTransactionCount", json!([from, "pending"]))
+        .await?;
+    Ok(pending.to::<u64>())
+}
+
+// Quote, sign with the unlocked keystore wallet and broadcast. A tx whose
+// broadcast could not be confirmed is still recorded and tracked.
+async fn submit(
+    app: &tauri::AppHandle,
+    rpc: &RpcClient<'_>,
+    from: Address,
+    call: Call,
+) -> AppResult<TxRecord> {
+    let quote = quote(rpc, from, &call).await?;
+
+    let sent = app
+        .state::<TxState>()
+        .broadcast(rpc, from, |nonce| {
+            wallet::sign_transaction(
+                app,
+                UnsignedTransaction {
+                    chain_id: rpc.settings().chain_id,
+                    nonce,
+                    to: Some(call.to),
+                    value: call.value,
+                    data: call.data.clone(),
+                    gas_limit: quote.gas_limit,
+                    max_fee_per_gas: quote.max_fee_per_gas,
+                    max_priority_fee_per_gas: quote.max_priority_fee_per_gas,
+                },
+            )
+        })
+        .await?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let record = TxRecord {
+        hash: sent.hash,
+        action: call.action.to_string(),
+        from,
+        to: call.to,
+        nonce: sent.nonce,
+        quote,
+        status: TxStatus::Pending,
+        block_number: None,
+        gas_used: None,
+        fee: None,
+        error: sent
+            .unconfirmed
+            .map(|e| format!("Broadcast unconfirmed: {}", e)),
+        submitted_at: now.clone(),
+        updated_at: now,
+    };
+    log::info!(
+        "Broadcast {} {} (nonce {})",
+        record.action,
+        record.hash,
+        record.nonce
+    );
+    store(app, &record);
+    Ok(record)
+}
+
+// Poll for the receipt until mined or timed out, emitting the outcome
+async fn track(app: tauri::AppHandle, mut record: TxRecord) -> TxRecord {
+    let started = Instant::now();
+
+    let outcome = loop {
+        tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        let rpc = RpcClient::new(&app);
+        match rpc
+            .request::<Option<Receipt>>("eth_getTransactionReceipt", json!([record.hash]))
+            .await
+        {
+            Ok(Some(receipt)) => break Ok(receipt),
+            Ok(None) => {}
+            Err(e) => log::warn!("Receipt poll for {} failed: {}", record.hash, e),
+        }
+        if started.elapsed() >= RECEIPT_TIMEOUT {
+            break Err(format!(
+                "Not mined within {} minutes",
+                RECEIPT_TIMEOUT.as_secs() / 60
+            ));
+        }
+    };
+
+    match outcome {
+        Ok(receipt) => {
+            let fee = receipt.fee();
+            record.block_number = Some(receipt.block_number.to::<u64>());
+            record.gas_used = Some(receipt.gas_used.to::<u64>());
+            record.fee = Some(fee);
+            if receipt.status.is_zero() {
+                record.status = TxStatus::Failed;
+                record.error = Some("Transaction reverted".into());
+            } else {
+                record.status = TxStatus::Confirmed;
+                record.error = None;
+            }
+
+            // Reverted transactions still pay for gas
+            let gas_fee = JournalFee {
+                id: 0,
+                position_id: None,
+                kind: "gas".into(),
+                amount: fee,
+                asset: "ETH".into(),
+                tx_hash: Some(record.hash.to_string()),
+                created_at: None,
+            };
+            if let Err(e) = journal::record_fee(&app, gas_fee) {
+                log::warn!("Failed to journal gas for {}: {}", record.hash, e);
+            }
+        }
+        // Dropped from the mempool, later sends must not queue behind it
+        Err(error) => {
+            record.status = TxStatus::Failed;
+            record.error = Some(error);
+            app.state::<TxState>().reset_nonce(record.from).await;
+        }
+    }
+
+    record.updated_at = chrono::Utc::now().to_rfc3339();
+    log::info!("{} {} is {:?}", record.action, record.hash, record.status);
+    store(&app, &record);
+    record
+}
+
+// The max approval vault.ts deposit() sends when the allowance is short
+async fn approval(rpc: &RpcClient<'_>, from: Address, amount: &str) -> AppResult<Option<Call>> {
+    let usdc = rpc.settings().usdc_address;
+    let vault = rpc.settings().vault_address;
+    let allowance = rpc
+        .call(
+            usdc,
+            IERC20::allowanceCall {
+                owner: from,
+                spender: vault,
+            },
+        )
+        .await?;
+    if allowance >= usdc_amount(amount)? {
+        return Ok(None);
+    }
+
+    Ok(Some(Call::new(
+        "approve",
+        usdc,
+        IERC20::approveCall {
+            spender: vault,
+            amount: U256::MAX,
+        },
+    )))
+}
+
+// Transactions `action` takes, in order. A deposit short on allowance is
+// preceded by the approval.
+async fn calls(rpc: &RpcClient<'_>, from: Address, action: &VaultAction) -> AppResult<Vec<Call>> {
+    let mut calls = Vec::new();
+    if let VaultAction::Deposit { amount } = action {
+        calls.extend(approval(rpc, from, amount).await?);
+    }
+    calls.push(build(rpc, action).await?);
+    Ok(calls)
+}
+
+// Quotes the first transaction only, a deposit waiting for its approval
+// would revert in eth_estimateGas
+pub async fn estimate(app: &tauri::AppHandle, action: &VaultAction) -> AppResult<GasQuote> {
+    let from = signer_address(app)?;
+    let rpc = RpcClient::new(app);
+    let calls = calls(&rpc, from, action).await?;
+    quote(&rpc, from, &calls[0]).await
+}
+
+// Earlier steps must confirm before the next goes out. Returns once the
+// last one is broadcast, its receipt arrives as a TX_EVENT.
+pub async fn send(app: &tauri::AppHandle, action: &VaultAction) -> AppResult<TxRecord> {
+    let from = signer_address(app)?;
+    let rpc = RpcClient::new(app);
+    let mut calls = calls(&rpc, from, action).await?;
+    let last = calls.pop().expect("calls() ends with the action");
+
+    for call in calls {
+        let record = submit(app, &rpc, from, call).await?;
+        let record = track(app.clone(), record).await;
+        if record.status != TxStatus::Confirmed {
+            return Err(AppError::ServerRejected(format!(
+                "{} {} failed: {}",
+                record.action,
+                record.hash,
+                record.error.unwrap_or_default()
+            )));
+        }
+    }
+
+    let record = submit(app, &rpc, from, last).await?;
+    tauri::async_runtime::spawn(track(app.clone(), record.clone()));
+    Ok(record)
+}
+
+// Same as send() for calldata built elsewhere, e.g. for a legacy vault
+pub async fn send_data(
+    app: &tauri::AppHandle,
+    action: &'static str,
+    to: Address,
+    data: Bytes,
+) -> AppResult<TxRecord> {
+    let from = signer_address(app)?;
+    let rpc = RpcClient::new(app);
+    let call = Call {
+        action,
+        to,
+        data,
+        value: U256::ZERO,
+    };
+    let record = submit(app, &rpc, from, call).await?;
+    tauri::async_runtime::spawn(track(app.clone(), record.clone()));
+    Ok(record)
+}
+
+pub fn transactions(app: &tauri::AppHandle) -> Vec<TxRecord> {
+    app.state::<TxState>().transactions.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::testing::{client, error, methods, node, result, ARBITRUM, DEAD_NODE};
+    use crate::chain::ChainState;
+    use serde_json::Value;
+
+    const FROM: Address = Address::repeat_byte(0x11);
+
+    // Any hex decodes, the recorded node does not look inside
+    fn sign(nonce: u64) -> AppResult<String> {
+        Ok(format!("0x{:02x}", nonce))
+    }
+
+    fn count(value: &str) -> (&'static str, (u16, Value)) {
+        ("eth_getTransactionCount", result(value))
+    }
+
+    fn sent() -> (&'static str, (u16, Value)) {
+        (
+            "eth_sendRawTransaction",
+            result("0x0000000000000000000000000000000000000000000000000000000000000001"),
+        )
+    }
+
+    fn rejected(message: &str) -> (&'static str, (u16, Value)) {
+        ("eth_sendRawTransaction", error(-32000, message))
+    }
+
+    async fn nonce_after(
+        state: &TxState,
+        responses: Vec<(&'static str, (u16, Value))>,
+    ) -> AppResult<u64> {
+        let mut responses = responses;
+        responses.push(("eth_chainId", result(ARBITRUM)));
+        let (url, _) = node(responses).await;
+        let chain = ChainState::default();
+        let rpc = client(&[&url], &chain);
+        state
+            .broadcast(&rpc, FROM, sign)
+            .await
+            .map(|sent| sent.nonce)
+    }
+
+    #[tokio::test]
+    async fn nonce_is_the_higher_of_node_and_own_count() {
+        let state = TxState::default();
+        assert_eq!(
+            nonce_after(&state, vec![count("0x5"), sent()])
+                .await
+                .unwrap(),
+            5
+        );
+        // The node has not seen nonce 5 yet
+        assert_eq!(
+            nonce_after(&state, vec![count("0x5"), sent()])
+                .await
+                .unwrap(),
+            6
+        );
+        // Sent from elsewhere in the meantime
+        assert_eq!(
+            nonce_after(&state, vec![count("0x9"), sent()])
+                .await
+                .unwrap(),
+            9
+        );
+        assert_eq!(state.nonces.lock().await.get(&FROM), Some(&10));
+    }
+
+    #[tokio::test]
+    async fn already_known_counts_as_sent() {
+        let state = TxState::default();
+        let sent = nonce_after(&state, vec![count("0x5"), rejected("already known")]).await;
+        assert_eq!(sent.unwrap(), 5);
+        assert_eq!(state.nonces.lock().await.get(&FROM), Some(&6));
+    }
+
+    #[tokio::test]
+    async fn nonce_too_low_falls_back_to_the_node() {
+        let state = TxState::default();
+        state.nonces.lock().await.insert(FROM, 7);
+        let error = nonce_after(
+            &state,
+            vec![count("0x5"), rejected("nonce too low: next nonce 8")],
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, AppError::ServerRejected(_)), "{:?}", error);
+        assert_eq!(state.nonces.lock().await.get(&FROM), None);
+    }
+
+    #[tokio::test]
+    async fn refused_tx_leaves_the_nonce_free() {
+        let state = TxState::default();
+        let error = nonce_after(
+            &state,
+            vec![count("0x5"), rejected("insufficient funds for gas")],
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, AppError::ServerRejected(_)), "{:?}", error);
+        assert_eq!(
+            nonce_after(&state, vec![count("0x5"), sent()])
+                .await
+                .unwrap(),
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_broadcast_frees_a_nonce_the_node_does_not_count() {
+        let (url, log) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            count("0x5"),
+            ("eth_sendRawTransaction", (503, Value::Null)),
+        ])
+        .await;
+        let chain = ChainState::default();
+        let rpc = client(&[&url], &chain);
+        let state = TxState::default();
+
+        let sent = state.broadcast(&rpc, FROM, sign).await.unwrap();
+        assert_eq!(sent.nonce, 5);
+        assert!(matches!(
+            sent.unconfirmed,
+            Some(AppError::ServerUnavailable(_))
+        ));
+        assert_eq!(sent.hash, keccak256([5u8]));
+        // The node still reports 5 pending, so nonce 5 is free again
+        assert_eq!(state.nonces.lock().await.get(&FROM), None);
+        assert_eq!(
+            methods(&log),
+            [
+                "eth_chainId",
+                "eth_getTransactionCount",
+                "eth_sendRawTransaction",
+                "eth_getTransactionCount"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn dropped_tx_resets_the_nonce() {
+        let state = TxState::default();
+        state.nonces.lock().await.insert(FROM, 12);
+        state.reset_nonce(FROM).await;
+        assert_eq!(
+            nonce_after(&state, vec![count("0x5"), sent()])
+                .await
+                .unwrap(),
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_node_sends_nothing() {
+        let chain = ChainState::default();
+        let rpc = client(&[DEAD_NODE], &chain);
+        let state = TxState::default();
+        let mut signed = false;
+        let outcome = state
+            .broadcast(&rpc, FROM, |nonce| {
+                signed = true;
+                sign(nonce)
+            })
+            .await;
+        assert!(outcome.is_err());
+        assert!(!signed);
+    }
+
+    // uint256 allowance as returned by eth_call
+    fn allowance(value: U256) -> (&'static str, (u16, Value)) {
+        let word = format!("0x{}", alloy::hex::encode(value.to_be_bytes::<32>()));
+        (
+            "eth_call",
+            (
+                200,
+                serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": word }),
+            ),
+        )
+    }
+
+    fn deposit(amount: &str) -> VaultAction {
+        VaultAction::Deposit {
+            amount: amount.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn deposit_short_on_allowance_approves_first() {
+        let (url, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            // 5 USDC approved
+            allowance(U256::from(5_000_000u64)),
+        ])
+        .await;
+        let chain = ChainState::default();
+        let rpc = client(&[&url], &chain);
+        let settings = rpc.settings().clone();
+
+        let calls = calls(&rpc, FROM, &deposit("10")).await.unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].action, "approve");
+        assert_eq!(calls[0].to, settings.usdc_address);
+        assert_eq!(
+            calls[0].data,
+            Bytes::from(
+                IERC20::approveCall {
+                    spender: settings.vault_address,
+                    amount: U256::MAX,
+                }
+                .abi_encode()
+            )
+        );
+        assert_eq!(calls[1].action, "deposit");
+        assert_eq!(calls[1].to, settings.vault_address);
+        assert_eq!(
+            calls[1].data,
+            Bytes::from(
+                IVault::depositCall {
+                    amount: U256::from(10_000_000u64),
+                }
+                .abi_encode()
+            )
+        );
+
+        // Exactly the approved amount needs no second approval
+        let calls = calls_for(&url, &deposit("5")).await;
+        assert_eq!(calls, ["deposit"]);
+    }
+
+    async fn calls_for(url: &str, action: &VaultAction) -> Vec<&'static str> {
+        let chain = ChainState::default();
+        let rpc = client(&[url], &chain);
+        calls(&rpc, FROM, action)
+            .await
+            .unwrap()
+            .iter()
+            .map(|call| call.action)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn other_actions_skip_the_allowance() {
+        let (url, log) = node(vec![("eth_chainId", result(ARBITRUM))]).await;
+        assert_eq!(
+            calls_for(
+                &url,
+                &VaultAction::Withdraw {
+                    amount: "10".into()
+                }
+            )
+            .await,
+            ["withdraw"]
+        );
+        assert!(!methods(&log).contains(&"eth_call".to_string()));
+    }
+
+    #[tokio::test]
+    async fn quote_adds_margin_and_base_fee_headroom() {
+        let (url, _) = node(vec![
+            ("eth_chainId", result(ARBITRUM)),
+            // 100_000 gas
+            ("eth_estimateGas", result("0x186a0")),
+            ("eth_getBlockByNumber", (200, serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": { "baseFeePerGas": "0x5f5e100" } }))),
+            // 0.01 gwei
+            ("eth_maxPriorityFeePerGas", result("0x989680")),
+        ])
+        .await;
+        let chain = ChainState::default();
+        let rpc = client(&[&url], &chain);
+        let call = Call {
+            value: U256::from(1_000_000_000_000_000u64),
+            ..Call::new(
+                "user_instant_close",
+                Address::ZERO,
+                IVault::emergencyWithdrawCall {},
+            )
+        };
+
+        let quote = quote(&rpc, FROM, &call).await.unwrap();
+        assert_eq!(quote.action, "user_instant_close");
+        assert_eq!(quote.gas_estimate, 100_000);
+        assert_eq!(quote.gas_limit, 120_000);
+        // Twice the 0.1 gwei base fee plus the tip
+        assert_eq!(quote.max_fee_per_gas, 210_000_000);
+        assert_eq!(quote.max_priority_fee_per_gas, 10_000_000);
+        // 120_000 * 0.21 gwei plus the 0.001 ETH sent along
+        assert!(
+            (quote.max_cost - 0.001_025_2).abs() < 1e-12,
+            "{}",
+            quote.max_cost
+        );
+    }
+
+    #[test]
+    fn receipt_fee_is_gas_used_at_the_effective_price() {
+        let receipt: Receipt = serde_json::from_value(serde_json::json!({
+            "status": "0x1",
+            "blockNumber": "0x10",
+            "gasUsed": "0x186a0",
+            "effectiveGasPrice": "0x5f5e100"
+        }))
+        .unwrap();
+        // 100_000 gas at 0.1 gwei
+        assert!(
+            (receipt.fee() - 0.000_01).abs() < 1e-15,
+            "{}",
+            receipt.fee()
+        );
+    }
+}
@@ -0,0 +1,754 @@
+use alloy::primitives::{address, Address, Bytes, U256};
+use alloy::sol_types::SolCall;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::chain::{self, ChainSettings, RpcClient};
+use crate::error::{AppError, AppResult};
+
+use artifacts::{
+    MonadierTradingVault as V1, MonadierTradingVaultV10 as V10, MonadierTradingVaultV2 as V2,
+    MonadierTradingVaultV3 as V3, MonadierTradingVaultV5 as V5, MonadierTradingVaultV6 as V6,
+    MonadierTradingVaultV7 as V7, MonadierTradingVaultV8 as V8, MonadierTradingVaultV9 as V9,
+};
+
+const BASE_CHAIN_ID: u64 = 8453;
+
+// Hardhat artifacts from contracts/artifacts, compiled in. Paths are
+// relative to src-tauri. The lints fire on macro output.
+#[allow(clippy::too_many_arguments)]
+mod artifacts {
+    use alloy::sol;
+
+    sol!(
+        MonadierTradingVault,
+        "../contracts/artifacts/contracts/MonadierTradingVault.sol/MonadierTradingVault.json"
+    );
+    sol!(
+        MonadierTradingVaultV2,
+        "../contracts/artifacts/contracts/MonadierTradingVaultV2.sol/MonadierTradingVaultV2.json"
+    );
+    sol!(
+        MonadierTradingVaultV3,
+        "../contracts/artifacts/contracts/MonadierTradingVaultV3.sol/MonadierTradingVaultV3.json"
+    );
+    sol!(
+        MonadierTradingVaultV5,
+        "../contracts/artifacts/contracts/MonadierTradingVaultV5.sol/MonadierTradingVaultV5.json"
+    );
+    sol!(
+        MonadierTradingVaultV6,
+        "../contracts/artifacts/contracts/MonadierTradingVaultV6.sol/MonadierTradingVaultV6.json"
+    );
+    sol!(
+        MonadierTradingVaultV7,
+        "../contracts/artifacts/contracts/MonadierTradingVaultV7.sol/MonadierTradingVaultV7.json"
+    );
+    sol!(
+        MonadierTradingVaultV8,
+        "../contracts/artifacts/contracts/MonadierTradingVaultV8.sol/MonadierTradingVaultV8.json"
+    );
+    sol!(
+        MonadierTradingVaultV9,
+        "../contracts/artifacts/contracts/MonadierTradingVaultV9.sol/MonadierTradingVaultV9.json"
+    );
+    sol!(
+        MonadierTradingVaultV10,
+        "../contracts/artifacts/contracts/MonadierTradingVaultV10.sol/MonadierTradingVaultV10.json"
+    );
+}
+
+// Contract generations. V4 was never built, V11 has no artifact and keeps
+// the V10 user functions.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VaultVersion {
+    V1,
+    V2,
+    V3,
+    V5,
+    V6,
+    V7,
+    V8,
+    V9,
+    V10,
+    V11,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct VaultDeployment {
+    pub chain_id: u64,
+    pub address: Address,
+    pub version: VaultVersion,
+    pub label: &'static str,
+}
+
+// contracts/deployments, LegacyVaultWithdraw.tsx and check_all_vaults.ts.
+// The V1 Base vault and V6 share an address, same deployer and nonce.
+pub const DEPLOYMENTS: &[VaultDeployment] = &[
+    VaultDeployment {
+        chain_id: BASE_CHAIN_ID,
+        address: address!("ceD685CDbcF9056CdbD0F37fFE9Cd8152851D13A"),
+        version: VaultVersion::V1,
+        label: "V1 Base",
+    },
+    VaultDeployment {
+        chain_id: BASE_CHAIN_ID,
+        address: address!("5eF29B4348d31c311918438e92a5fae7641Bc00a"),
+        version: VaultVersion::V2,
+        label: "V2 Base",
+    },
+    VaultDeployment {
+        chain_id: BASE_CHAIN_ID,
+        address: address!("08Afb514255187d664d6b250D699Edc51491E803"),
+        version: VaultVersion::V3,
+        label: "V3 Base",
+    },
+    VaultDeployment {
+        chain_id: chain::ARBITRUM_CHAIN_ID,
+        address: address!("6C51F75b164205e51a87038662060cfe54d95E70"),
+        version: VaultVersion::V5,
+        label: "V5",
+    },
+    VaultDeployment {
+        chain_id: chain::ARBITRUM_CHAIN_ID,
+        address: address!("ceD685CDbcF9056CdbD0F37fFE9Cd8152851D13A"),
+        version: VaultVersion::V6,
+        label: "V6",
+    },
+    VaultDeployment {
+        chain_id: chain::ARBITRUM_CHAIN_ID,
+        address: address!("712B3A0cFD00674a15c5D235e998F71709112675"),
+        version: VaultVersion::V7,
+        label: "V7 Original",
+    },
+    VaultDeployment {
+        chain_id: chain::ARBITRUM_CHAIN_ID,
+        address: address!("9879792a47725d5b18633e1395BC4a7A06c750df"),
+        version: VaultVersion::V7,
+        label: "V7",
+    },
+    VaultDeployment {
+        chain_id: chain::ARBITRUM_CHAIN_ID,
+        address: address!("FA38c191134A6a3382794BE6144D24c3e6D8a4C3"),
+        version: VaultVersion::V8,
+        label: "V8 Legacy",
+    },
+    VaultDeployment {
+        chain_id: chain::ARBITRUM_CHAIN_ID,
+        address: address!("9020bD5Ff2eD31a05dd5B48E92624A5a0E952bf6"),
+        version: VaultVersion::V8,
+        label: "V8",
+    },
+    VaultDeployment {
+        chain_id: chain::ARBITRUM_CHAIN_ID,
+        address: address!("4F86688216D560456a594d0C4b8E279dAd464D70"),
+        version: VaultVersion::V8,
+        label: "V8.3",
+    },
+    VaultDeployment {
+        chain_id: chain::ARBITRUM_CHAIN_ID,
+        address: chain::VAULT_ADDRESS,
+        version: VaultVersion::V11,
+        label: "Current",
+    },
+];
+
+pub fn lookup(chain_id: u64, address: Address) -> Option<&'static VaultDeployment> {
+    DEPLOYMENTS
+        .iter()
+        .find(|d| d.chain_id == chain_id && d.address == address)
+}
+
+// Version of a vault on the configured chain, user-added legacy vaults
+// first
+pub fn version_of(settings: &ChainSettings, address: Address) -> AppResult<VaultVersion> {
+    settings
+        .legacy_vaults
+        .iter()
+        .find(|v| v.address == address)
+        .map(|v| v.version)
+        .or_else(|| lookup(settings.chain_id, address).map(|d| d.version))
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "No known vault at {} on chain {}",
+                address, settings.chain_id
+            ))
+        })
+}
+
+pub type VaultFuture<'a, T> = Pin<Box<dyn Future<Output = AppResult<T>> + Send + 'a>>;
+
+// What the app needs from any vault generation. Reads go through the
+// caller's RpcClient, writes come back as calldata for tx::send_data and
+// are None where the generation lacks the function.
+pub trait VaultClient: Send + Sync {
+    // USDC credited to the user
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256>;
+
+    // What a withdraw pays out now. Before V8 the vault cannot tell and a
+    // withdraw simply reverts when it is short.
+    fn withdrawable<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        self.balance(rpc, user)
+    }
+
+    // Tokens the user still has a position or spot balance in
+    fn open_positions<'a>(
+        &'a self,
+        _rpc: &'a RpcClient<'_>,
+        _user: Address,
+        _tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes;
+
+    fn emergency_withdraw(&self) -> Option<Bytes> {
+        None
+    }
+
+    fn reconcile(&self, _user: Address, _token: Address) -> Option<Bytes> {
+        None
+    }
+
+    // V8 and later, the user may cancel a position GMX never executed once
+    // it timed out
+    fn cancel_stuck_position(&self, _user: Address, _token: Address) -> Option<Bytes> {
+        None
+    }
+
+    // V3 and V5 let the user sell a spot position back to USDC
+    fn emergency_close(&self, _token: Address) -> Option<Bytes> {
+        None
+    }
+}
+
+pub fn client(version: VaultVersion, address: Address) -> Box<dyn VaultClient> {
+    match version {
+        VaultVersion::V1 => Box::new(VaultV1(address)),
+        VaultVersion::V2 => Box::new(VaultV2(address)),
+        VaultVersion::V3 => Box::new(VaultV3(address)),
+        VaultVersion::V5 => Box::new(VaultV5(address)),
+        VaultVersion::V6 => Box::new(VaultV6(address)),
+        VaultVersion::V7 => Box::new(VaultV7(address)),
+        VaultVersion::V8 => Box::new(VaultV8(address)),
+        VaultVersion::V9 => Box::new(VaultV9(address)),
+        VaultVersion::V10 | VaultVersion::V11 => Box::new(VaultV10(address)),
+    }
+}
+
+fn read<'a, C>(rpc: &'a RpcClient<'_>, vault: Address, call: C) -> VaultFuture<'a, C::Return>
+where
+    C: SolCall + Send + 'a,
+    C::Return: Send,
+{
+    Box::pin(rpc.call(vault, call))
+}
+
+fn encode(call: impl SolCall) -> Bytes {
+    call.abi_encode().into()
+}
+
+// Uniswap V2 spot vault on Base
+struct VaultV1(Address);
+
+impl VaultClient for VaultV1 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V1::balancesCall(user))
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V1::withdrawCall { amount })
+    }
+}
+
+// V1 with per-user token balances
+struct VaultV2(Address);
+
+impl VaultClient for VaultV2 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V2::balancesCall(user))
+    }
+
+    fn open_positions<'a>(
+        &'a self,
+        rpc: &'a RpcClient<'_>,
+        user: Address,
+        tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async move {
+            let mut open = Vec::new();
+            for &token in tokens {
+                let held = rpc
+                    .call(self.0, V2::getTokenBalanceCall { user, token })
+                    .await?;
+                if !held.is_zero() {
+                    open.push(token);
+                }
+            }
+            Ok(open)
+        })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V2::withdrawCall { amount })
+    }
+}
+
+// Adds the user-side emergency close
+struct VaultV3(Address);
+
+impl VaultClient for VaultV3 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V3::balancesCall(user))
+    }
+
+    fn open_positions<'a>(
+        &'a self,
+        rpc: &'a RpcClient<'_>,
+        user: Address,
+        tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async move {
+            let amounts = rpc
+                .call(
+                    self.0,
+                    V3::getUserPositionsCall {
+                        user,
+                        tokens: tokens.to_vec(),
+                    },
+                )
+                .await?;
+            Ok(tokens
+                .iter()
+                .zip(amounts)
+                .filter(|(_, amount)| !amount.is_zero())
+                .map(|(token, _)| *token)
+                .collect())
+        })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V3::withdrawCall { amount })
+    }
+
+    fn emergency_close(&self, token: Address) -> Option<Bytes> {
+        Some(encode(V3::emergencyClosePositionCall { token }))
+    }
+}
+
+// Uniswap V3 on Arbitrum
+struct VaultV5(Address);
+
+impl VaultClient for VaultV5 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V5::balancesCall(user))
+    }
+
+    fn open_positions<'a>(
+        &'a self,
+        rpc: &'a RpcClient<'_>,
+        user: Address,
+        tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async move {
+            let mut open = Vec::new();
+            for &token in tokens {
+                let held = rpc
+                    .call(self.0, V5::getTokenBalanceCall { user, token })
+                    .await?;
+                if !held.is_zero() {
+                    open.push(token);
+                }
+            }
+            Ok(open)
+        })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V5::withdrawCall { amount })
+    }
+
+    fn emergency_close(&self, token: Address) -> Option<Bytes> {
+        Some(encode(V5::emergencyClosePositionCall { token }))
+    }
+}
+
+// Aave leveraged longs and shorts, closed by the bot only
+struct VaultV6(Address);
+
+impl VaultClient for VaultV6 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V6::balancesCall(user))
+    }
+
+    fn open_positions<'a>(
+        &'a self,
+        rpc: &'a RpcClient<'_>,
+        user: Address,
+        tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async move {
+            let mut open = Vec::new();
+            for &token in tokens {
+                if rpc
+                    .call(self.0, V6::hasOpenPositionCall { user, token })
+                    .await?
+                {
+                    open.push(token);
+                }
+            }
+            Ok(open)
+        })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V6::withdrawCall { amount })
+    }
+}
+
+// First GMX vault
+struct VaultV7(Address);
+
+impl VaultClient for VaultV7 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V7::balancesCall(user))
+    }
+
+    fn open_positions<'a>(
+        &'a self,
+        rpc: &'a RpcClient<'_>,
+        user: Address,
+        tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async move {
+            let mut open = Vec::new();
+            for &token in tokens {
+                let position = rpc
+                    .call(
+                        self.0,
+                        V7::getPositionCall {
+                            user,
+                            indexToken: token,
+                        },
+                    )
+                    .await?;
+                if position.isActive {
+                    open.push(token);
+                }
+            }
+            Ok(open)
+        })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V7::withdrawCall { amount })
+    }
+}
+
+// Adds getWithdrawable and cancelStuckPosition
+struct VaultV8(Address);
+
+impl VaultClient for VaultV8 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V8::balancesCall(user))
+    }
+
+    fn withdrawable<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V8::getWithdrawableCall { user })
+    }
+
+    fn open_positions<'a>(
+        &'a self,
+        rpc: &'a RpcClient<'_>,
+        user: Address,
+        tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async move {
+            let mut open = Vec::new();
+            for &token in tokens {
+                let position = rpc
+                    .call(self.0, V8::getPositionCall { user, token })
+                    .await?;
+                if position.isActive {
+                    open.push(token);
+                }
+            }
+            Ok(open)
+        })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V8::withdrawCall { amount })
+    }
+
+    fn cancel_stuck_position(&self, user: Address, token: Address) -> Option<Bytes> {
+        Some(encode(V8::cancelStuckPositionCall { user, token }))
+    }
+}
+
+// Adds emergencyWithdraw and reconcile
+struct VaultV9(Address);
+
+impl VaultClient for VaultV9 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V9::balancesCall(user))
+    }
+
+    fn withdrawable<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V9::getWithdrawableCall { user })
+    }
+
+    fn open_positions<'a>(
+        &'a self,
+        rpc: &'a RpcClient<'_>,
+        user: Address,
+        tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async move {
+            let mut open = Vec::new();
+            for &token in tokens {
+                let position = rpc
+                    .call(self.0, V9::getPositionCall { user, token })
+                    .await?;
+                if position.isActive {
+                    open.push(token);
+                }
+            }
+            Ok(open)
+        })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V9::withdrawCall { amount })
+    }
+
+    fn emergency_withdraw(&self) -> Option<Bytes> {
+        Some(encode(V9::emergencyWithdrawCall {}))
+    }
+
+    fn reconcile(&self, user: Address, token: Address) -> Option<Bytes> {
+        Some(encode(V9::reconcileCall { user, token }))
+    }
+
+    fn cancel_stuck_position(&self, user: Address, token: Address) -> Option<Bytes> {
+        Some(encode(V9::cancelStuckPositionCall { user, token }))
+    }
+}
+
+// V9 with a new bot wallet, also serves V11
+struct VaultV10(Address);
+
+impl VaultClient for VaultV10 {
+    fn balance<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V10::balancesCall(user))
+    }
+
+    fn withdrawable<'a>(&'a self, rpc: &'a RpcClient<'_>, user: Address) -> VaultFuture<'a, U256> {
+        read(rpc, self.0, V10::getWithdrawableCall { user })
+    }
+
+    fn open_positions<'a>(
+        &'a self,
+        rpc: &'a RpcClient<'_>,
+        user: Address,
+        tokens: &'a [Address],
+    ) -> VaultFuture<'a, Vec<Address>> {
+        Box::pin(async move {
+            let mut open = Vec::new();
+            for &token in tokens {
+                let position = rpc
+                    .call(self.0, V10::getPositionCall { user, token })
+                    .await?;
+                if position.isActive {
+                    open.push(token);
+                }
+            }
+            Ok(open)
+        })
+    }
+
+    fn withdraw(&self, amount: U256) -> Bytes {
+        encode(V10::withdrawCall { amount })
+    }
+
+    fn emergency_withdraw(&self) -> Option<Bytes> {
+        Some(encode(V10::emergencyWithdrawCall {}))
+    }
+
+    fn reconcile(&self, user: Address, token: Address) -> Option<Bytes> {
+        Some(encode(V10::reconcileCall { user, token }))
+    }
+
+    fn cancel_stuck_position(&self, user: Address, token: Address) -> Option<Bytes> {
+        Some(encode(V10::cancelStuckPositionCall { user, token }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::keccak256;
+    use chain::LegacyVault;
+
+    const USER: Address = Address::repeat_byte(0x11);
+    const TOKEN: Address = Address::repeat_byte(0x22);
+
+    // Selector from the Solidity signature, independent of the artifacts
+    fn selector(signature: &str) -> [u8; 4] {
+        keccak256(signature)[..4].try_into().unwrap()
+    }
+
+    fn calls(data: Option<Bytes>, signature: &str, args: &[[u8; 32]]) {
+        let data = data.unwrap_or_else(|| panic!("no {} calldata", signature));
+        assert_eq!(data[..4], selector(signature), "{}", signature);
+        assert_eq!(data[4..], args.concat()[..], "{} arguments", signature);
+    }
+
+    fn word(address: Address) -> [u8; 32] {
+        address.into_word().0
+    }
+
+    #[test]
+    fn looks_up_every_deployment_on_its_chain() {
+        for deployment in DEPLOYMENTS {
+            let found = lookup(deployment.chain_id, deployment.address).unwrap();
+            assert_eq!(found.version, deployment.version, "{}", deployment.label);
+            assert_eq!(found.label, deployment.label);
+        }
+        assert!(lookup(1, chain::VAULT_ADDRESS).is_none());
+        assert!(lookup(chain::ARBITRUM_CHAIN_ID, USER).is_none());
+    }
+
+    #[test]
+    fn shared_address_resolves_per_chain() {
+        let shared = address!("ceD685CDbcF9056CdbD0F37fFE9Cd8152851D13A");
+        assert_eq!(
+            lookup(BASE_CHAIN_ID, shared).unwrap().version,
+            VaultVersion::V1
+        );
+        assert_eq!(
+            lookup(chain::ARBITRUM_CHAIN_ID, shared).unwrap().version,
+            VaultVersion::V6
+        );
+    }
+
+    #[test]
+    fn versions_every_deployment_on_the_configured_chain() {
+        for deployment in DEPLOYMENTS {
+            // The default legacy vaults are the Arbitrum ones
+            let settings = if deployment.chain_id == chain::ARBITRUM_CHAIN_ID {
+                ChainSettings::default()
+            } else {
+                ChainSettings {
+                    chain_id: deployment.chain_id,
+                    legacy_vaults: Vec::new(),
+                    ..ChainSettings::default()
+                }
+            };
+            assert_eq!(
+                version_of(&settings, deployment.address).unwrap(),
+                deployment.version,
+                "{}",
+                deployment.label
+            );
+        }
+
+        let err = version_of(&ChainSettings::default(), USER).unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)));
+    }
+
+    #[test]
+    fn user_added_vaults_come_first() {
+        let settings = ChainSettings {
+            legacy_vaults: vec![LegacyVault {
+                label: "Fork".to_string(),
+                address: chain::VAULT_ADDRESS,
+                version: VaultVersion::V9,
+            }],
+            ..ChainSettings::default()
+        };
+        assert_eq!(
+            version_of(&settings, chain::VAULT_ADDRESS).unwrap(),
+            VaultVersion::V9
+        );
+    }
+
+    #[test]
+    fn every_version_encodes_withdraw() {
+        let amount = U256::from(25_000_000u64);
+        let versions = [
+            VaultVersion::V1,
+            VaultVersion::V2,
+            VaultVersion::V3,
+            VaultVersion::V5,
+            VaultVersion::V6,
+            VaultVersion::V7,
+            VaultVersion::V8,
+            VaultVersion::V9,
+            VaultVersion::V10,
+            VaultVersion::V11,
+        ];
+        for version in versions {
+            let vault = client(version, Address::ZERO);
+            calls(
+                Some(vault.withdraw(amount)),
+                "withdraw(uint256)",
+                &[amount.to_be_bytes()],
+            );
+        }
+    }
+
+    #[test]
+    fn encodes_recovery_calls_where_the_version_has_them() {
+        let pair = [word(USER), word(TOKEN)];
+        for version in [VaultVersion::V9, VaultVersion::V10, VaultVersion::V11] {
+            let vault = client(version, Address::ZERO);
+            calls(vault.emergency_withdraw(), "emergencyWithdraw()", &[]);
+            calls(
+                vault.reconcile(USER, TOKEN),
+                "reconcile(address,address)",
+                &pair,
+            );
+            calls(
+                vault.cancel_stuck_position(USER, TOKEN),
+                "cancelStuckPosition(address,address)",
+                &pair,
+            );
+            assert!(vault.emergency_close(TOKEN).is_none());
+        }
+
+        let v8 = client(VaultVersion::V8, Address::ZERO);
+        calls(
+            v8.cancel_stuck_position(USER, TOKEN),
+            "cancelStuckPosition(address,address)",
+            &pair,
+        );
+        assert!(v8.emergency_withdraw().is_none());
+        assert!(v8.reconcile(USER, TOKEN).is_none());
+
+        for version in [VaultVersion::V3, VaultVersion::V5] {
+            let vault = client(version, Address::ZERO);
+            calls(
+                vault.emergency_close(TOKEN),
+                "emergencyClosePosition(address)",
+                &[word(TOKEN)],
+            );
+            assert!(vault.cancel_stuck_position(USER, TOKEN).is_none());
+        }
+
+        for version in [
+            VaultVersion::V1,
+            VaultVersion::V2,
+            VaultVersion::V6,
+            VaultVersion::V7,
+        ] {
+            let vault = client(version, Address::ZERO);
+            assert!(vault.emergency_withdraw().is_none());
+            assert!(vault.reconcile(USER, TOKEN).is_none());
+            assert!(vault.cancel_stuck_position(USER, TOKEN).is_none());
+            assert!(vault.emergency_close(TOKEN).is_none());
+        }
+    }
+}
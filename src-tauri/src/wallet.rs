@@ -0,0 +1,327 @@
+use alloy::consensus::{SignableTransaction, TxEip1559, TxEnvelope};
+use alloy::dyn_abi::TypedData;
+use alloy::eips::eip2718::Encodable2718;
+use alloy::primitives::{Address, Bytes, TxKind, U256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::error::{AppError, AppResult};
+
+const KEYSTORE_DIR: &str = "keystore";
+const MIN_PASSWORD_LEN: usize = 8;
+
+// Unlocked signer, the private key never leaves this process
+#[derive(Default)]
+pub struct WalletState(Mutex<Option<PrivateKeySigner>>);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WalletInfo {
+    pub address: String,
+    pub unlocked: bool,
+}
+
+// EIP-1559 transaction as sent from the webview
+#[derive(Debug, Deserialize, Clone)]
+pub struct UnsignedTransaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub value: U256,
+    #[serde(default)]
+    pub data: Bytes,
+    pub gas_limit: u64,
+    #[serde(with = "alloy::serde::quantity")]
+    pub max_fee_per_gas: u128,
+    #[serde(with = "alloy::serde::quantity")]
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl UnsignedTransaction {
+    pub fn into_eip1559(self) -> TxEip1559 {
+        TxEip1559 {
+            chain_id: self.chain_id,
+            nonce: self.nonce,
+            gas_limit: self.gas_limit,
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            to: self.to.map(TxKind::Call).unwrap_or(TxKind::Create),
+            value: self.value,
+            input: self.data,
+            ..Default::default()
+        }
+    }
+}
+
+fn keystore_dir(app: &tauri::AppHandle) -> AppResult<PathBuf> {
+    let dir = app.path().app_data_dir()?.join(KEYSTORE_DIR);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+// One V3 keystore file per wallet, named after the lowercase address
+fn keystore_file_name(address: &Address) -> String {
+    format!("{}.json", address.to_string().to_lowercase())
+}
+
+// Hidden from list(), which only takes address-named files
+fn temp_path(dir: &Path, purpose: &str) -> PathBuf {
+    dir.join(format!(".{}-{:016x}.tmp", purpose, rand::random::<u64>()))
+}
+
+// Encrypt into a temp file and rename it into place, so a crash never
+// leaves a half-written keystore under a wallet's name
+fn write_keystore(
+    dir: &Path,
+    signer: &PrivateKeySigner,
+    password: &str,
+    name: &str,
+) -> AppResult<()> {
+    let temp = temp_path(dir, "keystore");
+    let temp_name = temp
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    PrivateKeySigner::encrypt_keystore(
+        dir,
+        &mut rand::thread_rng(),
+        signer.to_bytes(),
+        password,
+        Some(temp_name),
+    )
+    .map_err(|e| AppError::Storage(format!("failed to write keystore: {}", e)))?;
+
+    if let Err(e) = std::fs::rename(&temp, dir.join(name)) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+// Decrypt a V3 keystore given as JSON. The crate only reads keystores from
+// disk, so it goes through a temp file of its own.
+fn decrypt_json(dir: &Path, keystore_json: &str, password: &str) -> AppResult<PrivateKeySigner> {
+    let staging = temp_path(dir, "import");
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&staging)
+        .and_then(|mut file| file.write_all(keystore_json.as_bytes()))?;
+
+    let decrypted = PrivateKeySigner::decrypt_keystore(&staging, password);
+    let _ = std::fs::remove_file(&staging);
+    decrypted.map_err(|_| AppError::WrongPassword)
+}
+
+fn check_password(password: &str) -> AppResult<()> {
+    if password.len() < MIN_PASSWORD_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn parse_address(address: &str) -> AppResult<Address> {
+    address
+        .parse::<Address>()
+        .map_err(|_| AppError::InvalidInput(format!("Invalid address: {}", address)))
+}
+
+// Encrypt a key into the keystore dir and make it the unlocked wallet
+fn store_signer(
+    app: &tauri::AppHandle,
+    signer: PrivateKeySigner,
+    password: &str,
+) -> AppResult<WalletInfo> {
+    check_password(password)?;
+
+    let dir = keystore_dir(app)?;
+    let name = keystore_file_name(&signer.address());
+    // Re-importing under a new password would lock out the old backup
+    if dir.join(&name).exists() {
+        return Err(AppError::InvalidInput(format!(
+            "A wallet for {} already exists, unlock it instead",
+            signer.address()
+        )));
+    }
+    write_keystore(&dir, &signer, password, &name)?;
+
+    let info = WalletInfo {
+        address: signer.address().to_string(),
+        unlocked: true,
+    };
+    *app.state::<WalletState>().0.lock().unwrap() = Some(signer);
+    Ok(info)
+}
+
+pub fn create(app: &tauri::AppHandle, password: &str) -> AppResult<WalletInfo> {
+    store_signer(app, PrivateKeySigner::random(), password)
+}
+
+pub fn import_private_key(
+    app: &tauri::AppHandle,
+    private_key: &str,
+    password: &str,
+) -> AppResult<WalletInfo> {
+    let signer = private_key
+        .trim()
+        .parse::<PrivateKeySigner>()
+        .map_err(|_| AppError::InvalidInput("Invalid private key".to_string()))?;
+    store_signer(app, signer, password)
+}
+
+// Import an existing V3 keystore JSON, re-encrypting it under our naming
+pub fn import_keystore(
+    app: &tauri::AppHandle,
+    keystore_json: &str,
+    password: &str,
+) -> AppResult<WalletInfo> {
+    let signer = decrypt_json(&keystore_dir(app)?, keystore_json, password)?;
+    store_signer(app, signer, password)
+}
+
+pub fn list(app: &tauri::AppHandle) -> AppResult<Vec<WalletInfo>> {
+    let unlocked = unlocked_address(app);
+
+    let mut wallets = Vec::new();
+    for entry in std::fs::read_dir(keystore_dir(app)?)? {
+        let path = entry?.path();
+        let address = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<Address>().ok());
+
+        if let Some(address) = address {
+            wallets.push(WalletInfo {
+                address: address.to_string(),
+                unlocked: unlocked == Some(address),
+            });
+        }
+    }
+
+    Ok(wallets)
+}
+
+pub fn unlock(app: &tauri::AppHandle, address: &str, password: &str) -> AppResult<WalletInfo> {
+    let address = parse_address(address)?;
+    let path = keystore_dir(app)?.join(keystore_file_name(&address));
+    if !path.exists() {
+        return Err(AppError::NotFound(format!("keystore for {}", address)));
+    }
+
+    let signer =
+        PrivateKeySigner::decrypt_keystore(&path, password).map_err(|_| AppError::WrongPassword)?;
+
+    *app.state::<WalletState>().0.lock().unwrap() = Some(signer);
+    Ok(WalletInfo {
+        address: address.to_string(),
+        unlocked: true,
+    })
+}
+
+pub fn lock(app: &tauri::AppHandle) {
+    *app.state::<WalletState>().0.lock().unwrap() = None;
+}
+
+pub fn unlocked_address(app: &tauri::AppHandle) -> Option<Address> {
+    app.state::<WalletState>()
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|signer| signer.address())
+}
+
+// Encrypted V3 keystore JSON, safe to back up
+pub fn export(app: &tauri::AppHandle, address: &str) -> AppResult<String> {
+    let address = parse_address(address)?;
+    let path = keystore_dir(app)?.join(keystore_file_name(&address));
+    std::fs::read_to_string(path)
+        .map_err(|_| AppError::NotFound(format!("keystore for {}", address)))
+}
+
+// Run `f` with the unlocked signer
+fn with_signer<T>(
+    app: &tauri::AppHandle,
+    f: impl FnOnce(&PrivateKeySigner) -> AppResult<T>,
+) -> AppResult<T> {
+    let state = app.state::<WalletState>();
+    let guard = state.0.lock().unwrap();
+    match guard.as_ref() {
+        Some(signer) => f(signer),
+        None => Err(AppError::WalletLocked),
+    }
+}
+
+// EIP-191 personal_sign
+pub fn sign_message(app: &tauri::AppHandle, message: &str) -> AppResult<String> {
+    with_signer(app, |signer| {
+        let signature = signer
+            .sign_message_sync(message.as_bytes())
+            .map_err(|e| AppError::Crypto(e.to_string()))?;
+        Ok(alloy::hex::encode_prefixed(signature.as_bytes()))
+    })
+}
+
+// EIP-712 eth_signTypedData_v4 payload
+pub fn sign_typed_data(app: &tauri::AppHandle, typed_data: &TypedData) -> AppResult<String> {
+    with_signer(app, |signer| {
+        let signature = signer
+            .sign_dynamic_typed_data_sync(typed_data)
+            .map_err(|e| AppError::Crypto(e.to_string()))?;
+        Ok(alloy::hex::encode_prefixed(signature.as_bytes()))
+    })
+}
+
+// Signed EIP-2718 envelope, ready for eth_sendRawTransaction
+pub fn sign_transaction(app: &tauri::AppHandle, tx: UnsignedTransaction) -> AppResult<String> {
+    let tx = tx.into_eip1559();
+    with_signer(app, move |signer| {
+        let signature = signer
+            .sign_hash_sync(&tx.signature_hash())
+            .map_err(|e| AppError::Crypto(e.to_string()))?;
+        let envelope = TxEnvelope::from(tx.into_signed(signature));
+        Ok(alloy::hex::encode_prefixed(envelope.encoded_2718()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wallet-test-{:016x}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn keystores_round_trip_without_leftovers() {
+        let dir = scratch_dir();
+        let signer = PrivateKeySigner::random();
+        let name = keystore_file_name(&signer.address());
+        write_keystore(&dir, &signer, "correct horse", &name).unwrap();
+
+        let json = std::fs::read_to_string(dir.join(&name)).unwrap();
+        let imported = decrypt_json(&dir, &json, "correct horse").unwrap();
+        assert_eq!(imported.address(), signer.address());
+        assert!(matches!(
+            decrypt_json(&dir, &json, "wrong horse"),
+            Err(AppError::WrongPassword)
+        ));
+
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, [std::ffi::OsString::from(name)]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,11 +1,23 @@
 import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 export interface StoredLicense {
   code: string;
   plan_tier: string;
   activated_at: string;
   machine_id: string;
+  issued_at: string;
+  expires_at: string;
+  signature: string;
+  last_validated_at?: string;
+}
+
+// Payload of license://expiring and license://revoked
+export interface LicenseEvent {
+  reason: string;
+  expires_at?: string;
+  grace_ends_at?: string;
 }
 
 export interface LicenseValidation {
@@ -14,6 +26,22 @@ export interface LicenseValidation {
   error?: string;
 }
 
+// Error returned by every desktop command (AppError in src-tauri/src/error.rs)
+export interface DesktopError {
+  code: string;
+  message: string;
+  retryable: boolean;
+}
+
+export function isDesktopError(err: unknown): err is DesktopError {
+  return typeof err === 'object' && err !== null && 'code' in err && 'message' in err;
+}
+
+function errorMessage(err: unknown, fallback: string): string {
+  if (isDesktopError(err) || err instanceof Error) return err.message;
+  return fallback;
+}
+
 // Check if running in Tauri (desktop app)
 export function isDesktopApp(): boolean {
   return typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
@@ -24,6 +52,7 @@ export function useDesktopLicense() {
   const [license, setLicense] = useState<StoredLicense | null>(null);
   const [isLoading, setIsLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
+  const [expiring, setExpiring] = useState<LicenseEvent | null>(null);
 
   // Check if we're in desktop mode and get stored license
   useEffect(() => {
@@ -46,6 +75,25 @@ export function useDesktopLicense() {
     checkDesktop();
   }, []);
 
+  // Background re-validation results from the Rust license monitor
+  useEffect(() => {
+    if (!isDesktop) return;
+
+    const unlistenExpiring = listen<LicenseEvent>('license://expiring', (event) => {
+      setExpiring(event.payload);
+    });
+    const unlistenRevoked = listen<LicenseEvent>('license://revoked', (event) => {
+      setLicense(null);
+      setExpiring(null);
+      setError(event.payload.reason);
+    });
+
+    return () => {
+      unlistenExpiring.then((unlisten) => unlisten());
+      unlistenRevoked.then((unlisten) => unlisten());
+    };
+  }, [isDesktop]);
+
   // Get machine ID
   const getMachineId = useCallback(async (): Promise<string | null> => {
     if (!isDesktop) return null;
@@ -61,7 +109,7 @@ export function useDesktopLicense() {
   // Validate and activate a license code
   const validateLicense = useCallback(async (
     licenseCode: string,
-    supabaseUrl: string
+    supabaseUrl?: string
   ): Promise<LicenseValidation> => {
     if (!isDesktop) {
       return { valid: false, error: 'Not running in desktop mode' };
@@ -86,9 +134,9 @@ export function useDesktopLicense() {
 
       return result;
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : 'Validation failed';
-      setError(errorMessage);
-      return { valid: false, error: errorMessage };
+      const message = errorMessage(err, 'Validation failed');
+      setError(message);
+      return { valid: false, error: message };
     } finally {
       setIsLoading(false);
     }
@@ -106,14 +154,49 @@ export function useDesktopLicense() {
     }
   }, [isDesktop]);
 
+  // Release the machine binding server-side so the license can move
+  const deactivateLicense = useCallback(async (): Promise<boolean> => {
+    if (!isDesktop) return false;
+
+    try {
+      await invoke('deactivate_license');
+      setLicense(null);
+      return true;
+    } catch (err) {
+      setError(errorMessage(err, 'Deactivation failed'));
+      return false;
+    }
+  }, [isDesktop]);
+
+  // Release and re-activate for this machine's current hardware identity
+  const transferLicense = useCallback(async (): Promise<LicenseValidation> => {
+    if (!isDesktop) {
+      return { valid: false, error: 'Not running in desktop mode' };
+    }
+
+    try {
+      const result = await invoke<LicenseValidation>('transfer_license');
+      const storedLicense = await invoke<StoredLicense | null>('get_stored_license');
+      setLicense(storedLicense);
+      return result;
+    } catch (err) {
+      const message = errorMessage(err, 'Transfer failed');
+      setError(message);
+      return { valid: false, error: message };
+    }
+  }, [isDesktop]);
+
   return {
     isDesktop,
     isLicensed: !!license,
     license,
+    expiring,
     isLoading,
     error,
     getMachineId,
     validateLicense,
     clearLicense,
+    deactivateLicense,
+    transferLicense,
   };
 }
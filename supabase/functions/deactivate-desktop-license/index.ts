@@ -0,0 +1,88 @@
+import { serve } from 'https://deno.land/std@0.177.0/http/server.ts';
+import { createClient } from 'https://esm.sh/@supabase/supabase-js@2';
+import nacl from 'https://esm.sh/tweetnacl@1.0.3';
+import { decode as decodeBase64 } from 'https://deno.land/std@0.177.0/encoding/base64.ts';
+
+const corsHeaders = {
+  'Access-Control-Allow-Origin': '*',
+  'Access-Control-Allow-Headers': 'authorization, x-client-info, apikey, content-type',
+};
+
+// Must match DEACTIVATE_CONTEXT in src-tauri/src/license.rs
+const DEACTIVATE_CONTEXT = 'monadier-desktop-deactivate-v1';
+
+// Reject signed requests older than this to limit replay
+const MAX_REQUEST_AGE_MS = 5 * 60 * 1000;
+
+function jsonResponse(body: Record<string, unknown>, status: number) {
+  return new Response(JSON.stringify(body), {
+    status,
+    headers: { ...corsHeaders, 'Content-Type': 'application/json' },
+  });
+}
+
+serve(async (req) => {
+  // Handle CORS preflight
+  if (req.method === 'OPTIONS') {
+    return new Response('ok', { headers: corsHeaders });
+  }
+
+  try {
+    const supabaseUrl = Deno.env.get('SUPABASE_URL')!;
+    const supabaseServiceKey = Deno.env.get('SUPABASE_SERVICE_ROLE_KEY')!;
+
+    const supabase = createClient(supabaseUrl, supabaseServiceKey);
+
+    const { licenseCode, machineId, timestamp, signature } = await req.json();
+
+    if (!licenseCode || !machineId || !timestamp || !signature) {
+      return jsonResponse({ success: false, error: 'Missing required fields' }, 400);
+    }
+
+    const requestAge = Date.now() - new Date(timestamp).getTime();
+    if (!(requestAge >= -MAX_REQUEST_AGE_MS && requestAge <= MAX_REQUEST_AGE_MS)) {
+      return jsonResponse({ success: false, error: 'Request expired' }, 400);
+    }
+
+    const { data: license, error: licenseError } = await supabase
+      .from('licenses')
+      .select('*')
+      .eq('code', licenseCode.toUpperCase())
+      .single();
+
+    if (licenseError || !license) {
+      return jsonResponse({ success: false, error: 'Invalid license code' }, 404);
+    }
+
+    if (license.machine_id !== machineId || !license.device_public_key) {
+      return jsonResponse({ success: false, error: 'License is not bound to this machine' }, 403);
+    }
+
+    // Proof that the request comes from the install that activated the license
+    const message = [DEACTIVATE_CONTEXT, licenseCode, machineId, timestamp].join('\n');
+    const verified = nacl.sign.detached.verify(
+      new TextEncoder().encode(message),
+      decodeBase64(signature),
+      decodeBase64(license.device_public_key),
+    );
+
+    if (!verified) {
+      return jsonResponse({ success: false, error: 'Invalid machine signature' }, 403);
+    }
+
+    const { error: updateError } = await supabase
+      .from('licenses')
+      .update({ machine_id: null, device_public_key: null })
+      .eq('id', license.id);
+
+    if (updateError) {
+      console.error('Error releasing license:', updateError);
+      return jsonResponse({ success: false, error: 'Failed to release license' }, 500);
+    }
+
+    return jsonResponse({ success: true }, 200);
+  } catch (error) {
+    console.error('Error:', error);
+    return jsonResponse({ success: false, error: 'Server error' }, 500);
+  }
+});
@@ -1,5 +1,40 @@
 import { serve } from 'https://deno.land/std@0.177.0/http/server.ts';
 import { createClient } from 'https://esm.sh/@supabase/supabase-js@2';
+import nacl from 'https://esm.sh/tweetnacl@1.0.3';
+import { decode as decodeBase64, encode as encodeBase64 } from 'https://deno.land/std@0.177.0/encoding/base64.ts';
+
+// Must match TOKEN_CONTEXT in src-tauri/src/license.rs
+const TOKEN_CONTEXT = 'monadier-desktop-license-v1';
+
+// How long a signed token stays valid offline before the app must re-validate
+const TOKEN_LIFETIME_DAYS = 30;
+
+interface LicenseToken {
+  code: string;
+  plan_tier: string;
+  machine_id: string;
+  issued_at: string;
+  expires_at: string;
+  signature: string;
+}
+
+// Sign the license fields with the Ed25519 key whose public half is embedded in the desktop app
+function signLicenseToken(fields: Omit<LicenseToken, 'signature'>): LicenseToken {
+  const seed = decodeBase64(Deno.env.get('LICENSE_SIGNING_KEY')!);
+  const { secretKey } = nacl.sign.keyPair.fromSeed(seed);
+
+  const message = [
+    TOKEN_CONTEXT,
+    fields.code,
+    fields.plan_tier,
+    fields.machine_id,
+    fields.issued_at,
+    fields.expires_at,
+  ].join('\n');
+
+  const signature = nacl.sign.detached(new TextEncoder().encode(message), secretKey);
+  return { ...fields, signature: encodeBase64(signature) };
+}
 
 const corsHeaders = {
   'Access-Control-Allow-Origin': '*',
@@ -18,7 +53,7 @@ serve(async (req) => {
 
     const supabase = createClient(supabaseUrl, supabaseServiceKey);
 
-    const { licenseCode, machineId } = await req.json();
+    const { licenseCode, machineId, devicePublicKey } = await req.json();
 
     if (!licenseCode) {
       return new Response(
@@ -54,7 +89,7 @@ serve(async (req) => {
       return new Response(
         JSON.stringify({
           valid: false,
-          error: 'License already activated on another machine. Contact support to transfer.'
+          error: 'License already activated on another machine. Deactivate it there first or contact support.'
         }),
         { status: 200, headers: { ...corsHeaders, 'Content-Type': 'application/json' } }
       );
@@ -68,6 +103,7 @@ serve(async (req) => {
           is_active: true,
           activated_at: new Date().toISOString(),
           machine_id: machineId,
+          device_public_key: devicePublicKey ?? null,
         })
         .eq('id', license.id);
 
@@ -80,12 +116,31 @@ serve(async (req) => {
       }
     }
 
-    // License is valid
+    // Installs activated before device keys existed register theirs on the next check
+    if (license.machine_id && !license.device_public_key && devicePublicKey) {
+      await supabase
+        .from('licenses')
+        .update({ device_public_key: devicePublicKey })
+        .eq('id', license.id);
+    }
+
+    // License is valid, issue a signed token bound to this machine
+    const issuedAt = new Date();
+    const expiresAt = new Date(issuedAt.getTime() + TOKEN_LIFETIME_DAYS * 24 * 60 * 60 * 1000);
+    const token = signLicenseToken({
+      code: license.code,
+      plan_tier: license.plan_tier,
+      machine_id: machineId,
+      issued_at: issuedAt.toISOString(),
+      expires_at: expiresAt.toISOString(),
+    });
+
     return new Response(
       JSON.stringify({
         valid: true,
         plan_tier: license.plan_tier,
         activated_at: license.activated_at,
+        token,
       }),
       { status: 200, headers: { ...corsHeaders, 'Content-Type': 'application/json' } }
     );
@@ -0,0 +1,3 @@
+-- Per-install Ed25519 public key registered by the desktop app on activation.
+-- Deactivation requests must be signed with the matching private key.
+ALTER TABLE licenses ADD COLUMN IF NOT EXISTS device_public_key TEXT;